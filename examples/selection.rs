@@ -3,7 +3,7 @@ extern crate smithay_client_toolkit as sctk;
 use std::io::{Read, Write};
 
 use sctk::{
-    data_device::DataSourceEvent,
+    data_device::{DataSourceEvent, MimeTypes},
     environment::Environment,
     primary_selection::PrimarySelectionSourceEvent,
     seat::keyboard::{map_keyboard_repeat, Event as KbEvent, KeyState, RepeatKind},
@@ -280,7 +280,7 @@ fn process_keyboard_event(
 
         if text == "c" && state == KeyState::Pressed {
             let data_source =
-                env.new_data_source(vec!["text/plain;charset=utf-8".into()], move |event, _| {
+                env.new_data_source(MimeTypes::new().text().into(), move |event, _| {
                     if let DataSourceEvent::Send { mut pipe, .. } = event {
                         let contents = "Hello from clipboard";
                         println!("Setting clipboard to: {}", &contents);
@@ -297,7 +297,7 @@ fn process_keyboard_event(
 
         if text == "C" && state == KeyState::Pressed {
             let data_source = env.new_primary_selection_source(
-                vec!["text/plain;charset=utf-8".into()],
+                MimeTypes::new().text().into(),
                 move |event, _| {
                     if let PrimarySelectionSourceEvent::Send { mut pipe, .. } = event {
                         let contents = "Hello from primary selection";
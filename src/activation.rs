@@ -0,0 +1,151 @@
+//! Helpers for the `xdg_activation_v1` protocol
+//!
+//! This protocol lets a client hand over the keyboard focus to another application, for example
+//! when it launches it. The launching client requests an activation token from the compositor,
+//! and passes it to the launched application through the `XDG_ACTIVATION_TOKEN` environment
+//! variable, which will then use it to request its activation with [`activate`](fn.activate.html).
+//!
+//! The `xdg_activation_v1` global is not part of the
+//! [`default_environment!`](../macro.default_environment.html), you can add it to your
+//! environment as a [`SimpleGlobal`](../environment/struct.SimpleGlobal.html).
+
+use std::env;
+use std::io;
+use std::process::{Child, Command};
+
+use wayland_client::{
+    protocol::{wl_seat, wl_surface},
+    Attached,
+};
+
+use wayland_protocols::staging::xdg_activation::v1::client::xdg_activation_token_v1;
+pub use wayland_protocols::staging::xdg_activation::v1::client::xdg_activation_v1::XdgActivationV1;
+
+use crate::environment::{Environment, GlobalHandler};
+use crate::{MissingGlobal, PendingResponse};
+
+/// The environment variable used to pass an activation token to a launched application
+pub const ACTIVATION_TOKEN_ENV: &str = "XDG_ACTIVATION_TOKEN";
+
+/// Request an activation token from the compositor
+///
+/// The `seat` and `serial` should be the ones of the user input that triggered the request,
+/// like a click on a launcher button, and `surface` the surface that received this input.
+/// The compositor is likely to only grant focus to the launched application if they are
+/// provided. The `app_id` identifies the application about to be launched, if known.
+///
+/// The token is given by the returned response once the compositor has sent it.
+pub fn request_token(
+    activation: &Attached<XdgActivationV1>,
+    seat: Option<(&wl_seat::WlSeat, u32)>,
+    surface: Option<&wl_surface::WlSurface>,
+    app_id: Option<String>,
+) -> PendingResponse<String> {
+    let (response, responder) = PendingResponse::new();
+    let token = activation.get_activation_token();
+    let mut responder = Some(responder);
+    token.quick_assign(move |token, event, ddata| {
+        if let xdg_activation_token_v1::Event::Done { token: value } = event {
+            token.destroy();
+            if let Some(responder) = responder.take() {
+                responder.respond(value, ddata);
+            }
+        }
+    });
+    if let Some((seat, serial)) = seat {
+        token.set_serial(serial, seat);
+    }
+    if let Some(surface) = surface {
+        token.set_surface(surface);
+    }
+    if let Some(app_id) = app_id {
+        token.set_app_id(app_id);
+    }
+    token.commit();
+    response
+}
+
+/// Prepare a command to be launched with an activation token
+///
+/// This sets the `XDG_ACTIVATION_TOKEN` environment variable of the command, use it if you
+/// need to spawn it yourself rather than through [`spawn_with_token`](fn.spawn_with_token.html).
+pub fn prepare_command(command: &mut Command, token: &str) {
+    command.env(ACTIVATION_TOKEN_ENV, token);
+}
+
+/// Spawn a command, granting it an activation token
+///
+/// This requests an activation token (see [`request_token`](fn.request_token.html) for the
+/// meaning of `seat` and `surface`), and spawns the command with the token in its environment
+/// once it is received.
+///
+/// The returned response then gives the result of spawning the command.
+pub fn spawn_with_token(
+    activation: &Attached<XdgActivationV1>,
+    mut command: Command,
+    seat: Option<(&wl_seat::WlSeat, u32)>,
+    surface: Option<&wl_surface::WlSurface>,
+) -> PendingResponse<io::Result<Child>> {
+    let (response, responder) = PendingResponse::new();
+    request_token(activation, seat, surface, None).on_response(move |token, ddata| {
+        prepare_command(&mut command, &token);
+        responder.respond(command.spawn(), ddata);
+    });
+    response
+}
+
+/// Request the activation of a surface
+///
+/// The `token` is one received from the compositor with
+/// [`request_token`](fn.request_token.html), or one given to this application when it was
+/// launched, see [`take_token_from_env`](fn.take_token_from_env.html). The compositor decides
+/// whether to grant the focus to the surface, for example an invalid token may only make it
+/// mark the surface as urgent.
+pub fn activate(
+    activation: &Attached<XdgActivationV1>,
+    surface: &wl_surface::WlSurface,
+    token: String,
+) {
+    activation.activate(token, surface);
+}
+
+/// Get the activation token this application was launched with, if any
+///
+/// This reads the `XDG_ACTIVATION_TOKEN` environment variable, and removes it so that it is
+/// not inherited by the processes this application launches. Use the token to activate your
+/// first window once it is mapped.
+pub fn take_token_from_env() -> Option<String> {
+    let token = env::var(ACTIVATION_TOKEN_ENV).ok()?;
+    env::remove_var(ACTIVATION_TOKEN_ENV);
+    Some(token)
+}
+
+impl<E: GlobalHandler<XdgActivationV1>> Environment<E> {
+    /// Request an activation token from the compositor
+    ///
+    /// See [`activation::request_token`](../activation/fn.request_token.html). Returns an
+    /// error if the compositor does not support `xdg_activation_v1`.
+    pub fn request_activation_token(
+        &self,
+        seat: Option<(&wl_seat::WlSeat, u32)>,
+        surface: Option<&wl_surface::WlSurface>,
+        app_id: Option<String>,
+    ) -> Result<PendingResponse<String>, MissingGlobal> {
+        let activation = self.get_global::<XdgActivationV1>().ok_or(MissingGlobal)?;
+        Ok(request_token(&activation, seat, surface, app_id))
+    }
+
+    /// Request the activation of a surface with a token
+    ///
+    /// See [`activation::activate`](../activation/fn.activate.html). Returns an error if the
+    /// compositor does not support `xdg_activation_v1`.
+    pub fn activate_surface(
+        &self,
+        surface: &wl_surface::WlSurface,
+        token: String,
+    ) -> Result<(), MissingGlobal> {
+        let activation = self.get_global::<XdgActivationV1>().ok_or(MissingGlobal)?;
+        activate(&activation, surface, token);
+        Ok(())
+    }
+}
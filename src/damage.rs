@@ -0,0 +1,294 @@
+use wayland_client::protocol::{wl_buffer, wl_output, wl_surface};
+
+/// A damaged rectangle, as `(x, y, width, height)` in buffer pixels
+pub type DamageRect = (i32, i32, i32, i32);
+
+fn area(r: DamageRect) -> i64 {
+    i64::from(r.2) * i64::from(r.3)
+}
+
+fn union(a: DamageRect, b: DamageRect) -> DamageRect {
+    let (x, y) = (a.0.min(b.0), a.1.min(b.1));
+    let (right, bottom) = ((a.0 + a.2).max(b.0 + b.2), (a.1 + a.3).max(b.1 + b.3));
+    (x, y, right - x, bottom - y)
+}
+
+/// Simplify a list of damaged rectangles
+///
+/// Rectangles are clipped to a buffer of the given size, and the empty ones are dropped.
+/// Rectangles of a same row or column that touch or overlap are merged, as are the ones
+/// contained in another. If there are still more than `max_rects` rectangles, the ones whose
+/// merging damages the fewest extra pixels are merged until there are `max_rects` left.
+///
+/// This is useful for applications that render in tiles, like the cells of a terminal, to
+/// avoid sending hundreds of damage requests when many tiles changed.
+pub fn merge_damage(
+    damage: &[DamageRect],
+    buffer_size: (i32, i32),
+    max_rects: usize,
+) -> Vec<DamageRect> {
+    let (width, height) = buffer_size;
+    let mut rects: Vec<DamageRect> = damage
+        .iter()
+        .filter_map(|&(x, y, w, h)| {
+            let (x0, y0) = (x.max(0), y.max(0));
+            let (x1, y1) = (x.saturating_add(w).min(width), y.saturating_add(h).min(height));
+            if x1 > x0 && y1 > y0 {
+                Some((x0, y0, x1 - x0, y1 - y0))
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    // drop the rectangles contained in another one
+    let mut i = 0;
+    while i < rects.len() {
+        let r = rects[i];
+        let contained = rects.iter().enumerate().any(|(j, &o)| {
+            j != i
+                && o.0 <= r.0
+                && o.1 <= r.1
+                && o.0 + o.2 >= r.0 + r.2
+                && o.1 + o.3 >= r.1 + r.3
+                && (o != r || j < i)
+        });
+        if contained {
+            rects.remove(i);
+        } else {
+            i += 1;
+        }
+    }
+
+    // merge the rectangles of a row, then of a column, when it does not damage more pixels
+    rects.sort_by_key(|r| (r.1, r.3, r.0));
+    rects.dedup_by(|b, a| {
+        if a.1 == b.1 && a.3 == b.3 && b.0 <= a.0 + a.2 {
+            *a = union(*a, *b);
+            true
+        } else {
+            false
+        }
+    });
+    rects.sort_by_key(|r| (r.0, r.2, r.1));
+    rects.dedup_by(|b, a| {
+        if a.0 == b.0 && a.2 == b.2 && b.1 <= a.1 + a.3 {
+            *a = union(*a, *b);
+            true
+        } else {
+            false
+        }
+    });
+
+    // merge the neighbours wasting the fewest pixels until there are few enough rectangles
+    let max_rects = max_rects.max(1);
+    rects.sort_by_key(|r| (r.1, r.0));
+    while rects.len() > max_rects {
+        let (idx, _) = rects
+            .windows(2)
+            .map(|w| area(union(w[0], w[1])) - area(w[0]) - area(w[1]))
+            .enumerate()
+            .min_by_key(|&(_, waste)| waste)
+            .unwrap();
+        rects[idx] = union(rects[idx], rects[idx + 1]);
+        rects.remove(idx + 1);
+    }
+    rects
+}
+
+/// Attach a buffer to a surface and damage the changed parts of it
+///
+/// The damage is simplified with [`merge_damage`](fn.merge_damage.html), so that at most
+/// `max_rects` damage requests are sent. They use `wl_surface.damage_buffer` when the surface
+/// supports it, and are otherwise converted to surface coordinates using the buffer `scale`.
+///
+/// The surface is not committed, so that you can set other state before doing it. Returns the
+/// number of damage requests sent.
+pub fn attach_with_damage(
+    surface: &wl_surface::WlSurface,
+    buffer: &wl_buffer::WlBuffer,
+    buffer_size: (i32, i32),
+    scale: i32,
+    damage: &[DamageRect],
+    max_rects: usize,
+) -> usize {
+    let rects = merge_damage(damage, buffer_size, max_rects);
+    surface.attach(Some(buffer), 0, 0);
+    send_damage(surface, &rects, scale, wl_output::Transform::Normal);
+    rects.len()
+}
+
+// Damage rectangles in buffer coordinates, converting them if the surface is too old
+fn send_damage(
+    surface: &wl_surface::WlSurface,
+    rects: &[DamageRect],
+    scale: i32,
+    transform: wl_output::Transform,
+) {
+    let scale = scale.max(1);
+    if surface.as_ref().version() < 4 && transform != wl_output::Transform::Normal {
+        // converting the rectangles needs the size of the surface, damage all of it
+        if !rects.is_empty() {
+            surface.damage(0, 0, std::i32::MAX, std::i32::MAX);
+        }
+        return;
+    }
+    for &(x, y, w, h) in rects {
+        if surface.as_ref().version() >= 4 {
+            surface.damage_buffer(x, y, w, h);
+        } else {
+            // round outwards, to damage all the surface units covering the rectangle
+            let (x0, y0) = (x / scale, y / scale);
+            let (x1, y1) = ((x + w + scale - 1) / scale, (y + h + scale - 1) / scale);
+            surface.damage(x0, y0, x1 - x0, y1 - y0);
+        }
+    }
+}
+
+/// A wrapper of a surface sequencing the updates of its content
+///
+/// Updating the content of a surface requires sending several requests in the right order:
+/// the buffer scale and transform, the buffer, its damage, and finally the commit. This
+/// wrapper accumulates these changes and sends them all on [`commit`](#method.commit),
+/// using `wl_surface.damage_buffer` when the surface supports it.
+///
+/// Other state of the surface, like its input region, can still be set through
+/// [`surface`](#method.surface) before committing.
+#[derive(Debug)]
+pub struct SurfaceUpdater {
+    surface: wl_surface::WlSurface,
+    scale: i32,
+    transform: wl_output::Transform,
+    pending_scale: Option<i32>,
+    pending_transform: Option<wl_output::Transform>,
+    pending_buffer: Option<(wl_buffer::WlBuffer, (i32, i32))>,
+    buffer_size: (i32, i32),
+    damage: Vec<DamageRect>,
+    max_rects: usize,
+}
+
+impl SurfaceUpdater {
+    /// Wrap a surface
+    ///
+    /// The surface is assumed to have the default buffer scale and transform.
+    pub fn new(surface: wl_surface::WlSurface) -> SurfaceUpdater {
+        SurfaceUpdater {
+            surface,
+            scale: 1,
+            transform: wl_output::Transform::Normal,
+            pending_scale: None,
+            pending_transform: None,
+            pending_buffer: None,
+            buffer_size: (0, 0),
+            damage: Vec::new(),
+            max_rects: 16,
+        }
+    }
+
+    /// The wrapped surface
+    pub fn surface(&self) -> &wl_surface::WlSurface {
+        &self.surface
+    }
+
+    /// Set the maximum number of damage requests sent on each commit
+    ///
+    /// The damage is simplified with [`merge_damage`](fn.merge_damage.html) to fit it. The
+    /// default is 16.
+    pub fn set_max_rects(&mut self, max_rects: usize) {
+        self.max_rects = max_rects;
+    }
+
+    /// Set the scale of the next buffers
+    pub fn set_buffer_scale(&mut self, scale: i32) {
+        self.pending_scale = Some(scale);
+    }
+
+    /// Set the transform of the next buffers
+    ///
+    /// This is ignored if the surface does not support buffer transforms.
+    pub fn set_buffer_transform(&mut self, transform: wl_output::Transform) {
+        if self.surface.as_ref().version() >= 2 {
+            self.pending_transform = Some(transform);
+        }
+    }
+
+    /// Attach a new buffer, with the damaged parts of it
+    ///
+    /// `damage` is in buffer pixels, it is added to the damage declared since the last commit.
+    pub fn attach_and_damage(
+        &mut self,
+        buffer: &wl_buffer::WlBuffer,
+        buffer_size: (i32, i32),
+        damage: &[DamageRect],
+    ) {
+        self.pending_buffer = Some((buffer.clone(), buffer_size));
+        self.damage.extend_from_slice(damage);
+    }
+
+    /// Declare a part of the current buffer as damaged, in buffer pixels
+    pub fn damage(&mut self, rect: DamageRect) {
+        self.damage.push(rect);
+    }
+
+    /// Declare the whole buffer as damaged
+    pub fn damage_all(&mut self) {
+        self.damage.push((0, 0, std::i32::MAX, std::i32::MAX));
+    }
+
+    /// Send the pending changes and commit the surface
+    pub fn commit(&mut self) {
+        if let Some(scale) = self.pending_scale.take().filter(|&s| s != self.scale) {
+            self.surface.set_buffer_scale(scale);
+            self.scale = scale;
+        }
+        if let Some(transform) = self.pending_transform.take().filter(|&t| t != self.transform) {
+            self.surface.set_buffer_transform(transform);
+            self.transform = transform;
+        }
+        if let Some((buffer, size)) = self.pending_buffer.take() {
+            self.surface.attach(Some(&buffer), 0, 0);
+            self.buffer_size = size;
+        }
+        let rects = merge_damage(&self.damage, self.buffer_size, self.max_rects);
+        send_damage(&self.surface, &rects, self.scale, self.transform);
+        self.damage.clear();
+        self.surface.commit();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_damage_clips_to_the_buffer() {
+        let damage = [
+            (-5, -5, 10, 10),
+            (95, 0, 10, 10),
+            (200, 200, 5, 5),
+            (0, 0, 0, 5),
+            (std::i32::MAX - 1, 0, 10, 10),
+        ];
+        assert_eq!(merge_damage(&damage, (100, 100), 16), vec![(0, 0, 5, 5), (95, 0, 5, 10)]);
+    }
+
+    #[test]
+    fn merge_damage_drops_contained_rects() {
+        let damage = [(0, 0, 50, 50), (10, 10, 5, 5), (0, 0, 50, 50)];
+        assert_eq!(merge_damage(&damage, (100, 100), 16), vec![(0, 0, 50, 50)]);
+    }
+
+    #[test]
+    fn merge_damage_merges_touching_rects() {
+        let damage = [(0, 0, 10, 10), (10, 0, 10, 10), (30, 0, 10, 10), (30, 10, 10, 5)];
+        assert_eq!(merge_damage(&damage, (100, 100), 16), vec![(0, 0, 20, 10), (30, 0, 10, 15)]);
+    }
+
+    #[test]
+    fn merge_damage_respects_max_rects() {
+        let damage = [(0, 0, 10, 10), (20, 0, 10, 10), (0, 50, 10, 10)];
+        // the two rects of the first row waste the fewest pixels when merged
+        assert_eq!(merge_damage(&damage, (100, 100), 2), vec![(0, 0, 30, 10), (0, 50, 10, 10)]);
+        assert_eq!(merge_damage(&damage, (100, 100), 0), vec![(0, 0, 30, 60)]);
+    }
+}
@@ -3,13 +3,21 @@ use wayland_client::{
     DispatchData, Main,
 };
 
-use std::sync::{Arc, Mutex};
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex,
+};
 
 use super::{DataOffer, DataSource, DndAction};
+use crate::InternalErrorKind;
 
 #[derive(Debug)]
 struct Inner {
-    selection: Option<DataOffer>,
+    // shared so that the callback can receive it without holding the lock
+    selection: Option<Arc<DataOffer>>,
+    selection_age: u64,
+    // counts the selection events received by the devices sharing it, to find the most recent one
+    selection_counter: Arc<AtomicU64>,
     current_dnd: Option<DataOffer>,
     known_offers: Vec<DataOffer>,
 }
@@ -20,11 +28,18 @@ impl Inner {
     }
 
     fn set_selection(&mut self, offer: Option<wl_data_offer::WlDataOffer>) {
+        self.selection_age = self.selection_counter.fetch_add(1, Ordering::Relaxed) + 1;
         if let Some(offer) = offer {
             if let Some(id) = self.known_offers.iter().position(|o| o.offer == offer) {
-                self.selection = Some(self.known_offers.swap_remove(id));
+                let offer = self.known_offers.swap_remove(id);
+                offer.set_selection();
+                self.selection = Some(Arc::new(offer));
             } else {
-                panic!("Compositor set an unknown data_offer for selection.");
+                crate::error_policy::report(
+                    InternalErrorKind::UnexpectedEvent,
+                    "Compositor set an unknown data_offer for selection.".into(),
+                );
+                self.selection = None;
             }
         } else {
             // drop the current offer if any
@@ -32,12 +47,25 @@ impl Inner {
         }
     }
 
-    fn set_dnd(&mut self, offer: Option<wl_data_offer::WlDataOffer>) {
+    fn set_dnd(
+        &mut self,
+        offer: Option<wl_data_offer::WlDataOffer>,
+        serial: u32,
+        surface: &wl_surface::WlSurface,
+        x: f64,
+        y: f64,
+    ) {
         if let Some(offer) = offer {
             if let Some(id) = self.known_offers.iter().position(|o| o.offer == offer) {
-                self.current_dnd = Some(self.known_offers.swap_remove(id));
+                let offer = self.known_offers.swap_remove(id);
+                offer.set_dnd(serial, surface.clone(), x, y);
+                self.current_dnd = Some(offer);
             } else {
-                panic!("Compositor set an unknown data_offer for selection.");
+                crate::error_policy::report(
+                    InternalErrorKind::UnexpectedEvent,
+                    "Compositor set an unknown data_offer for drag'n'drop.".into(),
+                );
+                self.current_dnd = None;
             }
         } else {
             // drop the current offer if any
@@ -59,6 +87,7 @@ pub struct DataDevice {
 
 /// Possible events generated during a drag'n'drop session
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum DndEvent<'a> {
     /// A new drag'n'drop entered your surfaces
     Enter {
@@ -100,11 +129,22 @@ pub enum DndEvent<'a> {
         /// no source. See `DataDevice::start_drag` for details.
         offer: Option<&'a DataOffer>,
     },
+    /// The selection (clipboard) of the seat changed
+    ///
+    /// This is not a drag'n'drop event, but is sent through the same callback. It is
+    /// received when your application gains keyboard focus, and whenever the selection
+    /// changes while it has focus.
+    Selection {
+        /// The offer of the new selection
+        ///
+        /// Is None if the selection was cleared.
+        offer: Option<&'a DataOffer>,
+    },
 }
 
 fn data_device_implem<F>(
     event: wl_data_device::Event,
-    inner: &mut Inner,
+    inner: &Mutex<Inner>,
     implem: &mut F,
     ddata: DispatchData,
 ) where
@@ -112,23 +152,45 @@ fn data_device_implem<F>(
 {
     use self::wl_data_device::Event;
 
+    if let Event::Selection { id } = event {
+        // the lock is released before invoking the callback, as it may access the selection
+        let selection = {
+            let mut inner = inner.lock().unwrap();
+            inner.set_selection(id);
+            inner.selection.clone()
+        };
+        implem(DndEvent::Selection { offer: selection.as_deref() }, ddata);
+        return;
+    }
+
+    let mut inner = inner.lock().unwrap();
     match event {
         Event::DataOffer { id } => inner.new_offer(id),
         Event::Enter { serial, surface, x, y, id } => {
-            inner.set_dnd(id);
+            inner.set_dnd(id, serial, &surface, x, y);
             implem(
                 DndEvent::Enter { serial, surface, x, y, offer: inner.current_dnd.as_ref() },
                 ddata,
             );
         }
         Event::Motion { time, x, y } => {
+            if let Some(ref offer) = inner.current_dnd {
+                offer.set_dnd_position(x, y);
+            }
             implem(DndEvent::Motion { x, y, time, offer: inner.current_dnd.as_ref() }, ddata);
         }
-        Event::Leave => implem(DndEvent::Leave, ddata),
+        Event::Leave => {
+            if let Some(ref offer) = inner.current_dnd {
+                offer.set_dnd_left();
+            }
+            implem(DndEvent::Leave, ddata)
+        }
         Event::Drop => {
+            if let Some(ref offer) = inner.current_dnd {
+                offer.set_dropped();
+            }
             implem(DndEvent::Drop { offer: inner.current_dnd.as_ref() }, ddata);
         }
-        Event::Selection { id } => inner.set_selection(id),
         _ => unreachable!(),
     }
 }
@@ -141,6 +203,19 @@ impl DataDevice {
     pub fn init_for_seat<F>(
         manager: &wl_data_device_manager::WlDataDeviceManager,
         seat: &wl_seat::WlSeat,
+        callback: F,
+    ) -> DataDevice
+    where
+        for<'a> F: FnMut(DndEvent<'a>, DispatchData) + 'static,
+    {
+        DataDevice::init_with_counter(manager, seat, Arc::new(AtomicU64::new(0)), callback)
+    }
+
+    // The selection ages of devices created with the same counter can be compared
+    pub(crate) fn init_with_counter<F>(
+        manager: &wl_data_device_manager::WlDataDeviceManager,
+        seat: &wl_seat::WlSeat,
+        selection_counter: Arc<AtomicU64>,
         mut callback: F,
     ) -> DataDevice
     where
@@ -148,6 +223,8 @@ impl DataDevice {
     {
         let inner = Arc::new(Mutex::new(Inner {
             selection: None,
+            selection_age: 0,
+            selection_counter,
             current_dnd: None,
             known_offers: Vec::new(),
         }));
@@ -155,8 +232,7 @@ impl DataDevice {
         let inner2 = inner.clone();
         let device = manager.get_data_device(seat);
         device.quick_assign(move |_, evt, ddata| {
-            let mut inner = inner2.lock().unwrap();
-            data_device_implem(evt, &mut *inner, &mut callback, ddata);
+            data_device_implem(evt, &inner2, &mut callback, ddata);
         });
 
         DataDevice { device: device.detach(), inner }
@@ -207,7 +283,12 @@ impl DataDevice {
         F: FnOnce(Option<&DataOffer>) -> T,
     {
         let inner = self.inner.lock().unwrap();
-        f(inner.selection.as_ref())
+        f(inner.selection.as_deref())
+    }
+
+    // How recent the last selection event of this device is, compared to the other devices
+    pub(crate) fn selection_age(&self) -> u64 {
+        self.inner.lock().unwrap().selection_age
     }
 }
 
@@ -0,0 +1,229 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static MAX_COUNT: AtomicUsize = AtomicUsize::new(64);
+static MAX_LEN: AtomicUsize = AtomicUsize::new(256);
+
+/// Limits on the mime types stored for an offer
+///
+/// A buggy or malicious peer could announce thousands of mime types, or very long ones.
+/// Offers only keep the mime types within these limits, see
+/// [`set_mime_limits`](fn.set_mime_limits.html).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MimeLimits {
+    /// Maximum number of mime types stored for an offer
+    ///
+    /// Defaults to 64.
+    pub max_count: usize,
+    /// Maximum length of a mime type, in bytes
+    ///
+    /// Defaults to 256.
+    pub max_len: usize,
+}
+
+impl Default for MimeLimits {
+    fn default() -> MimeLimits {
+        MimeLimits { max_count: 64, max_len: 256 }
+    }
+}
+
+/// Set the limits on the mime types stored for data and primary selection offers
+///
+/// This affects the offers announced after this call.
+pub fn set_mime_limits(limits: MimeLimits) {
+    MAX_COUNT.store(limits.max_count, Ordering::Relaxed);
+    MAX_LEN.store(limits.max_len, Ordering::Relaxed);
+}
+
+/// Get the current limits on the mime types stored for offers
+pub fn mime_limits() -> MimeLimits {
+    MimeLimits {
+        max_count: MAX_COUNT.load(Ordering::Relaxed),
+        max_len: MAX_LEN.load(Ordering::Relaxed),
+    }
+}
+
+/// Statistics about the mime types an offer ignored
+///
+/// This can be used for diagnostics, a well-behaved peer never triggers any of these.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct MimeStats {
+    /// Mime types that were announced more than once
+    pub duplicates: usize,
+    /// Mime types that were empty, not valid UTF-8, contained control characters
+    /// or were too long
+    pub invalid: usize,
+    /// Mime types announced after the maximum count was reached
+    pub over_limit: usize,
+}
+
+/// The sanitized list of the mime types of an offer
+#[derive(Debug)]
+pub(crate) struct MimeList {
+    pub(crate) mime_types: Vec<String>,
+    pub(crate) stats: MimeStats,
+    limits: MimeLimits,
+}
+
+impl MimeList {
+    pub(crate) fn new() -> MimeList {
+        MimeList { mime_types: Vec::new(), stats: MimeStats::default(), limits: mime_limits() }
+    }
+
+    pub(crate) fn push(&mut self, mime_type: String) {
+        // invalid UTF-8 is replaced with U+FFFD when the string is received
+        let valid = !mime_type.is_empty()
+            && mime_type.len() <= self.limits.max_len
+            && !mime_type.chars().any(|c| c.is_control() || c == '\u{FFFD}');
+        if !valid {
+            self.stats.invalid += 1;
+        } else if self.mime_types.contains(&mime_type) {
+            self.stats.duplicates += 1;
+        } else if self.mime_types.len() >= self.limits.max_count {
+            self.stats.over_limit += 1;
+        } else {
+            self.mime_types.push(mime_type);
+            return;
+        }
+        log::debug!(
+            "[SCTK] Ignoring a mime type of {} bytes offered by the peer.",
+            mime_type.len()
+        );
+    }
+}
+
+// The mime types of UTF-8 text, the first one being canonical
+const TEXT_MIME_TYPES: &[&str] = &["text/plain;charset=utf-8", "UTF8_STRING", "text/plain"];
+// The mime types of Latin-1 text, which the ICCCM uses for the X11 `STRING` and `TEXT` targets
+const LATIN1_MIME_TYPES: &[&str] = &["text/plain;charset=iso-8859-1", "STRING", "TEXT"];
+const URI_LIST_MIME_TYPE: &str = "text/uri-list";
+
+/// A builder of the list of mime types offered by a data source
+///
+/// Many applications, in particular X11 ones going through Xwayland, only recognize some of
+/// the conventional names of a kind of content. This builder expands a kind of content to
+/// all of its names, so that pasting works everywhere:
+///
+/// ```no_run
+/// # use smithay_client_toolkit::data_device::MimeTypes;
+/// let mime_types = MimeTypes::new().text().files();
+/// # let _ = mime_types;
+/// ```
+///
+/// The result can be given to [`DataSource::new`](struct.DataSource.html#method.new) or
+/// [`PrimarySelectionSource::new`](../primary_selection/struct.PrimarySelectionSource.html#method.new),
+/// or converted into a `Vec<String>` for the methods of `Environment` creating sources.
+/// When the peer requests the content, use [`canonical_mime_type`](fn.canonical_mime_type.html)
+/// to know what to send.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MimeTypes {
+    mime_types: Vec<String>,
+}
+
+impl MimeTypes {
+    /// Create an empty list of mime types
+    pub fn new() -> MimeTypes {
+        MimeTypes { mime_types: Vec::new() }
+    }
+
+    /// Offer text
+    ///
+    /// This adds `text/plain;charset=utf-8`, `UTF8_STRING` and `text/plain`, whose content
+    /// should be the UTF-8 text, followed by `text/plain;charset=iso-8859-1`, `STRING` and
+    /// `TEXT`, whose content should be the text encoded with
+    /// [`encode_latin1`](fn.encode_latin1.html).
+    pub fn text(self) -> MimeTypes {
+        TEXT_MIME_TYPES.iter().chain(LATIN1_MIME_TYPES).fold(self, |list, mime| list.with(*mime))
+    }
+
+    /// Offer a list of files
+    ///
+    /// This adds `text/uri-list`, whose content is built by
+    /// [`encode_uri_list`](fn.encode_uri_list.html).
+    pub fn files(self) -> MimeTypes {
+        self.with(URI_LIST_MIME_TYPE)
+    }
+
+    /// Offer another mime type
+    ///
+    /// Mime types already in the list are not added again.
+    pub fn with<S: Into<String>>(mut self, mime_type: S) -> MimeTypes {
+        let mime_type = mime_type.into();
+        if !self.mime_types.contains(&mime_type) {
+            self.mime_types.push(mime_type);
+        }
+        self
+    }
+
+    /// The mime types of the list, in the order they were added
+    pub fn as_slice(&self) -> &[String] {
+        &self.mime_types
+    }
+}
+
+impl From<MimeTypes> for Vec<String> {
+    fn from(list: MimeTypes) -> Vec<String> {
+        list.mime_types
+    }
+}
+
+impl IntoIterator for MimeTypes {
+    type Item = String;
+    type IntoIter = std::vec::IntoIter<String>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.mime_types.into_iter()
+    }
+}
+
+/// The canonical mime type of a conventional name of some content
+///
+/// The aliases of text added by [`MimeTypes::text`](struct.MimeTypes.html#method.text) map
+/// to `text/plain;charset=utf-8` for UTF-8 text, or to `text/plain;charset=iso-8859-1` for
+/// Latin-1 text, and `text/uri-list` maps to itself. Returns `None` for other mime types.
+pub fn canonical_mime_type(mime_type: &str) -> Option<&'static str> {
+    if TEXT_MIME_TYPES.iter().any(|m| m.eq_ignore_ascii_case(mime_type)) {
+        Some(TEXT_MIME_TYPES[0])
+    } else if LATIN1_MIME_TYPES.iter().any(|m| m.eq_ignore_ascii_case(mime_type)) {
+        Some(LATIN1_MIME_TYPES[0])
+    } else if mime_type == URI_LIST_MIME_TYPE {
+        Some(URI_LIST_MIME_TYPE)
+    } else {
+        None
+    }
+}
+
+/// Encode text in Latin-1, for the `STRING` and `TEXT` mime types
+///
+/// The characters that Latin-1 can not represent are replaced with `?`.
+pub fn encode_latin1(text: &str) -> Vec<u8> {
+    text.chars().map(|c| if (c as u32) < 0x100 { c as u8 } else { b'?' }).collect()
+}
+
+/// Decode Latin-1 text, received for the `STRING` and `TEXT` mime types
+pub fn decode_latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| b as char).collect()
+}
+
+/// Encode a list of absolute paths as the content of `text/uri-list`
+///
+/// Each path is converted to a `file://` URI, with the bytes that are not allowed in URIs
+/// percent-encoded, and the URIs are separated by CRLF as required by the format.
+pub fn encode_uri_list<I, P>(paths: I) -> String
+where
+    I: IntoIterator<Item = P>,
+    P: AsRef<std::path::Path>,
+{
+    use std::os::unix::ffi::OsStrExt;
+    let mut list = String::new();
+    for path in paths {
+        list.push_str("file://");
+        for &byte in path.as_ref().as_os_str().as_bytes() {
+            if byte.is_ascii_alphanumeric() || b"/-._~".contains(&byte) {
+                list.push(byte as char);
+            } else {
+                list.push_str(&format!("%{:02X}", byte));
+            }
+        }
+        list.push_str("\r\n");
+    }
+    list
+}
@@ -1,9 +1,14 @@
 //! Helpers to handle data device related actions
 
-use std::{cell::RefCell, fmt, rc::Rc};
+use std::{
+    cell::{Cell, RefCell},
+    fmt,
+    rc::Rc,
+    sync::{atomic::AtomicU64, Arc},
+};
 
 use wayland_client::{
-    protocol::{wl_data_device_manager, wl_registry, wl_seat},
+    protocol::{wl_data_device_manager, wl_registry, wl_seat, wl_surface},
     Attached, DispatchData,
 };
 
@@ -12,20 +17,50 @@ pub use wayland_client::protocol::wl_data_device_manager::DndAction;
 use crate::MissingGlobal;
 
 mod device;
+mod mime;
 mod offer;
 mod source;
 
 pub use self::device::{DataDevice, DndEvent};
-pub use self::offer::{DataOffer, ReadPipe};
+pub(crate) use self::mime::MimeList;
+pub use self::mime::{
+    canonical_mime_type, decode_latin1, encode_latin1, encode_uri_list, mime_limits,
+    set_mime_limits, MimeLimits, MimeStats, MimeTypes,
+};
+pub use self::offer::{DataOffer, OfferError, ReadPipe};
+pub(crate) use self::source::CancelFlag;
 pub use self::source::{DataSource, DataSourceEvent, WritePipe};
+#[cfg(feature = "calloop")]
+pub use self::source::{PipeTransfer, TransferEvent};
 
 type DDCallback = dyn FnMut(wl_seat::WlSeat, DndEvent, DispatchData);
 
+/// How the clipboard behaves when there are several seats
+///
+/// Each seat has its own selection clipboard, this policy decides whether your application
+/// exposes them as is, or as a single logical clipboard.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ClipboardPolicy {
+    /// Each seat has its own clipboard
+    ///
+    /// This is the default.
+    PerSeat,
+    /// All seats share a single clipboard
+    ///
+    /// Setting the clipboard sets the selection of all seats, with a data source for each of
+    /// them, and reading it gives the most recent selection among all seats.
+    ///
+    /// Note that the compositor may refuse to set the selection of seats other than the one
+    /// the provided serial comes from.
+    Unified,
+}
+
 enum DDInner {
     Ready {
         mgr: Attached<wl_data_device_manager::WlDataDeviceManager>,
         devices: Vec<(wl_seat::WlSeat, DataDevice)>,
         callback: Rc<RefCell<Box<DDCallback>>>,
+        selection_counter: Arc<AtomicU64>,
     },
     Pending {
         seats: Vec<wl_seat::WlSeat>,
@@ -45,17 +80,23 @@ impl DDInner {
 
         let callback = Rc::new(RefCell::new(Box::new(|_, _: DndEvent, _: DispatchData| {})
             as Box<dyn FnMut(_, DndEvent, DispatchData)>));
+        let selection_counter = Arc::new(AtomicU64::new(0));
 
         for seat in seats {
             let cb = callback.clone();
             let my_seat = seat.clone();
-            let device = DataDevice::init_for_seat(&mgr, &seat, move |event, dispatch_data| {
-                (&mut *cb.borrow_mut())(my_seat.clone(), event, dispatch_data);
-            });
+            let device = DataDevice::init_with_counter(
+                &mgr,
+                &seat,
+                selection_counter.clone(),
+                move |event, dispatch_data| {
+                    (&mut *cb.borrow_mut())(my_seat.clone(), event, dispatch_data);
+                },
+            );
             devices.push((seat.clone(), device));
         }
 
-        *self = DDInner::Ready { mgr, devices, callback };
+        *self = DDInner::Ready { mgr, devices, callback, selection_counter };
     }
 
     // A potential new seat is seen
@@ -63,16 +104,21 @@ impl DDInner {
     // should do nothing if the seat is already known
     fn new_seat(&mut self, seat: &wl_seat::WlSeat) {
         match self {
-            DDInner::Ready { mgr, devices, callback } => {
+            DDInner::Ready { mgr, devices, callback, selection_counter } => {
                 if devices.iter().any(|(s, _)| s == seat) {
                     // the seat already exists, nothing to do
                     return;
                 }
                 let cb = callback.clone();
                 let my_seat = seat.clone();
-                let device = DataDevice::init_for_seat(mgr, seat, move |event, dispatch_data| {
-                    (&mut *cb.borrow_mut())(my_seat.clone(), event, dispatch_data);
-                });
+                let device = DataDevice::init_with_counter(
+                    mgr,
+                    seat,
+                    selection_counter.clone(),
+                    move |event, dispatch_data| {
+                        (&mut *cb.borrow_mut())(my_seat.clone(), event, dispatch_data);
+                    },
+                );
                 devices.push((seat.clone(), device));
             }
             DDInner::Pending { seats } => {
@@ -108,6 +154,44 @@ impl DDInner {
         }
     }
 
+    fn clipboard_seats(
+        &self,
+        policy: ClipboardPolicy,
+        seat: &wl_seat::WlSeat,
+    ) -> Result<Vec<wl_seat::WlSeat>, MissingGlobal> {
+        match (self, policy) {
+            (DDInner::Pending { .. }, _) => Err(MissingGlobal),
+            (DDInner::Ready { devices, .. }, ClipboardPolicy::Unified) if !devices.is_empty() => {
+                Ok(devices.iter().map(|(s, _)| s.clone()).collect())
+            }
+            (DDInner::Ready { devices, .. }, _) => {
+                if devices.iter().any(|(s, _)| s == seat) {
+                    Ok(vec![seat.clone()])
+                } else {
+                    Err(MissingGlobal)
+                }
+            }
+        }
+    }
+
+    fn with_clipboard<F: FnOnce(Option<&DataOffer>) -> T, T>(
+        &self,
+        policy: ClipboardPolicy,
+        seat: &wl_seat::WlSeat,
+        f: F,
+    ) -> Result<T, MissingGlobal> {
+        let device = match (self, policy) {
+            (DDInner::Pending { .. }, _) => return Err(MissingGlobal),
+            (DDInner::Ready { devices, .. }, ClipboardPolicy::PerSeat) => {
+                devices.iter().find(|(s, _)| s == seat).map(|(_, device)| device)
+            }
+            (DDInner::Ready { devices, .. }, ClipboardPolicy::Unified) => {
+                devices.iter().map(|(_, device)| device).max_by_key(|d| d.selection_age())
+            }
+        };
+        device.map(|device| device.with_selection(f)).ok_or(MissingGlobal)
+    }
+
     fn with_device<F: FnOnce(&DataDevice)>(
         &self,
         seat: &wl_seat::WlSeat,
@@ -151,6 +235,7 @@ impl fmt::Debug for DDInner {
 #[derive(Debug)]
 pub struct DataDeviceHandler {
     inner: Rc<RefCell<DDInner>>,
+    policy: Cell<ClipboardPolicy>,
     _listener: crate::seat::SeatListener,
 }
 
@@ -174,7 +259,21 @@ impl DataDeviceHandler {
             }
         });
 
-        DataDeviceHandler { inner, _listener: listener }
+        DataDeviceHandler {
+            inner,
+            policy: Cell::new(ClipboardPolicy::PerSeat),
+            _listener: listener,
+        }
+    }
+
+    /// Release the data devices of all seats
+    ///
+    /// This is intended to be used when exiting, see
+    /// [`Environment::shutdown`](../environment/struct.Environment.html#method.shutdown).
+    pub fn shutdown(&mut self) {
+        if let DDInner::Ready { ref mut devices, .. } = *self.inner.borrow_mut() {
+            devices.clear();
+        }
     }
 }
 
@@ -221,6 +320,52 @@ pub trait DataDeviceHandling {
         seat: &wl_seat::WlSeat,
         f: F,
     ) -> Result<(), MissingGlobal>;
+
+    /// Set how the clipboard behaves when there are several seats
+    ///
+    /// The default implementation only supports the `PerSeat` policy, and logs a warning
+    /// for the others. Delegate it to your
+    /// [`DataDeviceHandler`](struct.DataDeviceHandler.html) to support them.
+    fn set_clipboard_policy(&self, policy: ClipboardPolicy) {
+        if policy != ClipboardPolicy::PerSeat {
+            log::warn!(
+                "[SCTK] Ignoring the {:?} clipboard policy, set_clipboard_policy is not \
+                 delegated to a DataDeviceHandler.",
+                policy
+            );
+        }
+    }
+
+    /// The seats whose selection is set when setting the clipboard from a seat, following the
+    /// clipboard policy
+    ///
+    /// Returns an error if the seat is not found or if the `wl_data_device_manager` global
+    /// is missing.
+    ///
+    /// The default implementation only gives the given seat.
+    fn clipboard_seats(
+        &self,
+        seat: &wl_seat::WlSeat,
+    ) -> Result<Vec<wl_seat::WlSeat>, MissingGlobal> {
+        self.with_device(seat, |_| ())?;
+        Ok(vec![seat.clone()])
+    }
+
+    /// Access the clipboard selection, following the clipboard policy
+    ///
+    /// Returns an error if the seat is not found or if the `wl_data_device_manager` global
+    /// is missing.
+    ///
+    /// The default implementation gives the selection of the given seat.
+    fn with_clipboard<F: FnOnce(Option<&DataOffer>) -> T, T>(
+        &self,
+        seat: &wl_seat::WlSeat,
+        f: F,
+    ) -> Result<T, MissingGlobal> {
+        let mut result = None;
+        self.with_device(seat, |device| result = Some(device.with_selection(f)))?;
+        result.ok_or(MissingGlobal)
+    }
 }
 
 impl DataDeviceHandling for DataDeviceHandler {
@@ -238,6 +383,25 @@ impl DataDeviceHandling for DataDeviceHandler {
     ) -> Result<(), MissingGlobal> {
         self.inner.borrow().with_device(seat, f)
     }
+
+    fn set_clipboard_policy(&self, policy: ClipboardPolicy) {
+        self.policy.set(policy);
+    }
+
+    fn clipboard_seats(
+        &self,
+        seat: &wl_seat::WlSeat,
+    ) -> Result<Vec<wl_seat::WlSeat>, MissingGlobal> {
+        self.inner.borrow().clipboard_seats(self.policy.get(), seat)
+    }
+
+    fn with_clipboard<F: FnOnce(Option<&DataOffer>) -> T, T>(
+        &self,
+        seat: &wl_seat::WlSeat,
+        f: F,
+    ) -> Result<T, MissingGlobal> {
+        self.inner.borrow().with_clipboard(self.policy.get(), seat, f)
+    }
 }
 
 impl<E> crate::environment::Environment<E>
@@ -291,4 +455,78 @@ where
     ) -> Result<(), MissingGlobal> {
         self.with_inner(|inner| inner.with_device(seat, f))
     }
+
+    /// Start a drag'n'drop from a seat
+    ///
+    /// This forwards to [`DataDevice::start_drag`](../data_device/struct.DataDevice.html#method.start_drag)
+    /// on the data device of the seat, see it for the meaning of the arguments. The events
+    /// of the drag are delivered to the callback of the `source`.
+    ///
+    /// Returns an error if the seat is not found or if the `wl_data_device_manager` global
+    /// is missing.
+    pub fn start_drag(
+        &self,
+        seat: &wl_seat::WlSeat,
+        origin: &wl_surface::WlSurface,
+        source: Option<DataSource>,
+        actions: DndAction,
+        icon: Option<&wl_surface::WlSurface>,
+        serial: u32,
+    ) -> Result<(), MissingGlobal> {
+        self.with_data_device(seat, |device| {
+            device.start_drag(origin, source, actions, icon, serial)
+        })
+    }
+
+    /// Set how the clipboard behaves when there are several seats
+    ///
+    /// By default each seat has its own clipboard, see
+    /// [`ClipboardPolicy`](../data_device/enum.ClipboardPolicy.html) for details.
+    pub fn set_clipboard_policy(&self, policy: ClipboardPolicy) {
+        self.with_inner(|inner| inner.set_clipboard_policy(policy))
+    }
+
+    /// Set the clipboard selection
+    ///
+    /// With the `PerSeat` clipboard policy, this sets the selection of the given seat, with the
+    /// `Unified` policy this sets the selection of all seats.
+    ///
+    /// As a data source can only be used once, `source` is called to create the source of each
+    /// of these seats, for example with [`new_data_source`](#method.new_data_source). It can
+    /// return `None` to clear the selection.
+    ///
+    /// Returns an error if the seat is not found or if the `wl_data_device_manager` global
+    /// is missing.
+    pub fn set_clipboard<F>(
+        &self,
+        seat: &wl_seat::WlSeat,
+        mut source: F,
+        serial: u32,
+    ) -> Result<(), MissingGlobal>
+    where
+        F: FnMut(&wl_seat::WlSeat) -> Option<DataSource>,
+    {
+        // the sources are created outside of the environment, as creating them accesses it
+        let seats = self.with_inner(|inner| inner.clipboard_seats(seat))?;
+        for seat in seats {
+            let source = source(&seat);
+            self.with_data_device(&seat, |device| device.set_selection(&source, serial))?;
+        }
+        Ok(())
+    }
+
+    /// Access the clipboard selection
+    ///
+    /// With the `PerSeat` clipboard policy, this gives the selection of the given seat, with the
+    /// `Unified` policy the most recent selection among all seats.
+    ///
+    /// Returns an error if the seat is not found or if the `wl_data_device_manager` global
+    /// is missing.
+    pub fn with_clipboard<F: FnOnce(Option<&DataOffer>) -> T, T>(
+        &self,
+        seat: &wl_seat::WlSeat,
+        f: F,
+    ) -> Result<T, MissingGlobal> {
+        self.with_inner(|inner| inner.with_clipboard(seat, f))
+    }
 }
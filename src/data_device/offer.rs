@@ -1,19 +1,69 @@
 use std::{
-    fs, io,
+    fmt, fs, io,
     os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd},
     sync::{Arc, Mutex},
 };
 
 use wayland_client::protocol::wl_data_device_manager::DndAction;
-use wayland_client::protocol::wl_data_offer;
+use wayland_client::protocol::{wl_data_offer, wl_surface};
 use wayland_client::Main;
 
+use super::mime::{MimeList, MimeStats};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Kind {
+    // the offer was announced but not yet used
+    Unknown,
+    Selection,
+    Dnd { dropped: bool },
+}
+
 #[derive(Debug)]
 struct Inner {
-    mime_types: Vec<String>,
+    mime_types: MimeList,
     actions: DndAction,
     current_action: DndAction,
     serial: u32,
+    kind: Kind,
+    accepted: Option<String>,
+    finished: bool,
+    // surface and position of the drag'n'drop, while it is over one of our surfaces
+    dnd_location: Option<(wl_surface::WlSurface, f64, f64)>,
+}
+
+/// An illegal request on a data offer
+///
+/// Rather than sending requests that would trigger a protocol error, the methods
+/// of `DataOffer` check the state of the offer and return this error.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OfferError {
+    /// The offer was already finished
+    Finished,
+    /// This request is only valid for drag'n'drop offers
+    NotDragAndDrop,
+    /// The drag'n'drop offer has not been dropped yet
+    NotDropped,
+    /// No mime type was accepted or no action was negotiated
+    NothingAccepted,
+    /// The preferred action is not a single action among the supported ones
+    InvalidAction,
+    /// The request is not supported by the version of the `wl_data_device_manager` global
+    Unsupported,
+}
+
+impl std::error::Error for OfferError {}
+
+impl fmt::Display for OfferError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            OfferError::Finished => "the offer is finished",
+            OfferError::NotDragAndDrop => "not a drag'n'drop offer",
+            OfferError::NotDropped => "the drag'n'drop offer was not dropped",
+            OfferError::NothingAccepted => "no mime type or action was accepted",
+            OfferError::InvalidAction => "invalid preferred action",
+            OfferError::Unsupported => "request not supported by the compositor",
+        })
+    }
 }
 
 /// A data offer for receiving data though copy/paste or
@@ -27,10 +77,14 @@ pub struct DataOffer {
 impl DataOffer {
     pub(crate) fn new(offer: Main<wl_data_offer::WlDataOffer>) -> DataOffer {
         let inner = Arc::new(Mutex::new(Inner {
-            mime_types: Vec::new(),
+            mime_types: MimeList::new(),
             actions: DndAction::None,
             current_action: DndAction::None,
             serial: 0,
+            kind: Kind::Unknown,
+            accepted: None,
+            finished: false,
+            dnd_location: None,
         }));
         let inner2 = inner.clone();
         offer.quick_assign(move |_, event, _| {
@@ -54,12 +108,22 @@ impl DataOffer {
     }
 
     /// Access the list of mime types proposed by this offer
+    ///
+    /// Duplicated and invalid mime types are not part of this list, which is also
+    /// bounded, see [`MimeLimits`](struct.MimeLimits.html).
     pub fn with_mime_types<F, T>(&self, f: F) -> T
     where
         F: FnOnce(&[String]) -> T,
     {
         let inner = self.inner.lock().unwrap();
-        f(&inner.mime_types)
+        f(&inner.mime_types.mime_types)
+    }
+
+    /// Get statistics about the mime types of this offer that were ignored
+    ///
+    /// See [`MimeLimits`](struct.MimeLimits.html) for details.
+    pub fn mime_stats(&self) -> MimeStats {
+        self.inner.lock().unwrap().mime_types.stats
     }
 
     /// Get the list of available actions for this offer
@@ -72,10 +136,60 @@ impl DataOffer {
         self.inner.lock().unwrap().current_action
     }
 
+    pub(crate) fn set_selection(&self) {
+        self.inner.lock().unwrap().kind = Kind::Selection;
+    }
+
+    pub(crate) fn set_dnd(&self, serial: u32, surface: wl_surface::WlSurface, x: f64, y: f64) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.kind = Kind::Dnd { dropped: false };
+        inner.serial = serial;
+        inner.dnd_location = Some((surface, x, y));
+    }
+
+    pub(crate) fn set_dnd_position(&self, x: f64, y: f64) {
+        if let Some((_, ref mut ox, ref mut oy)) = self.inner.lock().unwrap().dnd_location {
+            *ox = x;
+            *oy = y;
+        }
+    }
+
+    pub(crate) fn set_dnd_left(&self) {
+        self.inner.lock().unwrap().dnd_location = None;
+    }
+
+    /// Get the surface and position of the drag'n'drop of this offer
+    ///
+    /// The position is in surface-local coordinates. Returns `None` if this is not a
+    /// drag'n'drop offer, or if the drag'n'drop is not over one of your surfaces anymore.
+    pub fn dnd_location(&self) -> Option<(wl_surface::WlSurface, f64, f64)> {
+        self.inner.lock().unwrap().dnd_location.clone()
+    }
+
+    /// Get the mime type last accepted with [`accept`](#method.accept), if any
+    pub fn accepted_mime_type(&self) -> Option<String> {
+        self.inner.lock().unwrap().accepted.clone()
+    }
+
+    pub(crate) fn set_dropped(&self) {
+        self.inner.lock().unwrap().kind = Kind::Dnd { dropped: true };
+    }
+
     /// Accept a mime type for receiving data through this offer
-    pub fn accept(&self, mime_type: Option<String>) {
-        let serial = self.inner.lock().unwrap().serial;
-        self.offer.accept(serial, mime_type);
+    ///
+    /// This is only meaningful for drag'n'drop offers. Use `None` to signal that none of
+    /// the mime types are accepted.
+    pub fn accept(&self, mime_type: Option<String>) -> Result<(), OfferError> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.finished {
+            return Err(OfferError::Finished);
+        }
+        if let Kind::Selection = inner.kind {
+            return Err(OfferError::NotDragAndDrop);
+        }
+        inner.accepted = mime_type.clone();
+        self.offer.accept(inner.serial, mime_type);
+        Ok(())
     }
 
     /// Request to receive the data of a given mime type
@@ -90,10 +204,14 @@ impl DataOffer {
     /// doing so.
     ///
     /// Fails if too many file descriptors were already open and a pipe
-    /// could not be created.
+    /// could not be created, or with an `OfferError` if the offer was
+    /// already finished.
     pub fn receive(&self, mime_type: String) -> std::io::Result<ReadPipe> {
         use nix::fcntl::OFlag;
         use nix::unistd::{close, pipe2};
+        if self.inner.lock().unwrap().finished {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, OfferError::Finished));
+        }
         // create a pipe
         let (readfd, writefd) = pipe2(OFlag::O_CLOEXEC)?;
 
@@ -109,24 +227,69 @@ impl DataOffer {
     /// Notify the send and compositor of the dnd actions you accept
     ///
     /// You need to provide the set of supported actions, as well as
-    /// a single preferred action.
-    pub fn set_actions(&self, supported: DndAction, preferred: DndAction) {
+    /// a single preferred action, which must be part of the supported ones
+    /// (or `DndAction::None`).
+    ///
+    /// This is only valid for drag'n'drop offers, and requires version 3 of
+    /// the `wl_data_device_manager` global.
+    pub fn set_actions(
+        &self,
+        supported: DndAction,
+        preferred: DndAction,
+    ) -> Result<(), OfferError> {
+        let inner = self.inner.lock().unwrap();
+        if inner.finished {
+            return Err(OfferError::Finished);
+        }
+        if let Kind::Selection = inner.kind {
+            return Err(OfferError::NotDragAndDrop);
+        }
+        if self.offer.as_ref().version() < 3 {
+            return Err(OfferError::Unsupported);
+        }
+        if !preferred.is_empty()
+            && (!preferred.bits().is_power_of_two() || !supported.contains(preferred))
+        {
+            return Err(OfferError::InvalidAction);
+        }
         self.offer.set_actions(supported, preferred);
+        Ok(())
     }
 
     /// Notify that you are finished with this offer, and will no longer
     /// be using it
     ///
-    /// Note that it is a protocol error to finish if no action or mime
-    /// type was accepted.
-    pub fn finish(&self) {
+    /// This is only valid for drag'n'drop offers that were dropped, once a mime type
+    /// was accepted and an action negotiated, and requires version 3 of the
+    /// `wl_data_device_manager` global.
+    pub fn finish(&self) -> Result<(), OfferError> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.finished {
+            return Err(OfferError::Finished);
+        }
+        match inner.kind {
+            Kind::Dnd { dropped: true } => {}
+            Kind::Dnd { dropped: false } => return Err(OfferError::NotDropped),
+            _ => return Err(OfferError::NotDragAndDrop),
+        }
+        if self.offer.as_ref().version() < 3 {
+            return Err(OfferError::Unsupported);
+        }
+        if inner.accepted.is_none() || inner.current_action.is_empty() {
+            return Err(OfferError::NothingAccepted);
+        }
+        inner.finished = true;
         self.offer.finish();
         self.offer.destroy();
+        Ok(())
     }
 }
 
 impl Drop for DataOffer {
     fn drop(&mut self) {
+        if self.inner.lock().unwrap().finished {
+            return;
+        }
         self.offer.destroy();
     }
 }
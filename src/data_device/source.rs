@@ -4,8 +4,49 @@ use wayland_client::{
 };
 
 use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+#[cfg(feature = "calloop")]
+use std::sync::{Mutex, Weak};
 use std::{fs, io};
 
+/// Whether a source was cancelled, shared with the pipes sent for it
+#[derive(Debug, Default)]
+pub(crate) struct CancelFlag {
+    cancelled: AtomicBool,
+    // wake up the transfers of the pipes, so that they stop even if the receiver stalled
+    #[cfg(feature = "calloop")]
+    wakers: Mutex<Vec<Weak<calloop::ping::Ping>>>,
+}
+
+impl CancelFlag {
+    pub(crate) fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Release);
+        #[cfg(feature = "calloop")]
+        for ping in self.wakers.lock().unwrap().drain(..).filter_map(|ping| ping.upgrade()) {
+            ping.ping();
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Acquire)
+    }
+
+    #[cfg(feature = "calloop")]
+    fn wake_on_cancel(&self, ping: &Arc<calloop::ping::Ping>) {
+        let mut wakers = self.wakers.lock().unwrap();
+        wakers.retain(|ping| ping.strong_count() > 0);
+        wakers.push(Arc::downgrade(ping));
+        drop(wakers);
+        // the source may have been cancelled before the ping was registered
+        if self.is_cancelled() {
+            ping.ping();
+        }
+    }
+}
+
 /// A data source for sending data though copy/paste or
 /// drag and drop
 #[derive(Debug)]
@@ -82,6 +123,7 @@ pub enum DataSourceEvent {
 fn data_source_impl<Impl>(
     evt: wl_data_source::Event,
     source: &wl_data_source::WlDataSource,
+    cancelled: &Arc<CancelFlag>,
     implem: &mut Impl,
     ddata: DispatchData,
 ) where
@@ -90,11 +132,13 @@ fn data_source_impl<Impl>(
     use self::wl_data_source::Event;
     let event = match evt {
         Event::Target { mime_type } => DataSourceEvent::Target { mime_type },
-        Event::Send { mime_type, fd } => {
-            DataSourceEvent::Send { mime_type, pipe: unsafe { FromRawFd::from_raw_fd(fd) } }
-        }
+        Event::Send { mime_type, fd } => DataSourceEvent::Send {
+            mime_type,
+            pipe: unsafe { WritePipe::with_cancel_flag(fd, cancelled.clone()) },
+        },
         Event::Action { dnd_action } => DataSourceEvent::Action { action: dnd_action },
         Event::Cancelled => {
+            cancelled.cancel();
             source.destroy();
             DataSourceEvent::Cancelled
         }
@@ -124,8 +168,9 @@ impl DataSource {
         It: IntoIterator<Item = S>,
     {
         let source = mgr.create_data_source();
+        let cancelled = Arc::new(CancelFlag::default());
         source.quick_assign(move |source, evt, dispatch_data| {
-            data_source_impl(evt, &source, &mut callback, dispatch_data)
+            data_source_impl(evt, &source, &cancelled, &mut callback, dispatch_data)
         });
 
         for mime in mime_types {
@@ -137,9 +182,67 @@ impl DataSource {
 }
 
 /// A file descriptor that can only be written to
+///
+/// Writing into it directly blocks if the receiving client is slow to read the data.
+/// If the `calloop` cargo feature is enabled, you can instead hand the data to
+/// [`WritePipe::transfer`](#method.transfer) and let your event loop write it in the
+/// background.
 #[derive(Debug)]
 pub struct WritePipe {
     file: fs::File,
+    cancelled: Arc<CancelFlag>,
+}
+
+impl WritePipe {
+    /// Create a pipe that is considered cancelled once `cancelled` is set
+    pub(crate) unsafe fn with_cancel_flag(fd: RawFd, cancelled: Arc<CancelFlag>) -> WritePipe {
+        WritePipe { file: FromRawFd::from_raw_fd(fd), cancelled }
+    }
+
+    /// Whether the source this pipe was sent for has been cancelled
+    ///
+    /// Once the source is cancelled, there is no point in writing more data into the pipe.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.is_cancelled()
+    }
+
+    /// Write the contents of a reader into this pipe without blocking
+    ///
+    /// The returned [`PipeTransfer`](struct.PipeTransfer.html) is a calloop `EventSource`
+    /// that needs to be inserted into your event loop. It then writes the data in chunks
+    /// whenever the pipe can accept more of it, so that a slow receiver does not block your
+    /// application. To send a byte slice you already have, wrap it in an `std::io::Cursor`.
+    ///
+    /// The transfer is aborted as soon as the source this pipe was sent for is cancelled, even
+    /// if the receiver stopped reading. Its callback is invoked once with the outcome of the
+    /// transfer, after which it removes itself from the event loop and closes the pipe.
+    ///
+    /// Fails if the pipe could not be made non-blocking, or if the wake up on cancellation
+    /// could not be set up.
+    #[cfg(feature = "calloop")]
+    pub fn transfer<R: io::Read + 'static>(self, reader: R) -> io::Result<PipeTransfer> {
+        use nix::fcntl::{fcntl, FcntlArg, OFlag};
+
+        let fd = self.file.as_raw_fd();
+        let flags = fcntl(fd, FcntlArg::F_GETFL)?;
+        fcntl(fd, FcntlArg::F_SETFL(OFlag::from_bits_truncate(flags) | OFlag::O_NONBLOCK))?;
+        let (ping, cancel) = calloop::ping::make_ping()?;
+        let ping = Arc::new(ping);
+        self.cancelled.wake_on_cancel(&ping);
+        Ok(PipeTransfer {
+            file: calloop::generic::Generic::new(
+                self.file,
+                calloop::Interest::WRITE,
+                calloop::Mode::Level,
+            ),
+            cancel,
+            _ping: ping,
+            cancelled: self.cancelled,
+            reader: Box::new(reader),
+            buffer: Vec::new(),
+            written: 0,
+        })
+    }
 }
 
 impl io::Write for WritePipe {
@@ -153,7 +256,7 @@ impl io::Write for WritePipe {
 
 impl FromRawFd for WritePipe {
     unsafe fn from_raw_fd(fd: RawFd) -> WritePipe {
-        WritePipe { file: FromRawFd::from_raw_fd(fd) }
+        WritePipe::with_cancel_flag(fd, Arc::new(CancelFlag::default()))
     }
 }
 
@@ -168,3 +271,135 @@ impl IntoRawFd for WritePipe {
         self.file.into_raw_fd()
     }
 }
+
+/// Size of the chunks written by a `PipeTransfer`
+#[cfg(feature = "calloop")]
+const TRANSFER_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Outcome of a `PipeTransfer`
+#[cfg(feature = "calloop")]
+#[derive(Debug)]
+pub enum TransferEvent {
+    /// All the data was written into the pipe
+    Finished,
+    /// The source was cancelled before all the data could be written
+    Cancelled,
+    /// Reading the data or writing it into the pipe failed
+    ///
+    /// This notably happens if the receiver closed its end of the pipe early.
+    Failed(io::Error),
+}
+
+/// A non-blocking transfer of data into a `WritePipe`
+///
+/// Created by [`WritePipe::transfer`](struct.WritePipe.html#method.transfer), see its
+/// documentation for details.
+#[cfg(feature = "calloop")]
+pub struct PipeTransfer {
+    file: calloop::generic::Generic<fs::File>,
+    // pinged when the source is cancelled
+    cancel: calloop::ping::PingSource,
+    _ping: Arc<calloop::ping::Ping>,
+    cancelled: Arc<CancelFlag>,
+    reader: Box<dyn io::Read>,
+    buffer: Vec<u8>,
+    written: usize,
+}
+
+#[cfg(feature = "calloop")]
+impl std::fmt::Debug for PipeTransfer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PipeTransfer")
+            .field("file", &self.file)
+            .field("cancelled", &self.cancelled)
+            .field("reader", &"dyn Read")
+            .field("pending", &(self.buffer.len() - self.written))
+            .finish()
+    }
+}
+
+#[cfg(feature = "calloop")]
+impl PipeTransfer {
+    /// Write as much data as the pipe accepts
+    ///
+    /// Returns `Ok(true)` once all the data has been written.
+    fn write_chunks(&mut self) -> io::Result<bool> {
+        use std::io::Write;
+        loop {
+            if self.written == self.buffer.len() {
+                self.buffer.resize(TRANSFER_CHUNK_SIZE, 0);
+                let len = loop {
+                    match self.reader.read(&mut self.buffer) {
+                        Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                        ret => break ret?,
+                    }
+                };
+                self.buffer.truncate(len);
+                self.written = 0;
+                if len == 0 {
+                    return Ok(true);
+                }
+            }
+            match self.file.file.write(&self.buffer[self.written..]) {
+                Ok(n) => self.written += n,
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(false),
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "calloop")]
+impl calloop::EventSource for PipeTransfer {
+    type Event = TransferEvent;
+    type Metadata = ();
+    type Ret = ();
+
+    fn process_events<F>(
+        &mut self,
+        readiness: calloop::Readiness,
+        token: calloop::Token,
+        mut callback: F,
+    ) -> std::io::Result<calloop::PostAction>
+    where
+        F: FnMut(TransferEvent, &mut ()),
+    {
+        // drain the ping, the flag tells whether the source was cancelled
+        self.cancel.process_events(readiness, token, |(), _| {})?;
+        let event = if self.cancelled.is_cancelled() {
+            TransferEvent::Cancelled
+        } else {
+            match self.write_chunks() {
+                Ok(false) => return Ok(calloop::PostAction::Continue),
+                Ok(true) => TransferEvent::Finished,
+                Err(e) => TransferEvent::Failed(e),
+            }
+        };
+        callback(event, &mut ());
+        Ok(calloop::PostAction::Remove)
+    }
+
+    fn register(
+        &mut self,
+        poll: &mut calloop::Poll,
+        token_factory: &mut calloop::TokenFactory,
+    ) -> std::io::Result<()> {
+        self.file.register(poll, token_factory)?;
+        self.cancel.register(poll, token_factory)
+    }
+
+    fn reregister(
+        &mut self,
+        poll: &mut calloop::Poll,
+        token_factory: &mut calloop::TokenFactory,
+    ) -> std::io::Result<()> {
+        self.file.reregister(poll, token_factory)?;
+        self.cancel.reregister(poll, token_factory)
+    }
+
+    fn unregister(&mut self, poll: &mut calloop::Poll) -> std::io::Result<()> {
+        self.file.unregister(poll)?;
+        self.cancel.unregister(poll)
+    }
+}
@@ -25,6 +25,30 @@
 //! as [`SimpleGlobal<I>`](struct.SimpleGlobal.html). It can manage "single" globals that do not generate
 //! events, and thus require no filter.
 //!
+//! ## Supporting other protocols
+//!
+//! SCTK only provides handlers for a subset of the Wayland protocols, but the handler traits are
+//! the same for all globals: a crate can support any other protocol by providing its own
+//! handlers, which applications then list in their `environment!` along with the ones of SCTK.
+//! No change to SCTK is needed for that, and this contract is stable:
+//!
+//! - `created()` is invoked for every global of the interface advertised by the registry, with the
+//!   version advertised by the server. The handler must bind it with a version no higher than both
+//!   this version and the one it supports, and assign a filter to it if it generates events.
+//! - `removed()` is invoked for "multi" globals when the server removes them, "single" globals
+//!   are never removed.
+//! - handlers can be shared between several interfaces, like the
+//!   [`PrimarySelectionHandler`](../primary_selection/struct.PrimarySelectionHandler.html)
+//!   handling two versions of the same protocol.
+//!
+//! Globals that generate no events can use a [`SimpleGlobal<I>`](struct.SimpleGlobal.html),
+//! limiting the version it binds with [`with_max_version`](struct.SimpleGlobal.html#method.with_max_version)
+//! if needed. The handlers of SCTK are good examples of the other cases, for example the
+//! [`OutputHandler`](../output/struct.OutputHandler.html) for a "multi" global.
+//!
+//! Functionality built on top of your globals can then be provided to the applications as methods
+//! on `Environment<E>`, bounded on `E: GlobalHandler<I>` or `E: MultiGlobalHandler<I>`.
+//!
 //! ## the  `environment!` macro
 //!
 //! This macro is at the core of this module. See its documentation for details about how to
@@ -33,14 +57,18 @@
 //! in all SCTK modules.
 
 use std::io::Result;
-use std::rc::Rc;
+use std::ops::RangeInclusive;
+use std::rc::{self, Rc};
 use std::{cell::RefCell, fmt};
 
 use wayland_client::{
-    protocol::{wl_display, wl_registry},
-    Attached, DispatchData, EventQueue, GlobalEvent, GlobalManager, Interface, Proxy,
+    protocol::{wl_display, wl_registry, wl_surface},
+    Attached, DispatchData, EventQueue, GlobalError, GlobalEvent, GlobalManager, Interface, Main,
+    Proxy,
 };
 
+use crate::InternalErrorKind;
+
 /*
  * Traits definitions
  */
@@ -94,6 +122,31 @@ pub struct Environment<E> {
     /// registry. See `wayland-client` documentation for details.
     pub manager: GlobalManager,
     inner: Rc<RefCell<E>>,
+    global_listeners: Rc<RefCell<GlobalListeners>>,
+    // the surfaces created through `create_surface`, see `surface.rs`
+    pub(crate) surfaces: Rc<RefCell<Vec<wl_surface::WlSurface>>>,
+}
+
+type GlobalCallback = dyn FnMut(GlobalEvent, Attached<wl_registry::WlRegistry>, DispatchData);
+
+#[derive(Default)]
+struct GlobalListeners {
+    // the registry, once it sent its first event
+    registry: Option<Attached<wl_registry::WlRegistry>>,
+    listeners: Vec<rc::Weak<RefCell<GlobalCallback>>>,
+}
+
+/// A handle to a global listener callback
+///
+/// Dropping it disables the associated callback and frees the closure.
+pub struct GlobalListener {
+    _cb: Rc<RefCell<GlobalCallback>>,
+}
+
+impl fmt::Debug for GlobalListener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GlobalListener").field("_cb", &"Fn(..) -> { ... }").finish()
+    }
 }
 
 impl<E: InnerEnv + 'static> Environment<E> {
@@ -120,15 +173,21 @@ impl<E: InnerEnv + 'static> Environment<E> {
 
         // Fully initialize the environment.
         queue.sync_roundtrip(&mut (), |event, _, _| {
-            panic!(
-                "Encountered unhandled event during initial roundtrip ({}::{})",
-                event.interface, event.name
+            crate::error_policy::report(
+                InternalErrorKind::UnexpectedEvent,
+                format!(
+                    "Encountered unhandled event during initial roundtrip ({}::{})",
+                    event.interface, event.name
+                ),
             );
         })?;
         queue.sync_roundtrip(&mut (), |event, _, _| {
-            panic!(
-                "Encountered unhandled event during initial roundtrip ({}::{})",
-                event.interface, event.name
+            crate::error_policy::report(
+                InternalErrorKind::UnexpectedEvent,
+                format!(
+                    "Encountered unhandled event during initial roundtrip ({}::{})",
+                    event.interface, event.name
+                ),
             );
         })?;
 
@@ -152,16 +211,35 @@ impl<E: InnerEnv + 'static> Environment<E> {
     /// roundtrips are required.
     pub fn new_pending(display: &Attached<wl_display::WlDisplay>, env: E) -> Environment<E> {
         let inner = Rc::new(RefCell::new(env));
+        let global_listeners = Rc::new(RefCell::new(GlobalListeners::default()));
 
         let my_inner = inner.clone();
-        let my_cb = move |event, registry, ddata: DispatchData| {
-            let mut inner = my_inner.borrow_mut();
-            inner.process_event(event, registry, ddata);
+        let my_listeners = global_listeners.clone();
+        let my_cb = move |event: GlobalEvent,
+                          registry: Attached<wl_registry::WlRegistry>,
+                          mut ddata: DispatchData| {
+            let listeners = {
+                let mut global_listeners = my_listeners.borrow_mut();
+                global_listeners.registry = Some(registry.clone());
+                global_listeners.listeners.retain(|l| l.upgrade().is_some());
+                global_listeners.listeners.iter().filter_map(rc::Weak::upgrade).collect::<Vec<_>>()
+            };
+            let copy = if listeners.is_empty() { None } else { Some(copy_global_event(&event)) };
+            my_inner.borrow_mut().process_event(event, registry.clone(), ddata.reborrow());
+            if let Some(event) = copy {
+                for listener in listeners {
+                    (*listener.borrow_mut())(
+                        copy_global_event(&event),
+                        registry.clone(),
+                        ddata.reborrow(),
+                    );
+                }
+            }
         };
 
         let manager = GlobalManager::new_with_cb(display, my_cb);
 
-        Self { manager, inner }
+        Self { manager, inner, global_listeners, surfaces: Rc::new(RefCell::new(Vec::new())) }
     }
 }
 
@@ -183,17 +261,34 @@ impl<E> Environment<E> {
     /// Access a "single" global or panic
     ///
     /// This method is similar to `get_global`, but will panic with a detailed error
-    /// message if the requested global was not advertized by the server.
+    /// message if the requested global was not advertized by the server, see
+    /// [`missing_global_report`](#method.missing_global_report).
+    ///
+    /// The missing global is also reported to the
+    /// [`ErrorPolicy`](../enum.ErrorPolicy.html) before panicking.
     pub fn require_global<I: Interface>(&self) -> Attached<I>
     where
         E: GlobalHandler<I>,
     {
         match self.inner.borrow().get() {
             Some(g) => g,
-            None => panic!("[SCTK] A missing global was required: {}", I::NAME),
+            None => crate::error_policy::report_fatal(
+                InternalErrorKind::MissingGlobal,
+                format!("A missing global was required: {}", self.missing_global_report(I::NAME)),
+            ),
         }
     }
 
+    /// Describe what the server advertises instead of a missing global
+    ///
+    /// The returned message names the missing interface, the advertised globals whose name
+    /// is the closest to it, and the list of all the advertised globals with their versions.
+    /// It is meant to be shown to your users when a global your program needs is missing, so
+    /// that their bug reports contain what their compositor supports.
+    pub fn missing_global_report(&self, interface: &str) -> String {
+        describe_missing_global(interface, &self.manager.list())
+    }
+
     /// Access all instances of a "multi" global
     ///
     /// This will return a `Vec` containing all currently existing instances of the
@@ -207,6 +302,35 @@ impl<E> Environment<E> {
         self.inner.borrow().get_all()
     }
 
+    /// Insert a listener for the globals of the registry
+    ///
+    /// This is meant for subsystems created after the initialization of the environment,
+    /// like a handler for a protocol that is only used once the user enabled some feature:
+    /// the closure is immediately invoked with a `GlobalEvent::New` for each of the globals
+    /// currently advertised, and then with the events of the registry as they are dispatched,
+    /// after the handlers of the environment processed them.
+    ///
+    /// The returned [`GlobalListener`](struct.GlobalListener.html) keeps your callback alive,
+    /// dropping it will disable it. This must not be called from a global listener.
+    pub fn add_global_listener<F>(&self, mut f: F) -> GlobalListener
+    where
+        F: FnMut(GlobalEvent, Attached<wl_registry::WlRegistry>, DispatchData) + 'static,
+    {
+        let registry = self.global_listeners.borrow().registry.clone();
+        if let Some(registry) = registry {
+            for (id, interface, version) in self.manager.list() {
+                f(
+                    GlobalEvent::New { id, interface, version },
+                    registry.clone(),
+                    DispatchData::wrap(&mut ()),
+                );
+            }
+        }
+        let rc = Rc::new(RefCell::new(f)) as Rc<RefCell<GlobalCallback>>;
+        self.global_listeners.borrow_mut().listeners.push(Rc::downgrade(&rc));
+        GlobalListener { _cb: rc }
+    }
+
     /// Access the inner environment
     ///
     /// This gives your access, via a closure, to the inner type you declared
@@ -222,7 +346,12 @@ impl<E> Environment<E> {
 
 impl<E> Clone for Environment<E> {
     fn clone(&self) -> Environment<E> {
-        Environment { manager: self.manager.clone(), inner: self.inner.clone() }
+        Environment {
+            manager: self.manager.clone(),
+            inner: self.inner.clone(),
+            global_listeners: self.global_listeners.clone(),
+            surfaces: self.surfaces.clone(),
+        }
     }
 }
 
@@ -252,6 +381,329 @@ pub trait InnerEnv {
     );
 }
 
+fn copy_global_event(event: &GlobalEvent) -> GlobalEvent {
+    match *event {
+        GlobalEvent::New { id, ref interface, version } => {
+            GlobalEvent::New { id, interface: interface.clone(), version }
+        }
+        GlobalEvent::Removed { id, ref interface } => {
+            GlobalEvent::Removed { id, interface: interface.clone() }
+        }
+    }
+}
+
+fn describe_missing_global(interface: &str, globals: &[(u32, String, u32)]) -> String {
+    // the significant words of an interface name, without the prefixes and version suffixes
+    fn words(name: &str) -> Vec<&str> {
+        name.split('_')
+            .filter(|w| {
+                !matches!(*w, "z" | "wl" | "wp" | "zwp" | "xdg" | "zxdg" | "ext" | "manager")
+            })
+            .filter(|w| !(w.starts_with('v') && w[1..].parse::<u32>().is_ok()))
+            .collect()
+    }
+
+    let mut report = format!("the `{}` global is not available", interface);
+    if let Some((_, _, version)) = globals.iter().find(|(_, name, _)| name == interface) {
+        report += &format!(", the server advertises it with version {}", version);
+    }
+
+    let wanted = words(interface);
+    let mut closest: Vec<(usize, &str, u32)> = globals
+        .iter()
+        .filter(|(_, name, _)| name != interface)
+        .map(|(_, name, version)| {
+            (words(name).iter().filter(|w| wanted.contains(w)).count(), &name[..], *version)
+        })
+        .filter(|&(score, _, _)| score > 0)
+        .collect();
+    closest.sort_by_key(|&(score, _, _)| std::cmp::Reverse(score));
+    if !closest.is_empty() {
+        let names: Vec<String> = closest
+            .iter()
+            .take(3)
+            .map(|(_, name, version)| format!("{} v{}", name, version))
+            .collect();
+        report += &format!("; closest matches: {}", names.join(", "));
+    }
+
+    let all: Vec<String> =
+        globals.iter().map(|(_, name, version)| format!("{} v{}", name, version)).collect();
+    report += &format!("; the server advertises: {}", all.join(", "));
+    report
+}
+
+/*
+ * Global list
+ */
+
+/// An error binding a global from a [`GlobalList`](struct.GlobalList.html)
+///
+/// This is the `GlobalError` of `wayland-client`, along with the interface of the global
+/// and the required version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BindError {
+    /// The server does not advertise the global
+    Missing {
+        /// The interface of the global
+        interface: &'static str,
+    },
+    /// The server advertises the global with a version lower than the required one
+    ///
+    /// This is also the case if the version of the interface known by `wayland-client` is
+    /// lower than the required one, it is then given as the advertised version.
+    VersionTooLow {
+        /// The interface of the global
+        interface: &'static str,
+        /// The version advertised by the server
+        advertised: u32,
+        /// The minimum version that was required
+        required: u32,
+    },
+}
+
+impl std::error::Error for BindError {}
+
+impl fmt::Display for BindError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            BindError::Missing { interface } => {
+                write!(f, "the `{}` global is not available", interface)
+            }
+            BindError::VersionTooLow { interface, advertised, required } => write!(
+                f,
+                "the `{}` global is advertised with version {}, version {} is required",
+                interface, advertised, required
+            ),
+        }
+    }
+}
+
+impl BindError {
+    fn new<I: Interface>(error: GlobalError, required: u32) -> BindError {
+        match error {
+            GlobalError::Missing => BindError::Missing { interface: I::NAME },
+            GlobalError::VersionTooLow(advertised) => {
+                BindError::VersionTooLow { interface: I::NAME, advertised, required }
+            }
+        }
+    }
+}
+
+/// The list of the globals advertised by the server
+///
+/// This is an alternative to the global handlers for programs that only need to bind a few
+/// globals once, like command-line tools: it retrieves the globals with a roundtrip, and then
+/// binds them with typed methods reporting precisely why a global could not be bound.
+///
+/// ```no_run
+/// # use smithay_client_toolkit::environment::GlobalList;
+/// # use smithay_client_toolkit::reexports::client::{protocol::wl_compositor::WlCompositor, Display};
+/// let display = Display::connect_to_env().unwrap();
+/// let mut queue = display.create_event_queue();
+/// let globals = GlobalList::new(&display.attach(queue.token()), &mut queue).unwrap();
+/// let compositor = globals.bind_one::<WlCompositor>(1..=4).unwrap();
+/// ```
+///
+/// The list is kept up to date as globals are added and removed while the event queue is
+/// dispatched, by the `GlobalManager` of `wayland-client` it wraps. The bound objects have no
+/// filter assigned, you need to use `quick_assign()` or `assign()` on those that generate
+/// events, and you can attach your own user data to them as usual.
+#[derive(Debug)]
+pub struct GlobalList {
+    manager: GlobalManager,
+    // the registry, once it sent its first event
+    registry: Rc<RefCell<Option<Attached<wl_registry::WlRegistry>>>>,
+}
+
+impl GlobalList {
+    /// Retrieve the list of globals
+    ///
+    /// This does a roundtrip to the server on the event queue `display` is attached to.
+    pub fn new(
+        display: &Attached<wl_display::WlDisplay>,
+        queue: &mut EventQueue,
+    ) -> Result<GlobalList> {
+        let registry = Rc::new(RefCell::new(None));
+        let my_registry = registry.clone();
+        let manager = GlobalManager::new_with_cb(display, move |_, registry, _| {
+            *my_registry.borrow_mut() = Some(registry);
+        });
+        queue.sync_roundtrip(&mut (), |_, _, _| {})?;
+        Ok(GlobalList { manager, registry })
+    }
+
+    /// The underlying `GlobalManager`
+    pub fn manager(&self) -> &GlobalManager {
+        &self.manager
+    }
+
+    /// The registry the globals are bound with
+    ///
+    /// Returns `None` if the server did not advertise any global.
+    pub fn registry(&self) -> Option<Attached<wl_registry::WlRegistry>> {
+        self.registry.borrow().clone()
+    }
+
+    /// The list of globals, as `(id, interface, version)` tuples
+    pub fn list(&self) -> Vec<(u32, String, u32)> {
+        self.manager.list()
+    }
+
+    /// Describe what the server advertises instead of a missing global
+    ///
+    /// See [`Environment::missing_global_report`](struct.Environment.html#method.missing_global_report).
+    pub fn missing_global_report(&self, interface: &str) -> String {
+        describe_missing_global(interface, &self.manager.list())
+    }
+
+    /// Bind a "single" global
+    ///
+    /// The global is bound with the highest version advertised by the server within
+    /// `versions`, capped to the version of the interface known by `wayland-client`. If the
+    /// server advertises several instances of the global, the first one is bound.
+    pub fn bind_one<I>(
+        &self,
+        versions: RangeInclusive<u32>,
+    ) -> std::result::Result<Main<I>, BindError>
+    where
+        I: Interface + AsRef<Proxy<I>> + From<Proxy<I>>,
+    {
+        let (min, max) = supported_versions::<I>(&versions)?;
+        self.manager.instantiate_range::<I>(min, max).map_err(|e| BindError::new::<I>(e, min))
+    }
+
+    /// Bind all the instances of a "multi" global
+    ///
+    /// Each instance is bound like with [`bind_one`](#method.bind_one). Returns an error if
+    /// the server advertises no instance, or if any of them has a version lower than the
+    /// required one, in which case none are bound.
+    pub fn bind_all<I>(
+        &self,
+        versions: RangeInclusive<u32>,
+    ) -> std::result::Result<Vec<Main<I>>, BindError>
+    where
+        I: Interface + AsRef<Proxy<I>> + From<Proxy<I>>,
+    {
+        let (min, max) = supported_versions::<I>(&versions)?;
+        // the GlobalManager only binds the first instance, the others are bound with the
+        // versions `instantiate_range` would choose
+        let instances = self
+            .manager
+            .list()
+            .into_iter()
+            .filter(|(_, interface, _)| interface == I::NAME)
+            .map(|(id, _, advertised)| {
+                if advertised < min {
+                    Err(BindError::new::<I>(GlobalError::VersionTooLow(advertised), min))
+                } else {
+                    Ok((id, advertised.min(max)))
+                }
+            })
+            .collect::<std::result::Result<Vec<_>, BindError>>()?;
+        let registry = match self.registry() {
+            Some(ref registry) if !instances.is_empty() => registry.clone(),
+            _ => return Err(BindError::Missing { interface: I::NAME }),
+        };
+        Ok(instances.into_iter().map(|(id, version)| registry.bind::<I>(version, id)).collect())
+    }
+}
+
+// The versions of an interface that can be bound, capped to the one known by wayland-client
+fn supported_versions<I: Interface>(
+    versions: &RangeInclusive<u32>,
+) -> std::result::Result<(u32, u32), BindError> {
+    let min = *versions.start();
+    if I::VERSION < min {
+        return Err(BindError::new::<I>(GlobalError::VersionTooLow(I::VERSION), min));
+    }
+    // an empty range binds the minimum version
+    Ok((min, (*versions.end()).min(I::VERSION).max(min)))
+}
+
+/*
+ * Shutdown
+ */
+
+/// An interface trait to release the objects owned by the global handlers
+///
+/// It is implemented by the environments generated by the
+/// [`default_environment!`](../macro.default_environment.html) macro. If you use the
+/// [`environment!`](../macro.environment.html) macro, you can implement it by calling the
+/// `shutdown()` methods of your handlers.
+pub trait ShutdownHandling {
+    /// Destroy the objects owned by the handlers
+    ///
+    /// Objects must be destroyed before the objects they depend on, for example the data
+    /// devices before the seats.
+    fn shutdown(&mut self);
+}
+
+/// The result of an [`Environment::shutdown`](struct.Environment.html#method.shutdown)
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ShutdownReport {
+    /// Number of other handles to the environment that were still alive
+    ///
+    /// They are likely to be held by objects of the application that were not dropped
+    /// before the shutdown, like windows.
+    pub live_handles: usize,
+}
+
+impl<E: ShutdownHandling> Environment<E> {
+    /// Gracefully shut the environment down
+    ///
+    /// This destroys the objects owned by the global handlers in dependency order, and then
+    /// does a roundtrip to the server so that it has processed all the destructions when
+    /// this returns.
+    ///
+    /// The events received during this roundtrip are still dispatched to the callbacks of
+    /// their objects, with `()` as dispatch data: callbacks expecting your own dispatch data,
+    /// for example with `dispatch_data.get::<T>().unwrap()`, must be dropped or must tolerate
+    /// it. Only the events without callback are ignored.
+    ///
+    /// Objects owned by the application, like windows, surfaces and memory pools, should be
+    /// dropped before calling this, as they may depend on the objects destroyed here. The
+    /// returned report indicates how many handles to the environment were still alive.
+    pub fn shutdown(self, queue: &mut EventQueue) -> Result<ShutdownReport> {
+        let live_handles = Rc::strong_count(&self.inner) - 1;
+        if live_handles > 0 {
+            log::warn!(
+                "[SCTK] Shutting down while {} handles to the environment are alive.",
+                live_handles
+            );
+        }
+        self.inner.borrow_mut().shutdown();
+        queue.sync_roundtrip(&mut (), |_, _, _| {})?;
+        Ok(ShutdownReport { live_handles })
+    }
+}
+
+/*
+ * Synchronization
+ */
+
+/// Invoke a callback once the server has processed all requests sent so far
+///
+/// This issues a `wl_display.sync` request, the callback is invoked when its `done`
+/// event is dispatched, meaning that the server has processed all requests sent
+/// before this call, and that all the events they generated have been dispatched.
+///
+/// This is useful to order some cleanup, for example making sure the server has
+/// seen the destruction of your objects before exiting. Unlike a roundtrip, it does
+/// not block: the callback will be invoked during the regular dispatching of the
+/// event queue `display` is attached to.
+pub fn barrier<F>(display: &Attached<wl_display::WlDisplay>, callback: F)
+where
+    F: FnOnce(DispatchData) + 'static,
+{
+    let mut callback = Some(callback);
+    display.sync().quick_assign(move |_, _, ddata| {
+        if let Some(callback) = callback.take() {
+            callback(ddata);
+        }
+    });
+}
+
 /*
  * Simple handlers
  */
@@ -263,15 +715,28 @@ pub trait InnerEnv {
 ///
 /// It is appropriate for globals that never generate events, like `wl_compositor`
 /// or `wl_data_device_manager`.
+///
+/// The global is bound with the version advertised by the server, capped to the version
+/// of the interface known by `wayland-client`, or to a lower version given with
+/// [`with_max_version`](#method.with_max_version).
 #[derive(Debug)]
 pub struct SimpleGlobal<I: Interface> {
     global: Option<Attached<I>>,
+    max_version: u32,
 }
 
 impl<I: Interface> SimpleGlobal<I> {
     /// Create a new handler
     pub fn new() -> SimpleGlobal<I> {
-        SimpleGlobal { global: None }
+        SimpleGlobal { global: None, max_version: I::VERSION }
+    }
+
+    /// Create a new handler binding the global with at most the given version
+    ///
+    /// Use this when your code only supports the older versions of a protocol, as binding a
+    /// newer version could make the server send events or expect behaviors you don't handle.
+    pub fn with_max_version(max_version: u32) -> SimpleGlobal<I> {
+        SimpleGlobal { global: None, max_version: max_version.min(I::VERSION) }
     }
 }
 
@@ -283,6 +748,7 @@ impl<I: Interface + Clone + From<Proxy<I>> + AsRef<Proxy<I>>> GlobalHandler<I> f
         version: u32,
         _: DispatchData,
     ) {
+        let version = std::cmp::min(version, self.max_version);
         self.global = Some((*registry.bind::<I>(version, id)).clone())
     }
     fn get(&self) -> Option<Attached<I>> {
@@ -392,3 +858,38 @@ macro_rules! environment {
         )*
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn globals(list: &[(&str, u32)]) -> Vec<(u32, String, u32)> {
+        list.iter().enumerate().map(|(i, &(name, v))| (i as u32 + 1, name.to_owned(), v)).collect()
+    }
+
+    #[test]
+    fn describe_missing_global_suggests_close_names() {
+        let globals = globals(&[
+            ("wl_compositor", 4),
+            ("zwp_text_input_manager_v1", 1),
+            ("zwp_input_method_manager_v2", 1),
+        ]);
+        assert_eq!(
+            describe_missing_global("zwp_text_input_manager_v3", &globals),
+            "the `zwp_text_input_manager_v3` global is not available; closest matches: \
+             zwp_text_input_manager_v1 v1, zwp_input_method_manager_v2 v1; the server \
+             advertises: wl_compositor v4, zwp_text_input_manager_v1 v1, \
+             zwp_input_method_manager_v2 v1"
+        );
+    }
+
+    #[test]
+    fn describe_missing_global_gives_the_advertised_version() {
+        let globals = globals(&[("wl_compositor", 3), ("wl_shm", 1)]);
+        assert_eq!(
+            describe_missing_global("wl_compositor", &globals),
+            "the `wl_compositor` global is not available, the server advertises it with \
+             version 3; the server advertises: wl_compositor v3, wl_shm v1"
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use std::{
+    fmt,
+    sync::{Arc, RwLock},
+};
+
+/// The kind of an internal fault of SCTK
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum InternalErrorKind {
+    /// The compositor sent an event SCTK did not expect, like an unknown data offer
+    UnexpectedEvent,
+    /// A global required by an operation was not advertised by the compositor
+    MissingGlobal,
+    /// The compositor sent data that could not be used, like an invalid keymap
+    InvalidState,
+}
+
+/// An internal fault encountered by SCTK
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InternalError {
+    /// The kind of fault
+    pub kind: InternalErrorKind,
+    /// A description of the fault
+    pub message: String,
+}
+
+impl std::error::Error for InternalError {}
+
+impl fmt::Display for InternalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// What SCTK does when it encounters an internal fault
+///
+/// By default, SCTK panics when its handlers encounter an invalid state, like an unexpected
+/// event from the compositor. Production applications can instead log these faults, or
+/// forward them to their telemetry, and let SCTK degrade gracefully: the faulty event is
+/// ignored, or the affected object is left unusable, like a keyboard without keymap.
+///
+/// Some faults can not be recovered from, like a global required by
+/// [`Environment::require_global`](environment/struct.Environment.html#method.require_global)
+/// being missing. These are still logged or passed to the callback, and then panic whatever
+/// the policy.
+///
+/// The policy is shared by the whole process, see [`set_error_policy`](fn.set_error_policy.html).
+#[derive(Clone)]
+pub enum ErrorPolicy {
+    /// Panic with the description of the fault, the default
+    Panic,
+    /// Log the fault as an error and carry on
+    Log,
+    /// Pass the fault to a callback and carry on
+    ///
+    /// The callback may be invoked from any thread dispatching Wayland events.
+    Callback(Arc<dyn Fn(&InternalError) + Send + Sync>),
+}
+
+impl fmt::Debug for ErrorPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorPolicy::Panic => f.write_str("Panic"),
+            ErrorPolicy::Log => f.write_str("Log"),
+            ErrorPolicy::Callback(_) => {
+                f.debug_tuple("Callback").field(&"Fn(..) -> { ... }").finish()
+            }
+        }
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref POLICY: RwLock<ErrorPolicy> = RwLock::new(ErrorPolicy::Panic);
+}
+
+/// Set how SCTK handles its internal faults
+///
+/// This applies to the whole process, and should be set before connecting to the
+/// compositor.
+pub fn set_error_policy(policy: ErrorPolicy) {
+    *POLICY.write().unwrap() = policy;
+}
+
+/// The current policy for the internal faults of SCTK
+pub fn error_policy() -> ErrorPolicy {
+    POLICY.read().unwrap().clone()
+}
+
+// Report a fault to the policy, the caller carries on if this returns
+pub(crate) fn report(kind: InternalErrorKind, message: String) {
+    let error = InternalError { kind, message };
+    // the lock is not held while invoking the callback, so that it can change the policy
+    match error_policy() {
+        ErrorPolicy::Panic => panic!("[SCTK] {}", error),
+        ErrorPolicy::Log => log::error!("[SCTK] {}", error),
+        ErrorPolicy::Callback(callback) => callback(&error),
+    }
+}
+
+// Report a fault that can not be recovered from, and panic
+pub(crate) fn report_fatal(kind: InternalErrorKind, message: String) -> ! {
+    let error = InternalError { kind, message };
+    match error_policy() {
+        ErrorPolicy::Panic => {}
+        ErrorPolicy::Log => log::error!("[SCTK] {}", error),
+        ErrorPolicy::Callback(callback) => callback(&error),
+    }
+    panic!("[SCTK] {}", error)
+}
@@ -1,4 +1,9 @@
-use std::io;
+use std::{
+    cell::RefCell,
+    fmt, io,
+    rc::Rc,
+    time::{Duration, Instant},
+};
 
 use calloop::{
     generic::{Fd, Generic},
@@ -8,6 +13,8 @@ use calloop::{
 
 use wayland_client::EventQueue;
 
+use crate::InternalErrorKind;
+
 /// An adapter to insert a Wayland `EventQueue` into a calloop event loop
 ///
 /// This is a struct that implements `calloop::EventSource`. It generates an
@@ -16,23 +23,174 @@ use wayland_client::EventQueue;
 /// and forward its return value, allowing you to handle orphan events as you prefer.
 ///
 /// If you don't use orphan events, the `quick_insert` method will directly
-/// insert the source into a provided `LoopHandle` with an adapter which will panic
-/// whenever an oprhan event is encountered.
+/// insert the source into a provided `LoopHandle` with an adapter which will report
+/// whenever an oprhan event is encountered to the [`ErrorPolicy`](enum.ErrorPolicy.html),
+/// panicking by default.
+///
+/// By default, each wake of the source dispatches all the events available. A budget can be
+/// set with [`set_dispatch_budget`](#method.set_dispatch_budget) to keep the latency of the
+/// event loop bounded when the compositor sends huge bursts of events.
 #[derive(Debug)]
 pub struct WaylandSource {
     queue: EventQueue,
     fd: Generic<Fd>,
+    // boxed, as they are rarely enabled
+    stats: Option<Box<DispatchStats>>,
+    idle: IdleHandle,
+    max_events: Option<u32>,
+    max_time: Option<Duration>,
+}
+
+type IdleCallback = dyn FnOnce();
+
+/// A handle to defer closures until a `WaylandSource` finished dispatching
+///
+/// Obtained with [`WaylandSource::idle_handle`](struct.WaylandSource.html#method.idle_handle),
+/// it can be cloned and moved into your callbacks.
+#[derive(Clone, Default)]
+pub struct IdleHandle {
+    pending: Rc<RefCell<Vec<Box<IdleCallback>>>>,
+}
+
+impl IdleHandle {
+    /// Run a closure once the current dispatch batch is complete
+    ///
+    /// The closure is run after all the events currently available have been dispatched and
+    /// the requests flushed to the server, so it can do things that must not happen while
+    /// dispatching, like destroying objects whose events are still being processed. The
+    /// requests it sends are flushed right after it.
+    ///
+    /// Closures posted from an idle closure are run after the next dispatch batch.
+    pub fn post_idle<F: FnOnce() + 'static>(&self, f: F) {
+        self.pending.borrow_mut().push(Box::new(f));
+    }
+
+    // Run the pending closures, returns whether any was run
+    fn run(&self) -> bool {
+        let pending = std::mem::take(&mut *self.pending.borrow_mut());
+        let ran = !pending.is_empty();
+        for f in pending {
+            f();
+        }
+        ran
+    }
+}
+
+impl fmt::Debug for IdleHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IdleHandle")
+            .field(
+                "pending",
+                &format_args!("[{} x FnOnce() -> {{ ... }}]", self.pending.borrow().len()),
+            )
+            .finish()
+    }
+}
+
+/// Statistics about the dispatching done by a `WaylandSource`
+///
+/// These are only collected if enabled with `WaylandSource::enable_stats()`,
+/// and can help spotting a saturated main loop: many events per wake or
+/// frequent flush stalls mean the application struggles to keep up with the
+/// compositor.
+///
+/// The underlying wayland library does not report how many bytes are read from
+/// the socket, so the size of a read is measured as the number of events it made
+/// available for dispatching.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DispatchStats {
+    /// Number of times the source was woken up by the event loop
+    pub wakes: u64,
+    /// Number of reads of the wayland socket that returned data
+    pub reads: u64,
+    /// Number of reads that found nothing to read (`WouldBlock`)
+    pub empty_reads: u64,
+    /// Total number of events dispatched
+    pub events: u64,
+    /// Number of events dispatched during the last wake
+    pub last_wake_events: u32,
+    /// Largest number of events dispatched during a single wake
+    pub max_wake_events: u32,
+    /// Largest number of events dispatched following a single read
+    pub max_read_events: u32,
+    /// Number of flushes that could not send all requests (`WouldBlock`)
+    pub flush_stalls: u64,
+    /// Number of wakes that exhausted the dispatch budget, deferring events to the next one
+    pub budget_exhausted: u64,
+}
+
+impl DispatchStats {
+    /// Average number of events dispatched per wake
+    pub fn events_per_wake(&self) -> f64 {
+        if self.wakes == 0 {
+            0.0
+        } else {
+            self.events as f64 / self.wakes as f64
+        }
+    }
 }
 
 impl WaylandSource {
     /// Wrap an `EventQueue` as a `WaylandSource`.
     pub fn new(queue: EventQueue) -> WaylandSource {
         let fd = queue.display().get_connection_fd();
-        WaylandSource { queue, fd: Generic::from_fd(fd, Interest::READ, Mode::Level) }
+        WaylandSource {
+            queue,
+            fd: Generic::from_fd(fd, Interest::READ, Mode::Level),
+            stats: None,
+            idle: IdleHandle::default(),
+            max_events: None,
+            max_time: None,
+        }
+    }
+
+    /// Limit the dispatching done by each wake of the source
+    ///
+    /// Once `max_events` events were dispatched or `max_time` elapsed during a wake, the
+    /// source stops reading the socket and returns to the event loop, letting the other
+    /// sources run. The remaining events are dispatched by the next iteration of the loop,
+    /// which happens right away as the socket is still readable. `None` removes the
+    /// corresponding limit, which is the default.
+    ///
+    /// The budget is checked after each read of the socket, whose events are always
+    /// dispatched together, so it can be exceeded by the size of a read.
+    pub fn set_dispatch_budget(&mut self, max_events: Option<u32>, max_time: Option<Duration>) {
+        self.max_events = max_events;
+        self.max_time = max_time;
+    }
+
+    /// Get a handle to defer closures until this source finished dispatching
+    ///
+    /// See [`IdleHandle::post_idle`](struct.IdleHandle.html#method.post_idle).
+    pub fn idle_handle(&self) -> IdleHandle {
+        self.idle.clone()
+    }
+
+    /// Start collecting dispatch statistics
+    ///
+    /// Statistics are reset if they were already being collected.
+    pub fn enable_stats(&mut self) {
+        self.stats = Some(Box::default());
+    }
+
+    /// Stop collecting dispatch statistics
+    pub fn disable_stats(&mut self) {
+        self.stats = None;
+    }
+
+    /// Access the dispatch statistics collected so far
+    ///
+    /// Returns `None` if the statistics were not enabled with `enable_stats()`.
+    pub fn stats(&self) -> Option<&DispatchStats> {
+        self.stats.as_deref()
     }
 
     /// Insert this source into given event loop with an adapter that panics on orphan events
     ///
+    /// Orphan events are reported to the [`ErrorPolicy`](enum.ErrorPolicy.html), which panics by
+    /// default.
+    ///
     /// The adapter will pass the event loop's global shared data as `dispatch_data` too all
     /// callbacks.
     pub fn quick_insert<Data: 'static>(
@@ -41,11 +199,14 @@ impl WaylandSource {
     ) -> Result<RegistrationToken, InsertError<WaylandSource>> {
         handle.insert_source(self, |(), queue, ddata| {
             queue.dispatch_pending(ddata, |event, object, _| {
-                panic!(
-                    "[calloop] Encountered an orphan event: {}@{} : {}",
-                    event.interface,
-                    object.as_ref().id(),
-                    event.name
+                crate::error_policy::report(
+                    InternalErrorKind::UnexpectedEvent,
+                    format!(
+                        "[calloop] Encountered an orphan event: {}@{} : {}",
+                        event.interface,
+                        object.as_ref().id(),
+                        event.name
+                    ),
                 );
             })
         })
@@ -64,6 +225,22 @@ impl WaylandSource {
     }
 }
 
+fn flush(queue: &EventQueue, stats: &mut Option<Box<DispatchStats>>) -> io::Result<()> {
+    if let Err(e) = queue.display().flush() {
+        if e.kind() != io::ErrorKind::WouldBlock {
+            // in case of error, forward it and fast-exit
+            return Err(e);
+        }
+        if let Some(stats) = stats.as_mut() {
+            stats.flush_stalls += 1;
+        }
+        // WouldBlock error means the compositor could not process all our messages
+        // quickly. Either it is slowed down or we are a spammer.
+        // Should not really happen, if it does we do nothing and will flush again later
+    }
+    Ok(())
+}
+
 impl EventSource for WaylandSource {
     type Event = ();
     type Metadata = EventQueue;
@@ -79,44 +256,78 @@ impl EventSource for WaylandSource {
         F: FnMut((), &mut EventQueue) -> std::io::Result<u32>,
     {
         let queue = &mut self.queue;
+        let stats = &mut self.stats;
+        let idle = &self.idle;
+        let (max_events, max_time) = (self.max_events, self.max_time);
         self.fd.process_events(readiness, token, |_, _| {
+            let start = max_time.map(|_| Instant::now());
+            let mut wake_events = 0u32;
             // in case of readiness of the wayland socket we do the following in a loop, until nothing
             // more can be read:
             loop {
                 // 1. read events from the socket if any are available
+                let mut did_read = false;
                 if let Some(guard) = queue.prepare_read() {
                     // might be None if some other thread read events before us, concurently
-                    if let Err(e) = guard.read_events() {
-                        if e.kind() != io::ErrorKind::WouldBlock {
-                            return Err(e);
+                    match guard.read_events() {
+                        Ok(()) => did_read = true,
+                        Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                            if let Some(stats) = stats.as_mut() {
+                                stats.empty_reads += 1;
+                            }
                         }
+                        Err(e) => return Err(e),
                     }
                 }
                 // 2. dispatch any pending event in the queue
                 // propagate orphan events to the user
                 let ret = callback((), queue);
+                if let Ok(n) = ret {
+                    wake_events = wake_events.saturating_add(n);
+                }
+                if let (Some(stats), Ok(n)) = (stats.as_mut(), &ret) {
+                    stats.events += *n as u64;
+                    if did_read {
+                        stats.reads += 1;
+                        stats.max_read_events = std::cmp::max(stats.max_read_events, *n);
+                    }
+                }
                 match ret {
                     Ok(0) => {
                         // no events were dispatched even after reading the socket,
                         // nothing more to do, stop here
                         break;
                     }
-                    Ok(_) => {}
+                    Ok(_) => {
+                        // stop once the budget is exhausted, the socket is still readable
+                        // so the loop will wake us again right away
+                        let exhausted = max_events.map(|max| wake_events >= max).unwrap_or(false)
+                            || max_time
+                                .and_then(|max| start.map(|start| start.elapsed() >= max))
+                                .unwrap_or(false);
+                        if exhausted {
+                            if let Some(stats) = stats.as_mut() {
+                                stats.budget_exhausted += 1;
+                            }
+                            break;
+                        }
+                    }
                     Err(e) => {
                         // in case of error, forward it and fast-exit
                         return Err(e);
                     }
                 }
             }
+            if let Some(stats) = stats.as_mut() {
+                stats.wakes += 1;
+                stats.last_wake_events = wake_events;
+                stats.max_wake_events = std::cmp::max(stats.max_wake_events, wake_events);
+            }
             // 3. Once dispatching is finished, flush the responses to the compositor
-            if let Err(e) = queue.display().flush() {
-                if e.kind() != io::ErrorKind::WouldBlock {
-                    // in case of error, forward it and fast-exit
-                    return Err(e);
-                }
-                // WouldBlock error means the compositor could not process all our messages
-                // quickly. Either it is slowed down or we are a spammer.
-                // Should not really happen, if it does we do nothing and will flush again later
+            flush(queue, stats)?;
+            // 4. Run the deferred closures, and flush the requests they sent
+            if idle.run() {
+                flush(queue, stats)?;
             }
             Ok(PostAction::Continue)
         })
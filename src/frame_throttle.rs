@@ -0,0 +1,142 @@
+use std::{cell::RefCell, fmt, rc::Rc};
+
+use wayland_client::{
+    protocol::{wl_callback, wl_surface},
+    DispatchData,
+};
+
+type DrawCallback = dyn FnMut(&wl_surface::WlSurface, Option<u32>, DispatchData) -> bool;
+
+struct State {
+    surface: wl_surface::WlSurface,
+    // a frame callback was requested with the last commit and did not fire yet
+    waiting: bool,
+    // a redraw was requested while waiting for the frame callback
+    dirty: bool,
+    // identifies the last requested frame callback, to ignore the older ones
+    generation: u32,
+}
+
+/// A helper drawing a surface at most once per frame
+///
+/// Drawing more often than the compositor displays frames wastes resources, so applications
+/// should wait for the frame callback of their surface before drawing again. This helper
+/// implements this: call [`request_redraw`](#method.request_redraw) whenever the content
+/// of the surface changed, and it invokes your `draw` callback right away if the compositor
+/// is ready for a new frame, or once when it signals the next frame otherwise, no matter
+/// how many redraws were requested in between.
+///
+/// The `draw` callback receives the surface, the timestamp of the frame callback (or `None`
+/// if it is invoked directly from `request_redraw`) and the `DispatchData` (which is empty
+/// in that case). It must commit the surface and return `true`, a frame callback being
+/// requested before it is invoked, or return `false` if it did not commit anything.
+pub struct FrameThrottle {
+    state: Rc<RefCell<State>>,
+    draw: Rc<RefCell<DrawCallback>>,
+}
+
+impl FrameThrottle {
+    /// Create a new throttle for a surface
+    pub fn new<F>(surface: wl_surface::WlSurface, draw: F) -> FrameThrottle
+    where
+        F: FnMut(&wl_surface::WlSurface, Option<u32>, DispatchData) -> bool + 'static,
+    {
+        FrameThrottle {
+            state: Rc::new(RefCell::new(State {
+                surface,
+                waiting: false,
+                dirty: false,
+                generation: 0,
+            })),
+            draw: Rc::new(RefCell::new(draw)),
+        }
+    }
+
+    /// Request the surface to be drawn
+    ///
+    /// The surface is drawn immediately if no frame is pending, and on the next frame
+    /// callback otherwise. Calling this from the `draw` callback schedules a new draw on
+    /// the next frame, which can be used for animations.
+    pub fn request_redraw(&self) {
+        let mut state = self.state.borrow_mut();
+        if state.waiting {
+            state.dirty = true;
+        } else {
+            drop(state);
+            draw(&self.state, &self.draw, None, DispatchData::wrap(&mut ()));
+        }
+    }
+
+    /// Whether a redraw is scheduled for the next frame
+    pub fn is_dirty(&self) -> bool {
+        self.state.borrow().dirty
+    }
+
+    /// Whether the compositor did not signal the last committed frame yet
+    pub fn is_waiting(&self) -> bool {
+        self.state.borrow().waiting
+    }
+
+    /// The surface drawn by this throttle
+    pub fn surface(&self) -> wl_surface::WlSurface {
+        self.state.borrow().surface.clone()
+    }
+}
+
+fn draw(
+    state: &Rc<RefCell<State>>,
+    callback: &Rc<RefCell<DrawCallback>>,
+    time: Option<u32>,
+    ddata: DispatchData,
+) {
+    let surface = {
+        let mut st = state.borrow_mut();
+        st.generation = st.generation.wrapping_add(1);
+        st.waiting = true;
+        st.dirty = false;
+        let generation = st.generation;
+        let my_state = Rc::downgrade(state);
+        let my_callback = Rc::downgrade(callback);
+        st.surface.frame().quick_assign(move |_, event, ddata| {
+            let time = match event {
+                wl_callback::Event::Done { callback_data } => callback_data,
+                _ => return,
+            };
+            let (state, callback) = match (my_state.upgrade(), my_callback.upgrade()) {
+                (Some(state), Some(callback)) => (state, callback),
+                _ => return,
+            };
+            let dirty = {
+                let mut st = state.borrow_mut();
+                if st.generation != generation {
+                    return;
+                }
+                st.waiting = false;
+                st.dirty
+            };
+            if dirty {
+                draw(&state, &callback, Some(time), ddata);
+            }
+        });
+        st.surface.clone()
+    };
+    let committed = (*callback.borrow_mut())(&surface, time, ddata);
+    if !committed {
+        // the frame callback will only fire with a later commit, don't wait for it
+        let mut st = state.borrow_mut();
+        st.waiting = false;
+        st.generation = st.generation.wrapping_add(1);
+    }
+}
+
+impl fmt::Debug for FrameThrottle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.state.borrow();
+        f.debug_struct("FrameThrottle")
+            .field("surface", &state.surface)
+            .field("waiting", &state.waiting)
+            .field("dirty", &state.dirty)
+            .field("draw", &"Fn(..) -> { ... }")
+            .finish()
+    }
+}
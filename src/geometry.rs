@@ -0,0 +1,142 @@
+//! Geometry types
+//!
+//! SCTK exposes sizes and positions as plain tuples, like the `new_size` of a window
+//! configure event or the `location` and `physical_size` of an output. The types of this
+//! module can be created from (and converted back into) these tuples, and are returned by
+//! accessors like [`WindowConfigure::size`](../window/struct.WindowConfigure.html#method.size),
+//! [`OutputInfo::position`](../output/struct.OutputInfo.html#method.position) and
+//! [`pointer_position`](fn.pointer_position.html). They serve as a bridge to the types of
+//! the math libraries used by graphics-heavy programs:
+//!
+//! - with the `mint` cargo feature, `Size` converts to and from `mint::Vector2` and
+//!   `Position` to and from `mint::Point2`
+//! - with the `euclid` cargo feature, `Size` converts to and from `euclid::Size2D` and
+//!   `Position` to and from `euclid::Point2D`
+//!
+//! ```no_run
+//! # #[cfg(feature = "euclid")]
+//! # fn convert(new_size: Option<(u32, u32)>) {
+//! use smithay_client_toolkit::geometry::Size;
+//! use euclid::default::Size2D;
+//!
+//! let size: Option<Size2D<u32>> = new_size.map(|s| Size::from(s).into());
+//! # }
+//! ```
+
+use wayland_client::protocol::wl_pointer;
+
+/// A size
+///
+/// Sizes in SCTK are expressed in logical pixels unless stated otherwise.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Size<T> {
+    /// The width
+    pub width: T,
+    /// The height
+    pub height: T,
+}
+
+/// A position
+///
+/// Positions in SCTK are expressed in logical pixels unless stated otherwise.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Position<T> {
+    /// The horizontal coordinate
+    pub x: T,
+    /// The vertical coordinate
+    pub y: T,
+}
+
+impl<T> From<(T, T)> for Size<T> {
+    fn from((width, height): (T, T)) -> Size<T> {
+        Size { width, height }
+    }
+}
+
+impl<T> From<Size<T>> for (T, T) {
+    fn from(size: Size<T>) -> (T, T) {
+        (size.width, size.height)
+    }
+}
+
+impl<T> From<(T, T)> for Position<T> {
+    fn from((x, y): (T, T)) -> Position<T> {
+        Position { x, y }
+    }
+}
+
+impl<T> From<Position<T>> for (T, T) {
+    fn from(position: Position<T>) -> (T, T) {
+        (position.x, position.y)
+    }
+}
+
+#[cfg(feature = "mint")]
+impl<T> From<mint::Vector2<T>> for Size<T> {
+    fn from(v: mint::Vector2<T>) -> Size<T> {
+        Size { width: v.x, height: v.y }
+    }
+}
+
+#[cfg(feature = "mint")]
+impl<T> From<Size<T>> for mint::Vector2<T> {
+    fn from(size: Size<T>) -> mint::Vector2<T> {
+        mint::Vector2 { x: size.width, y: size.height }
+    }
+}
+
+#[cfg(feature = "mint")]
+impl<T> From<mint::Point2<T>> for Position<T> {
+    fn from(p: mint::Point2<T>) -> Position<T> {
+        Position { x: p.x, y: p.y }
+    }
+}
+
+#[cfg(feature = "mint")]
+impl<T> From<Position<T>> for mint::Point2<T> {
+    fn from(position: Position<T>) -> mint::Point2<T> {
+        mint::Point2 { x: position.x, y: position.y }
+    }
+}
+
+#[cfg(feature = "euclid")]
+impl<T, U> From<euclid::Size2D<T, U>> for Size<T> {
+    fn from(size: euclid::Size2D<T, U>) -> Size<T> {
+        Size { width: size.width, height: size.height }
+    }
+}
+
+#[cfg(feature = "euclid")]
+impl<T, U> From<Size<T>> for euclid::Size2D<T, U> {
+    fn from(size: Size<T>) -> euclid::Size2D<T, U> {
+        euclid::Size2D::new(size.width, size.height)
+    }
+}
+
+#[cfg(feature = "euclid")]
+impl<T, U> From<euclid::Point2D<T, U>> for Position<T> {
+    fn from(p: euclid::Point2D<T, U>) -> Position<T> {
+        Position { x: p.x, y: p.y }
+    }
+}
+
+#[cfg(feature = "euclid")]
+impl<T, U> From<Position<T>> for euclid::Point2D<T, U> {
+    fn from(position: Position<T>) -> euclid::Point2D<T, U> {
+        euclid::Point2D::new(position.x, position.y)
+    }
+}
+
+/// The position of the pointer on its surface, for the events carrying one
+///
+/// This is the surface-local position of the `enter` and `motion` events, and `None` for the
+/// other events.
+pub fn pointer_position(event: &wl_pointer::Event) -> Option<Position<f64>> {
+    match *event {
+        wl_pointer::Event::Enter { surface_x, surface_y, .. }
+        | wl_pointer::Event::Motion { surface_x, surface_y, .. } => {
+            Some(Position { x: surface_x, y: surface_y })
+        }
+        _ => None,
+    }
+}
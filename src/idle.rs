@@ -0,0 +1,215 @@
+//! Tracking of the user activity, to prevent the system from going idle
+//!
+//! The compositor considers the system idle when it has received no user input for a while,
+//! and may then blank the screen or lock the session. Some applications need to prevent this
+//! under their own policy, for example a video player while a video is playing, or a
+//! presentation tool until a few minutes after the last slide change.
+//!
+//! The [`ActivityNotifier`](struct.ActivityNotifier.html) aggregates the input events of the
+//! seats and the activity signaled by the application, and reports when the application
+//! becomes active or inactive. It can directly drive an idle inhibitor of the
+//! `zwp_idle_inhibit_manager_v1` protocol. Applications with a simpler policy, inhibiting
+//! the idleness for as long as they like, can use an [`IdleInhibitor`](struct.IdleInhibitor.html)
+//! directly.
+//!
+//! The `zwp_idle_inhibit_manager_v1` global is not part of the
+//! [`default_environment!`](../macro.default_environment.html), you can add it to your
+//! environment as a [`SimpleGlobal`](../environment/struct.SimpleGlobal.html).
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use wayland_client::{
+    protocol::{wl_pointer, wl_surface, wl_touch},
+    Attached,
+};
+
+pub use wayland_protocols::unstable::idle_inhibit::v1::client::zwp_idle_inhibit_manager_v1::ZwpIdleInhibitManagerV1;
+use wayland_protocols::unstable::idle_inhibit::v1::client::zwp_idle_inhibitor_v1::ZwpIdleInhibitorV1;
+
+use crate::environment::{Environment, GlobalHandler};
+use crate::seat::keyboard;
+use crate::MissingGlobal;
+
+/// An inhibitor preventing the system from going idle
+///
+/// As per the protocol, the inhibition only applies while its surface is visible. The
+/// inhibitor is destroyed when dropped, which lifts the inhibition.
+#[derive(Debug)]
+pub struct IdleInhibitor {
+    inhibitor: ZwpIdleInhibitorV1,
+    surface: wl_surface::WlSurface,
+}
+
+impl IdleInhibitor {
+    /// Inhibit the idleness of the system while given surface is visible
+    pub fn new(
+        manager: &Attached<ZwpIdleInhibitManagerV1>,
+        surface: &wl_surface::WlSurface,
+    ) -> IdleInhibitor {
+        IdleInhibitor {
+            inhibitor: manager.create_inhibitor(surface).detach(),
+            surface: surface.clone(),
+        }
+    }
+
+    /// The surface of this inhibitor
+    pub fn surface(&self) -> &wl_surface::WlSurface {
+        &self.surface
+    }
+}
+
+impl Drop for IdleInhibitor {
+    fn drop(&mut self) {
+        self.inhibitor.destroy();
+    }
+}
+
+impl<E: GlobalHandler<ZwpIdleInhibitManagerV1>> Environment<E> {
+    /// Inhibit the idleness of the system while given surface is visible
+    ///
+    /// See [`IdleInhibitor`](../idle/struct.IdleInhibitor.html). Returns an error if the
+    /// compositor does not support `zwp_idle_inhibit_manager_v1`.
+    pub fn create_idle_inhibitor(
+        &self,
+        surface: &wl_surface::WlSurface,
+    ) -> Result<IdleInhibitor, MissingGlobal> {
+        let manager = self.get_global::<ZwpIdleInhibitManagerV1>().ok_or(MissingGlobal)?;
+        Ok(IdleInhibitor::new(&manager, surface))
+    }
+}
+
+/// An aggregator of the user and application activity
+///
+/// The notifier becomes active on any activity, and inactive once no activity happened during
+/// its timeout. The callback it was created with is invoked on each of these transitions, with
+/// the new state.
+///
+/// Feed it the events of the seats with the `handle_*_event` methods, signal the activity of
+/// the application with [`notify`](#method.notify), and regularly call
+/// [`poll`](#method.poll) so that it can notice the end of the activity. If the notifier is
+/// dropped while active, the callback is invoked a last time as if it became inactive.
+pub struct ActivityNotifier {
+    timeout: Duration,
+    last_activity: Option<Instant>,
+    callback: Box<dyn FnMut(bool)>,
+}
+
+impl ActivityNotifier {
+    /// Create a new notifier, initially inactive
+    pub fn new<F>(timeout: Duration, callback: F) -> ActivityNotifier
+    where
+        F: FnMut(bool) + 'static,
+    {
+        ActivityNotifier { timeout, last_activity: None, callback: Box::new(callback) }
+    }
+
+    /// Create a new notifier inhibiting the idleness of the system while active
+    ///
+    /// An idle inhibitor is created for given surface when the notifier becomes active, and
+    /// destroyed when it becomes inactive. As per the protocol, the inhibition only applies
+    /// while the surface is visible.
+    pub fn inhibit_while_active(
+        timeout: Duration,
+        manager: &Attached<ZwpIdleInhibitManagerV1>,
+        surface: wl_surface::WlSurface,
+    ) -> ActivityNotifier {
+        let manager = manager.clone();
+        let mut inhibitor = None;
+        ActivityNotifier::new(timeout, move |active| {
+            if !active {
+                inhibitor = None;
+            } else if inhibitor.is_none() {
+                inhibitor = Some(IdleInhibitor::new(&manager, &surface));
+            }
+        })
+    }
+
+    /// Change the duration without activity after which the notifier becomes inactive
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Whether the notifier is currently active
+    pub fn is_active(&self) -> bool {
+        self.last_activity.is_some()
+    }
+
+    /// Signal some activity
+    ///
+    /// Call this from the application for the activities that do not come from user input,
+    /// like every frame of a playing video.
+    pub fn notify(&mut self) {
+        let was_active = self.is_active();
+        self.last_activity = Some(Instant::now());
+        if !was_active {
+            (self.callback)(true);
+        }
+    }
+
+    /// Process an event of a pointer
+    ///
+    /// All events can be given, only the motion, button and axis ones count as activity.
+    pub fn handle_pointer_event(&mut self, event: &wl_pointer::Event) {
+        match *event {
+            wl_pointer::Event::Motion { .. }
+            | wl_pointer::Event::Button { .. }
+            | wl_pointer::Event::Axis { .. } => self.notify(),
+            _ => {}
+        }
+    }
+
+    /// Process an event of a keyboard
+    ///
+    /// All events can be given, only the key and repeat ones count as activity.
+    pub fn handle_keyboard_event(&mut self, event: &keyboard::Event) {
+        match *event {
+            keyboard::Event::Key { .. } | keyboard::Event::Repeat { .. } => self.notify(),
+            _ => {}
+        }
+    }
+
+    /// Process an event of a touchscreen
+    ///
+    /// All events can be given, only the down and motion ones count as activity.
+    pub fn handle_touch_event(&mut self, event: &wl_touch::Event) {
+        match *event {
+            wl_touch::Event::Down { .. } | wl_touch::Event::Motion { .. } => self.notify(),
+            _ => {}
+        }
+    }
+
+    /// Check whether the activity ended
+    ///
+    /// If the notifier is active, returns the time left before it becomes inactive, after
+    /// which this method should be called again. Returns `None` if it is inactive.
+    pub fn poll(&mut self) -> Option<Duration> {
+        let last_activity = self.last_activity?;
+        match self.timeout.checked_sub(last_activity.elapsed()) {
+            Some(left) if left > Duration::from_secs(0) => Some(left),
+            _ => {
+                self.last_activity = None;
+                (self.callback)(false);
+                None
+            }
+        }
+    }
+}
+
+impl Drop for ActivityNotifier {
+    fn drop(&mut self) {
+        if self.is_active() {
+            (self.callback)(false);
+        }
+    }
+}
+
+impl fmt::Debug for ActivityNotifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ActivityNotifier")
+            .field("timeout", &self.timeout)
+            .field("last_activity", &self.last_activity)
+            .field("callback", &"Fn(bool) -> { ... }")
+            .finish()
+    }
+}
@@ -0,0 +1,349 @@
+//! Layer surfaces of the `wlr_layer_shell` protocol
+//!
+//! Layer surfaces are used to create desktop components like panels, docks, notifications,
+//! lock screens or wallpapers. They are placed by the compositor in a layer above or below
+//! the regular windows, anchored to the edges of an output.
+//!
+//! The `zwlr_layer_shell_v1` global is not part of the
+//! [`default_environment!`](../macro.default_environment.html), you can add it to your
+//! environment as a [`SimpleGlobal`](../environment/struct.SimpleGlobal.html).
+//!
+//! The [`LayerOptions`](struct.LayerOptions.html) type provides presets for the most common
+//! kinds of layer surfaces.
+
+use std::{cell::RefCell, fmt, rc::Rc};
+
+use wayland_client::{
+    protocol::{wl_output, wl_surface},
+    Attached, DispatchData,
+};
+
+use wayland_protocols::wlr::unstable::layer_shell::v1::client::zwlr_layer_surface_v1::{
+    self, ZwlrLayerSurfaceV1,
+};
+pub use wayland_protocols::wlr::unstable::layer_shell::v1::client::{
+    zwlr_layer_shell_v1::{Layer, ZwlrLayerShellV1},
+    zwlr_layer_surface_v1::{Anchor, KeyboardInteractivity},
+};
+
+/// Possible events generated by a layer surface that you need to handle
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// The compositor configured the surface
+    ///
+    /// You need to draw your surface with this size, in logical pixels. It has
+    /// already been acknowledged.
+    Configure {
+        /// The new size of the surface
+        ///
+        /// An axis on which the size is `0` is left for you to choose.
+        new_size: (u32, u32),
+    },
+    /// The surface was closed by the compositor
+    ///
+    /// It will not be shown anymore, and you should drop it.
+    Closed,
+}
+
+/// The exclusive zone of a layer surface
+///
+/// The exclusive zone is the area along the edge the surface is anchored to that other
+/// surfaces should not cover, like the area of a panel.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ExclusiveZone {
+    /// A fixed exclusive zone, see the `zwlr_layer_surface_v1.set_exclusive_zone` request
+    ///
+    /// `0` lets the surface be moved to avoid the exclusive zones of other surfaces, and `-1`
+    /// lets it extend under them.
+    Fixed(i32),
+    /// An exclusive zone matching the size of the surface
+    ///
+    /// If the surface is anchored to a single edge (optionally stretched along it), its
+    /// exclusive zone is kept equal to its size perpendicular to this edge, and updated
+    /// whenever it is resized. Otherwise, this is the same as `Fixed(0)`.
+    Auto,
+}
+
+/// The properties of a layer surface
+///
+/// The preset constructors provide the options for common kinds of layer surfaces, which
+/// you can then adjust by changing the fields.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LayerOptions {
+    /// The layer the surface is placed in
+    pub layer: Layer,
+    /// The edges the surface is anchored to
+    pub anchor: Anchor,
+    /// The initial size of the surface, `0` on an axis lets the compositor choose it
+    pub size: (u32, u32),
+    /// The exclusive zone of the surface
+    pub exclusive_zone: ExclusiveZone,
+    /// The margins of the surface, in the order top, right, bottom, left
+    pub margin: (i32, i32, i32, i32),
+    /// Whether the surface receives keyboard events
+    pub keyboard_interactivity: KeyboardInteractivity,
+}
+
+impl LayerOptions {
+    /// A panel stretched along an edge of the output
+    ///
+    /// The surface is `thickness` logical pixels thick and reserves its size as exclusive
+    /// zone, so that windows are not placed under it. It does not take keyboard input.
+    pub fn panel(edge: Anchor, thickness: u32) -> LayerOptions {
+        let (anchor, size) = if edge.intersects(Anchor::Left | Anchor::Right) {
+            (edge | Anchor::Top | Anchor::Bottom, (thickness, 0))
+        } else {
+            (edge | Anchor::Left | Anchor::Right, (0, thickness))
+        };
+        LayerOptions {
+            layer: Layer::Top,
+            anchor,
+            size,
+            exclusive_zone: ExclusiveZone::Auto,
+            margin: (0, 0, 0, 0),
+            keyboard_interactivity: KeyboardInteractivity::None,
+        }
+    }
+
+    /// A surface covering the whole output above everything else, like a lock or
+    /// splash screen
+    ///
+    /// It extends under the exclusive zones of other surfaces, and takes the keyboard
+    /// focus exclusively.
+    pub fn overlay() -> LayerOptions {
+        LayerOptions {
+            layer: Layer::Overlay,
+            anchor: Anchor::all(),
+            size: (0, 0),
+            exclusive_zone: ExclusiveZone::Fixed(-1),
+            margin: (0, 0, 0, 0),
+            keyboard_interactivity: KeyboardInteractivity::Exclusive,
+        }
+    }
+
+    /// A surface covering the whole output below everything else, like a wallpaper
+    ///
+    /// It extends under the exclusive zones of other surfaces, and does not take keyboard
+    /// input.
+    pub fn background() -> LayerOptions {
+        LayerOptions {
+            layer: Layer::Background,
+            anchor: Anchor::all(),
+            size: (0, 0),
+            exclusive_zone: ExclusiveZone::Fixed(-1),
+            margin: (0, 0, 0, 0),
+            keyboard_interactivity: KeyboardInteractivity::None,
+        }
+    }
+
+    /// A surface above the windows that takes the keyboard focus when the user clicks on
+    /// it, like a launcher or a notification with a text field
+    ///
+    /// It is centered on the output with the given size, and avoids the exclusive zones of
+    /// other surfaces.
+    ///
+    /// On-demand keyboard interactivity requires version 4 of the protocol. With older
+    /// compositors, the surface takes the keyboard focus exclusively instead.
+    pub fn on_demand_input(size: (u32, u32)) -> LayerOptions {
+        LayerOptions {
+            layer: Layer::Top,
+            anchor: Anchor::empty(),
+            size,
+            exclusive_zone: ExclusiveZone::Fixed(0),
+            margin: (0, 0, 0, 0),
+            keyboard_interactivity: KeyboardInteractivity::OnDemand,
+        }
+    }
+}
+
+// Compute the exclusive zone of a surface, resolving `ExclusiveZone::Auto`
+fn exclusive_zone(zone: ExclusiveZone, anchor: Anchor, size: (u32, u32)) -> i32 {
+    let edges = |axis: Anchor| (anchor & axis).bits().count_ones();
+    let horizontal = edges(Anchor::Left | Anchor::Right);
+    let vertical = edges(Anchor::Top | Anchor::Bottom);
+    match zone {
+        ExclusiveZone::Fixed(zone) => zone,
+        // Anchored to the left or right edge, possibly stretched along it
+        ExclusiveZone::Auto if horizontal == 1 && vertical != 1 => size.0 as i32,
+        // Anchored to the top or bottom edge, possibly stretched along it
+        ExclusiveZone::Auto if vertical == 1 && horizontal != 1 => size.1 as i32,
+        ExclusiveZone::Auto => 0,
+    }
+}
+
+struct LayerInner {
+    anchor: Anchor,
+    size: (u32, u32),
+    exclusive_zone: ExclusiveZone,
+}
+
+impl LayerInner {
+    fn update_exclusive_zone(&self, surface: &ZwlrLayerSurfaceV1) {
+        if self.exclusive_zone == ExclusiveZone::Auto {
+            surface.set_exclusive_zone(exclusive_zone(self.exclusive_zone, self.anchor, self.size));
+        }
+    }
+}
+
+/// A layer surface
+///
+/// It is created from a wayland surface, which must not have any role or buffer attached yet.
+/// The initial state is committed right away, and you need to wait for the first `Configure`
+/// event before drawing.
+pub struct LayerSurface {
+    surface: wl_surface::WlSurface,
+    layer_surface: ZwlrLayerSurfaceV1,
+    inner: Rc<RefCell<LayerInner>>,
+}
+
+impl LayerSurface {
+    /// Create a new layer surface
+    ///
+    /// If `output` is `None`, the compositor chooses the output to show the surface on.
+    /// The `namespace` describes the purpose of the surface, like `"panel"` or `"wallpaper"`.
+    pub fn new<F>(
+        layer_shell: &Attached<ZwlrLayerShellV1>,
+        surface: wl_surface::WlSurface,
+        output: Option<&wl_output::WlOutput>,
+        namespace: String,
+        options: LayerOptions,
+        mut callback: F,
+    ) -> LayerSurface
+    where
+        F: FnMut(Event, DispatchData) + 'static,
+    {
+        let layer_surface =
+            layer_shell.get_layer_surface(&surface, output, options.layer, namespace);
+
+        let inner = Rc::new(RefCell::new(LayerInner {
+            anchor: options.anchor,
+            size: options.size,
+            exclusive_zone: options.exclusive_zone,
+        }));
+
+        let layer_inner = inner.clone();
+        layer_surface.quick_assign(move |layer_surface, event, ddata| match event {
+            zwlr_layer_surface_v1::Event::Configure { serial, width, height } => {
+                layer_surface.ack_configure(serial);
+                {
+                    let mut inner = layer_inner.borrow_mut();
+                    if inner.size != (width, height) {
+                        inner.size = (width, height);
+                        inner.update_exclusive_zone(&layer_surface);
+                    }
+                }
+                callback(Event::Configure { new_size: (width, height) }, ddata);
+            }
+            zwlr_layer_surface_v1::Event::Closed => callback(Event::Closed, ddata),
+            _ => unreachable!(),
+        });
+
+        let keyboard_interactivity =
+            supported_interactivity(&layer_surface, options.keyboard_interactivity);
+
+        layer_surface.set_size(options.size.0, options.size.1);
+        layer_surface.set_anchor(options.anchor);
+        layer_surface.set_exclusive_zone(exclusive_zone(
+            options.exclusive_zone,
+            options.anchor,
+            options.size,
+        ));
+        let (top, right, bottom, left) = options.margin;
+        layer_surface.set_margin(top, right, bottom, left);
+        layer_surface.set_keyboard_interactivity(keyboard_interactivity);
+        surface.commit();
+
+        LayerSurface { surface, layer_surface: layer_surface.detach(), inner }
+    }
+
+    /// Access the wayland surface of this layer surface
+    pub fn surface(&self) -> &wl_surface::WlSurface {
+        &self.surface
+    }
+
+    /// Access the `zwlr_layer_surface_v1` object of this layer surface
+    pub fn layer_surface(&self) -> &ZwlrLayerSurfaceV1 {
+        &self.layer_surface
+    }
+
+    /// Request a new size for the surface
+    ///
+    /// With an automatic exclusive zone, it is updated to match the new size.
+    ///
+    /// The change is applied on the next commit of the surface.
+    pub fn set_size(&self, width: u32, height: u32) {
+        self.layer_surface.set_size(width, height);
+        let mut inner = self.inner.borrow_mut();
+        inner.size = (width, height);
+        inner.update_exclusive_zone(&self.layer_surface);
+    }
+
+    /// Change the edges the surface is anchored to
+    ///
+    /// The change is applied on the next commit of the surface.
+    pub fn set_anchor(&self, anchor: Anchor) {
+        self.layer_surface.set_anchor(anchor);
+        let mut inner = self.inner.borrow_mut();
+        inner.anchor = anchor;
+        inner.update_exclusive_zone(&self.layer_surface);
+    }
+
+    /// Change the exclusive zone of the surface
+    ///
+    /// The change is applied on the next commit of the surface.
+    pub fn set_exclusive_zone(&self, zone: ExclusiveZone) {
+        let mut inner = self.inner.borrow_mut();
+        inner.exclusive_zone = zone;
+        self.layer_surface.set_exclusive_zone(exclusive_zone(zone, inner.anchor, inner.size));
+    }
+
+    /// Change the margins of the surface, in the order top, right, bottom, left
+    ///
+    /// The change is applied on the next commit of the surface.
+    pub fn set_margin(&self, top: i32, right: i32, bottom: i32, left: i32) {
+        self.layer_surface.set_margin(top, right, bottom, left);
+    }
+
+    /// Change whether the surface receives keyboard events
+    ///
+    /// The change is applied on the next commit of the surface. Like with
+    /// [`LayerOptions::on_demand_input`](struct.LayerOptions.html#method.on_demand_input),
+    /// on-demand interactivity falls back to exclusive with compositors older than version 4
+    /// of the protocol.
+    pub fn set_keyboard_interactivity(&self, interactivity: KeyboardInteractivity) {
+        let interactivity = supported_interactivity(&self.layer_surface, interactivity);
+        self.layer_surface.set_keyboard_interactivity(interactivity);
+    }
+}
+
+// On-demand keyboard interactivity only exists since version 4 of the protocol
+fn supported_interactivity(
+    layer_surface: &zwlr_layer_surface_v1::ZwlrLayerSurfaceV1,
+    interactivity: KeyboardInteractivity,
+) -> KeyboardInteractivity {
+    match interactivity {
+        KeyboardInteractivity::OnDemand if layer_surface.as_ref().version() < 4 => {
+            KeyboardInteractivity::Exclusive
+        }
+        other => other,
+    }
+}
+
+impl Drop for LayerSurface {
+    fn drop(&mut self) {
+        self.layer_surface.destroy();
+    }
+}
+
+impl fmt::Debug for LayerSurface {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inner = self.inner.borrow();
+        f.debug_struct("LayerSurface")
+            .field("surface", &self.surface)
+            .field("layer_surface", &self.layer_surface)
+            .field("anchor", &inner.anchor)
+            .field("size", &inner.size)
+            .field("exclusive_zone", &inner.exclusive_zone)
+            .finish()
+    }
+}
@@ -33,27 +33,50 @@ extern crate dlib;
 pub mod reexports {
     #[cfg(feature = "calloop")]
     pub use calloop;
+    pub use memmap2;
     pub use wayland_client as client;
     pub use wayland_protocols as protocols;
 }
 
+pub mod activation;
 pub mod data_device;
 pub mod environment;
+pub mod geometry;
+pub mod idle;
+pub mod layer;
 mod lazy_global;
 pub mod output;
+pub mod presentation_time;
 pub mod primary_selection;
+pub mod quickstart;
 pub mod seat;
 pub mod shell;
 pub mod shm;
+mod strict;
+#[cfg(feature = "test-harness")]
+pub mod test_harness;
+pub mod viewport;
 pub mod window;
 
+mod damage;
+mod error_policy;
 #[cfg(feature = "calloop")]
 mod event_loop;
+mod frame_throttle;
+mod response;
 mod surface;
 
+pub use damage::{attach_with_damage, merge_damage, DamageRect, SurfaceUpdater};
+pub use error_policy::{
+    error_policy, set_error_policy, ErrorPolicy, InternalError, InternalErrorKind,
+};
 #[cfg(feature = "calloop")]
-pub use event_loop::WaylandSource;
-pub use surface::{get_surface_outputs, get_surface_scale_factor};
+pub use event_loop::{DispatchStats, IdleHandle, WaylandSource};
+pub use frame_throttle::FrameThrottle;
+pub use response::{PendingResponse, Responder, ResponseCancelled};
+pub use surface::{
+    defer_destroy, get_surface_outputs, get_surface_scale_factor, DeferredDestroy, Destroyable,
+};
 
 #[macro_export]
 /// Declare a batteries-included SCTK environment
@@ -68,6 +91,7 @@ pub use surface::{get_surface_outputs, get_surface_scale_factor};
 /// - `wl_compositor` as a [`SimpleGlobal`](environment/struct.SimpleGlobal.html)
 /// - `wl_data_device_manager` as a [`DataDeviceHandler`](data_device/struct.DataDeviceHandler.html)
 /// - `wl_output` with the [`OutputHandler`](output/struct.OutputHandler.html)
+/// - `zxdg_output_manager_v1` with the [`XdgOutputHandler`](output/struct.XdgOutputHandler.html)
 /// - `wl_seat` with the [`SeatHandler`](seat/struct.SeatHandler.html)
 /// - `wl_subcompositor` as a [`SimpleGlobal`](environment/struct.SimpleGlobal.html)
 /// - `wl_shm` as a [`ShmHandler`](shm/struct.ShmHandler.html)
@@ -161,6 +185,7 @@ macro_rules! default_environment {
             sctk_shm: $crate::shm::ShmHandler,
             // output
             sctk_outputs: $crate::output::OutputHandler,
+            sctk_xdg_outputs: $crate::output::XdgOutputHandler,
             // seat
             sctk_seats: $crate::seat::SeatHandler,
             // data device
@@ -191,6 +216,10 @@ macro_rules! default_environment {
             {
                 self.sctk_seats.listen(f)
             }
+
+            fn restrict_to_seat(&mut self, name: Option<String>) {
+                self.sctk_seats.restrict_to_seat(name)
+            }
         }
 
         // Output utility
@@ -225,6 +254,38 @@ macro_rules! default_environment {
             ) -> Result<(), $crate::MissingGlobal> {
                 self.sctk_data_device_manager.with_device(seat, f)
             }
+
+            fn set_clipboard_policy(&self, policy: $crate::data_device::ClipboardPolicy) {
+                self.sctk_data_device_manager.set_clipboard_policy(policy)
+            }
+
+            fn clipboard_seats(
+                &self,
+                seat: &$crate::reexports::client::protocol::wl_seat::WlSeat,
+            ) -> Result<Vec<$crate::reexports::client::protocol::wl_seat::WlSeat>, $crate::MissingGlobal> {
+                self.sctk_data_device_manager.clipboard_seats(seat)
+            }
+
+            fn with_clipboard<F, T>(
+                &self,
+                seat: &$crate::reexports::client::protocol::wl_seat::WlSeat,
+                f: F,
+            ) -> Result<T, $crate::MissingGlobal>
+            where F: FnOnce(Option<&$crate::data_device::DataOffer>) -> T
+            {
+                self.sctk_data_device_manager.with_clipboard(seat, f)
+            }
+        }
+
+        // Shutdown utility
+        impl $crate::environment::ShutdownHandling for $env_name {
+            fn shutdown(&mut self) {
+                // devices before the seats they belong to
+                self.sctk_data_device_manager.shutdown();
+                self.sctk_primary_selection_manager.shutdown();
+                self.sctk_seats.shutdown();
+                self.sctk_outputs.shutdown();
+            }
         }
 
         // Primary selection utility
@@ -254,6 +315,8 @@ macro_rules! default_environment {
                 $crate::reexports::client::protocol::wl_subcompositor::WlSubcompositor => sctk_subcompositor,
                 // shm
                 $crate::reexports::client::protocol::wl_shm::WlShm => sctk_shm,
+                // output
+                $crate::reexports::protocols::unstable::xdg_output::v1::client::zxdg_output_manager_v1::ZxdgOutputManagerV1 => sctk_xdg_outputs,
                 // data device
                 $crate::reexports::client::protocol::wl_data_device_manager::WlDataDeviceManager => sctk_data_device_manager,
                 // primary selection
@@ -347,13 +410,15 @@ macro_rules! new_default_environment {
             let mut sctk_seats = $crate::seat::SeatHandler::new();
             let sctk_data_device_manager = $crate::data_device::DataDeviceHandler::init(&mut sctk_seats);
             let sctk_primary_selection_manager = $crate::primary_selection::PrimarySelectionHandler::init(&mut sctk_seats);
+            let (sctk_outputs, sctk_xdg_outputs) = $crate::output::XdgOutputHandler::new_output_handlers();
 
             let display = $crate::reexports::client::Proxy::clone(&$display);
             let env = $crate::environment::Environment::new(&display.attach($queue.token()), &mut $queue,$env_name {
                 sctk_compositor: $crate::environment::SimpleGlobal::new(),
                 sctk_subcompositor: $crate::environment::SimpleGlobal::new(),
                 sctk_shm: $crate::shm::ShmHandler::new(),
-                sctk_outputs: $crate::output::OutputHandler::new(),
+                sctk_outputs,
+                sctk_xdg_outputs,
                 sctk_seats,
                 sctk_data_device_manager,
                 sctk_primary_selection_manager,
@@ -67,10 +67,10 @@ pub struct OutputInfo {
     /// not assume that the name is a reflection of an underlying DRM connector,
     /// X11 connection, etc.
     ///
-    /// Note that this is not filled in by version 3 of the wl_output protocol,
-    /// but it has been proposed for inclusion in version 4.  Until then, it is
-    /// only filled in if your environment has an [XdgOutputHandler] global
-    /// handler for [ZxdgOutputManagerV1].
+    /// Note that this is only filled in by version 4 of the wl_output protocol, or
+    /// if your environment has an [XdgOutputHandler] global handler for
+    /// [ZxdgOutputManagerV1], which is the case of the
+    /// [`default_environment!`](../macro.default_environment.html).
     pub name: String,
     /// The description of this output as advertised by the server
     ///
@@ -79,10 +79,10 @@ pub struct OutputInfo {
     /// wl_output globals. Examples might include 'Foocorp 11" Display' or
     /// 'Virtual X11 output via :1'.
     ///
-    /// Note that this is not filled in by version 3 of the wl_output protocol,
-    /// but it has been proposed for inclusion in version 4.  Until then, it is
-    /// only filled in if your environment has an [XdgOutputHandler] global
-    /// handler for [ZxdgOutputManagerV1].
+    /// Note that this is only filled in by version 4 of the wl_output protocol, or
+    /// if your environment has an [XdgOutputHandler] global handler for
+    /// [ZxdgOutputManagerV1], which is the case of the
+    /// [`default_environment!`](../macro.default_environment.html).
     pub description: String,
     /// Location of the top-left corner of this output in compositor
     /// space
@@ -92,6 +92,20 @@ pub struct OutputInfo {
     pub location: (i32, i32),
     /// Physical dimensions of this output, in unspecified units
     pub physical_size: (i32, i32),
+    /// Location of the top-left corner of this output in the global compositor space, in
+    /// logical pixels
+    ///
+    /// Unlike `location`, this accounts for the scaling and transform of the other outputs.
+    /// It is only filled in if your environment has an [XdgOutputHandler] global handler for
+    /// [ZxdgOutputManagerV1] and the compositor supports it.
+    pub logical_position: Option<(i32, i32)>,
+    /// Size of this output in the global compositor space, in logical pixels
+    ///
+    /// This is the size of the current mode after applying the transform and the scale
+    /// (fractional or not) of the output. It is only filled in if your environment has an
+    /// [XdgOutputHandler] global handler for [ZxdgOutputManagerV1] and the compositor
+    /// supports it.
+    pub logical_size: Option<(i32, i32)>,
     /// The subpixel layout for this output
     pub subpixel: Subpixel,
     /// The current transformation applied to this output
@@ -116,6 +130,13 @@ pub struct OutputInfo {
     /// call its `release()` method if you don't plan to use it any
     /// longer.
     pub obsolete: bool,
+    /// Has all the information about this output been received
+    ///
+    /// The server sends the properties of an output in several events, the information is
+    /// complete once it signaled it sent all of them. Only
+    /// [`with_partial_output_info`](fn.with_partial_output_info.html) and the output listeners
+    /// can give you incomplete information, to make provisional decisions early.
+    pub complete: bool,
 }
 
 impl OutputInfo {
@@ -128,32 +149,95 @@ impl OutputInfo {
             description: String::new(),
             location: (0, 0),
             physical_size: (0, 0),
+            logical_position: None,
+            logical_size: None,
             subpixel: Subpixel::Unknown,
             transform: Transform::Normal,
             scale_factor: 1,
             modes: Vec::new(),
             obsolete: false,
+            complete: false,
         }
     }
+
+    /// The location of this output in compositor space, see the `location` field
+    pub fn position(&self) -> crate::geometry::Position<i32> {
+        self.location.into()
+    }
+
+    /// The logical position and size of this output, if they were advertised by xdg-output
+    pub fn logical_geometry(
+        &self,
+    ) -> Option<(crate::geometry::Position<i32>, crate::geometry::Size<i32>)> {
+        Some((self.logical_position?.into(), self.logical_size?.into()))
+    }
+
+    /// The affine matrix of the transform of this output
+    ///
+    /// The matrix maps a position on the output as the user sees it to the matching pixel of
+    /// the current mode, in the orientation of the output device. This is the transformation
+    /// to apply when pre-rendering buffers in the orientation of the output (and advertising
+    /// it with `wl_surface.set_buffer_transform`).
+    ///
+    /// See [`transform_matrix`](fn.transform_matrix.html) for the layout of the matrix. Returns
+    /// `None` if the output has no current mode.
+    pub fn transform_matrix(&self) -> Option<[[f64; 3]; 2]> {
+        let (w, h) = self.modes.iter().find(|m| m.is_current)?.dimensions;
+        let size = match self.transform {
+            Transform::_90 | Transform::_270 | Transform::Flipped90 | Transform::Flipped270 => {
+                (h, w)
+            }
+            _ => (w, h),
+        };
+        Some(transform_matrix(self.transform, (f64::from(size.0), f64::from(size.1))))
+    }
+
+    /// The inverse of the [`transform_matrix`](#method.transform_matrix) of this output
+    ///
+    /// It maps a pixel of the current mode of the output back to a position on the output
+    /// as the user sees it. Returns `None` if the output has no current mode.
+    pub fn inverse_transform_matrix(&self) -> Option<[[f64; 3]; 2]> {
+        self.transform_matrix().map(invert_matrix)
+    }
+}
+
+/// Compute the affine matrix of an output transform
+///
+/// The matrix `[[a, b, c], [d, e, f]]` maps a position `(x, y)` to
+/// `(a * x + b * y + c, d * x + e * y + f)`.
+///
+/// It maps a position in an area of `size` as the user sees it, to the matching position
+/// in the area with the transform applied, which is the rotation counter-clockwise (after
+/// horizontal flip for flipped transforms) described by `wl_output.transform`. For rotations
+/// by 90 or 270 degrees, the width and height of the transformed area are swapped.
+pub fn transform_matrix(transform: Transform, size: (f64, f64)) -> [[f64; 3]; 2] {
+    let (w, h) = size;
+    match transform {
+        Transform::_90 => [[0.0, 1.0, 0.0], [-1.0, 0.0, w]],
+        Transform::_180 => [[-1.0, 0.0, w], [0.0, -1.0, h]],
+        Transform::_270 => [[0.0, -1.0, h], [1.0, 0.0, 0.0]],
+        Transform::Flipped => [[-1.0, 0.0, w], [0.0, 1.0, 0.0]],
+        Transform::Flipped90 => [[0.0, 1.0, 0.0], [1.0, 0.0, 0.0]],
+        Transform::Flipped180 => [[1.0, 0.0, 0.0], [0.0, -1.0, h]],
+        Transform::Flipped270 => [[0.0, -1.0, h], [-1.0, 0.0, w]],
+        _ => [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+    }
+}
+
+// Invert an affine matrix whose linear part is a rotation or symmetry
+fn invert_matrix(m: [[f64; 3]; 2]) -> [[f64; 3]; 2] {
+    // The linear part is orthogonal, so its inverse is its transpose
+    let (a, b, d, e) = (m[0][0], m[1][0], m[0][1], m[1][1]);
+    let (c, f) = (m[0][2], m[1][2]);
+    [[a, b, -(a * c + b * f)], [d, e, -(d * c + e * f)]]
 }
 
 type OutputCallback = dyn Fn(WlOutput, &OutputInfo, DispatchData) + Send + Sync;
 
 enum OutputData {
-    Ready {
-        info: OutputInfo,
-        callbacks: Vec<sync::Weak<OutputCallback>>,
-    },
-    Pending {
-        id: u32,
-        has_xdg: bool,
-        events: Vec<Event>,
-        callbacks: Vec<sync::Weak<OutputCallback>>,
-    },
-    PendingXDG {
-        info: OutputInfo,
-        callbacks: Vec<sync::Weak<OutputCallback>>,
-    },
+    Ready { info: OutputInfo, callbacks: Vec<sync::Weak<OutputCallback>> },
+    Pending { has_xdg: bool, info: OutputInfo, callbacks: Vec<sync::Weak<OutputCallback>> },
+    PendingXDG { info: OutputInfo, callbacks: Vec<sync::Weak<OutputCallback>> },
 }
 
 type OutputStatusCallback = dyn FnMut(WlOutput, &OutputInfo, DispatchData) + 'static;
@@ -181,6 +265,19 @@ impl OutputHandler {
             xdg_listener: None,
         }
     }
+
+    /// Release all the outputs
+    ///
+    /// Outputs are released with `wl_output.release` when the server supports it, and
+    /// forgotten otherwise. This is intended to be used when exiting, see
+    /// [`Environment::shutdown`](../environment/struct.Environment.html#method.shutdown).
+    pub fn shutdown(&mut self) {
+        for (_, output) in self.outputs.drain(..) {
+            if output.as_ref().version() >= 3 {
+                output.release();
+            }
+        }
+    }
 }
 
 impl crate::environment::MultiGlobalHandler<WlOutput> for OutputHandler {
@@ -191,8 +288,8 @@ impl crate::environment::MultiGlobalHandler<WlOutput> for OutputHandler {
         version: u32,
         _: DispatchData,
     ) {
-        // We currently support wl_output up to version 3
-        let version = std::cmp::min(version, 3);
+        // We currently support wl_output up to version 4
+        let version = std::cmp::min(version, 4);
         let output = registry.bind::<WlOutput>(version, id);
         let has_xdg;
         if let Some(xdg) = self.xdg_listener.as_ref().and_then(rc::Weak::upgrade) {
@@ -204,12 +301,19 @@ impl crate::environment::MultiGlobalHandler<WlOutput> for OutputHandler {
             // wl_output.done event was only added at version 2
             // In case of an old version 1, we just behave as if it was send at the start
             output.as_ref().user_data().set_threadsafe(|| {
-                Mutex::new(OutputData::Pending { id, has_xdg, events: vec![], callbacks: vec![] })
+                Mutex::new(OutputData::Pending {
+                    has_xdg,
+                    info: OutputInfo::new(id),
+                    callbacks: vec![],
+                })
             });
         } else {
-            output.as_ref().user_data().set_threadsafe(|| {
-                Mutex::new(OutputData::Ready { info: OutputInfo::new(id), callbacks: vec![] })
-            });
+            let mut info = OutputInfo::new(id);
+            info.complete = true;
+            output
+                .as_ref()
+                .user_data()
+                .set_threadsafe(|| Mutex::new(OutputData::Ready { info, callbacks: vec![] }));
         }
         let status_listeners_handle = self.status_listeners.clone();
         let xdg_listener_handle = self.xdg_listener.clone();
@@ -265,11 +369,12 @@ fn process_output_event(
         .expect("SCTK: wl_output has invalid UserData");
     let mut udata = udata_mutex.lock().unwrap();
     if let Event::Done = event {
-        let (id, has_xdg, pending_events, mut callbacks) = match *udata {
-            OutputData::Pending { id, has_xdg, events: ref mut v, callbacks: ref mut cb } => {
-                (id, has_xdg, std::mem::take(v), std::mem::take(cb))
+        let (has_xdg, mut info, mut callbacks) = match *udata {
+            OutputData::Pending { has_xdg, ref info, callbacks: ref mut cb } => {
+                (has_xdg, info.clone(), std::mem::take(cb))
             }
             OutputData::PendingXDG { ref mut info, ref mut callbacks } => {
+                info.complete = true;
                 notify(&output, info, ddata.reborrow(), callbacks);
                 notify_status_listeners(&output, info, ddata, listeners);
                 let info = info.clone();
@@ -284,23 +389,23 @@ fn process_output_event(
                 return;
             }
         };
-        let mut info = OutputInfo::new(id);
-        for evt in pending_events {
-            merge_event(&mut info, evt);
-        }
+        // the information is only complete once the xdg_output sent its own, if any
+        let pending_xdg = match xdg_listener.as_ref().and_then(rc::Weak::upgrade) {
+            Some(xdg) => has_xdg || xdg.borrow_mut().new_xdg_output(&output, listeners),
+            None => false,
+        };
+        info.complete = !pending_xdg;
         notify(&output, &info, ddata.reborrow(), &mut callbacks);
-        if let Some(xdg) = xdg_listener.as_ref().and_then(rc::Weak::upgrade) {
-            if has_xdg || xdg.borrow_mut().new_xdg_output(&output, listeners) {
-                *udata = OutputData::PendingXDG { info, callbacks };
-                return;
-            }
+        if pending_xdg {
+            *udata = OutputData::PendingXDG { info, callbacks };
+            return;
         }
         notify_status_listeners(&output, &info, ddata, listeners);
         *udata = OutputData::Ready { info, callbacks };
     } else {
         match *udata {
-            OutputData::Pending { events: ref mut v, .. } => v.push(event),
-            OutputData::PendingXDG { ref mut info, .. }
+            OutputData::Pending { ref mut info, .. }
+            | OutputData::PendingXDG { ref mut info, .. }
             | OutputData::Ready { ref mut info, .. } => {
                 merge_event(info, event);
             }
@@ -323,7 +428,7 @@ fn make_obsolete(
     if let Some(xdg) = xdg_listener.as_ref().and_then(rc::Weak::upgrade) {
         xdg.borrow_mut().destroy_xdg_output(output);
     }
-    let (id, mut callbacks) = match *udata {
+    let (mut info, mut callbacks) = match *udata {
         OutputData::PendingXDG { ref mut info, ref mut callbacks }
         | OutputData::Ready { ref mut info, ref mut callbacks } => {
             info.obsolete = true;
@@ -331,9 +436,10 @@ fn make_obsolete(
             notify_status_listeners(output, info, ddata, listeners);
             return;
         }
-        OutputData::Pending { id, callbacks: ref mut cb, .. } => (id, std::mem::take(cb)),
+        OutputData::Pending { ref info, callbacks: ref mut cb, .. } => {
+            (info.clone(), std::mem::take(cb))
+        }
     };
-    let mut info = OutputInfo::new(id);
     info.obsolete = true;
     notify(output, &info, ddata.reborrow(), &mut callbacks);
     notify_status_listeners(output, &info, ddata, listeners);
@@ -384,6 +490,12 @@ fn merge_event(info: &mut OutputInfo, event: Event) {
                 })
             }
         }
+        Event::Name { name } => {
+            info.name = name;
+        }
+        Event::Description { description } => {
+            info.description = description;
+        }
         // ignore all other events
         _ => (),
     }
@@ -446,6 +558,28 @@ pub fn with_output_info<T, F: FnOnce(&OutputInfo) -> T>(output: &WlOutput, f: F)
     }
 }
 
+/// Access the info associated with this output, even if it is not complete yet
+///
+/// This is similar to [`with_output_info`](fn.with_output_info.html), but the closure is also
+/// invoked before the server sent all the information about the output, with the information
+/// received so far. The `complete` field of the [`OutputInfo`](struct.OutputInfo.html)
+/// indicates whether the information is complete. This lets you make provisional decisions
+/// early during the startup of your program, and refine them once the information is complete.
+///
+/// If the provided `WlOutput` is not managed by SCTK, `None` is returned.
+pub fn with_partial_output_info<T, F: FnOnce(&OutputInfo) -> T>(
+    output: &WlOutput,
+    f: F,
+) -> Option<T> {
+    let udata_mutex = output.as_ref().user_data().get::<Mutex<OutputData>>()?;
+    let udata = udata_mutex.lock().unwrap();
+    match *udata {
+        OutputData::Pending { ref info, .. }
+        | OutputData::PendingXDG { ref info, .. }
+        | OutputData::Ready { ref info, .. } => Some(f(info)),
+    }
+}
+
 /// Add a listener to this output
 ///
 /// The provided closure will be called whenever a property of the output changes,
@@ -475,6 +609,87 @@ pub fn add_output_listener<F: Fn(WlOutput, &OutputInfo, DispatchData) + Send + S
     OutputListener { _cb: arc }
 }
 
+/// The properties of an output that changed
+///
+/// This is given to the listeners added with
+/// [`add_output_update_listener`](fn.add_output_update_listener.html), so that they can
+/// only react to the changes they care about.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct OutputInfoUpdate {
+    /// The current mode, or the list of modes, changed
+    pub mode: bool,
+    /// The transform changed
+    pub transform: bool,
+    /// The scale factor changed
+    pub scale_factor: bool,
+    /// The location, logical position, physical or logical size, or subpixel layout changed
+    pub geometry: bool,
+    /// The make or model changed
+    pub make_model: bool,
+    /// The name or description changed
+    pub name: bool,
+    /// The output was removed by the compositor
+    pub obsolete: bool,
+}
+
+impl OutputInfoUpdate {
+    /// Compute the changes between two versions of the information of an output
+    pub fn between(old: &OutputInfo, new: &OutputInfo) -> OutputInfoUpdate {
+        let same_mode = |a: &Mode, b: &Mode| {
+            a.dimensions == b.dimensions
+                && a.refresh_rate == b.refresh_rate
+                && a.is_current == b.is_current
+                && a.is_preferred == b.is_preferred
+        };
+        OutputInfoUpdate {
+            mode: old.modes.len() != new.modes.len()
+                || old.modes.iter().zip(&new.modes).any(|(a, b)| !same_mode(a, b)),
+            transform: old.transform != new.transform,
+            scale_factor: old.scale_factor != new.scale_factor,
+            geometry: old.location != new.location
+                || old.logical_position != new.logical_position
+                || old.physical_size != new.physical_size
+                || old.logical_size != new.logical_size
+                || old.subpixel != new.subpixel,
+            make_model: old.make != new.make || old.model != new.model,
+            name: old.name != new.name || old.description != new.description,
+            obsolete: old.obsolete != new.obsolete,
+        }
+    }
+
+    /// Whether nothing changed
+    pub fn is_empty(&self) -> bool {
+        *self == OutputInfoUpdate::default()
+    }
+}
+
+/// Add a listener to this output, receiving the changes of its properties
+///
+/// This is similar to [`add_output_listener`](fn.add_output_listener.html), but the closure
+/// also receives an [`OutputInfoUpdate`](struct.OutputInfoUpdate.html) listing the properties
+/// that changed since it was last invoked (or since the listener was added), and it is not
+/// invoked if none changed.
+pub fn add_output_update_listener<F>(output: &WlOutput, f: F) -> OutputListener
+where
+    F: Fn(WlOutput, &OutputInfo, OutputInfoUpdate, DispatchData) + Send + Sync + 'static,
+{
+    let previous = with_partial_output_info(output, |info| info.clone());
+    let previous = Mutex::new(previous);
+    add_output_listener(output, move |output, info, ddata| {
+        let mut previous = previous.lock().unwrap();
+        let update = match *previous {
+            Some(ref old) => OutputInfoUpdate::between(old, info),
+            None => OutputInfoUpdate::between(&OutputInfo::new(info.id), info),
+        };
+        *previous = Some(info.clone());
+        drop(previous);
+        if !update.is_empty() {
+            f(output, info, update, ddata);
+        }
+    })
+}
+
 /// A handle to an output listener callback
 ///
 /// Dropping it disables the associated callback and frees the closure.
@@ -551,16 +766,35 @@ impl<E: crate::environment::MultiGlobalHandler<WlOutput>> crate::environment::En
     pub fn get_all_outputs(&self) -> Vec<WlOutput> {
         self.get_all_globals::<WlOutput>().into_iter().map(|o| o.detach()).collect()
     }
+
+    /// Find an output by its name, like `HDMI-A-1`
+    ///
+    /// Returns the output along with its information. Names are only known if the environment
+    /// has an [`XdgOutputHandler`](../output/struct.XdgOutputHandler.html), see the `name`
+    /// field of [`OutputInfo`](../output/struct.OutputInfo.html).
+    pub fn output_info_by_name(&self, name: &str) -> Option<(WlOutput, OutputInfo)> {
+        self.get_all_outputs().into_iter().find_map(|output| {
+            let info = with_output_info(&output, |info| info.clone())?;
+            if info.name == name && !info.obsolete {
+                Some((output, info))
+            } else {
+                None
+            }
+        })
+    }
 }
 
 /// A handler for `zxdg_output_manager_v1`
 ///
 /// This handler adds additional information to the OutputInfo struct that is
-/// available through the xdg_output interface.  Because this requires binding
-/// the two handlers together when they are being created, it does not work with
-/// [`new_default_environment!`](../macro.new_default_environment.html); you
-/// must use [`default_environment!`](../macro.default_environment.html) and
-/// create the [OutputHandler] outside the constructor.
+/// available through the xdg_output interface: the logical position and size of
+/// the outputs, and their name and description with versions of `wl_output` older
+/// than 4.  It is automatically included if you use the
+/// [`new_default_environment!`](../macro.new_default_environment.html).
+///
+/// Because this requires binding the two handlers together when they are being
+/// created, with [`environment!`](../macro.environment.html) you must create the
+/// [OutputHandler] outside the constructor:
 ///
 /// ```no_compile
 ///  let (sctk_outputs, sctk_xdg_out) = smithay_client_toolkit::output::XdgOutputHandler::new_output_handlers();
@@ -635,6 +869,10 @@ impl XdgOutputHandlerInner {
     }
 }
 
+fn output_version(output: &WlOutput) -> u32 {
+    output.as_ref().version()
+}
+
 fn process_xdg_event(
     wl_out: &WlOutput,
     event: zxdg_output_v1::Event,
@@ -654,13 +892,24 @@ fn process_xdg_event(
         OutputData::Pending { .. } => unreachable!(),
     };
     match event {
-        Event::Name { name } => {
+        Event::LogicalPosition { x, y } => {
+            info.logical_position = Some((x, y));
+        }
+        Event::LogicalSize { width, height } => {
+            info.logical_size = Some((width, height));
+        }
+        // with wl_output version 4, the name and description are given by the output itself
+        Event::Name { name } if output_version(wl_out) < 4 => {
             info.name = name;
         }
-        Event::Description { description } => {
+        Event::Description { description } if output_version(wl_out) < 4 => {
             info.description = description;
         }
         Event::Done => {
+            // only sent before version 3, the changes are then applied by wl_output.done
+            if pending {
+                info.complete = true;
+            }
             notify(wl_out, info, ddata.reborrow(), callbacks);
             if pending {
                 notify_status_listeners(wl_out, info, ddata, listeners);
@@ -0,0 +1,195 @@
+//! Feedback on the presentation of the surface contents
+//!
+//! The `wp_presentation` protocol reports when the content of each commit was actually shown
+//! to the user, along with the refresh rate of the output, so that clients can pace their
+//! frames precisely and measure their latency. Request a feedback with
+//! [`request_feedback`](fn.request_feedback.html) before committing a surface, and its
+//! callback receives a [`Presentation`](enum.Presentation.html) once the content of the commit
+//! was displayed or discarded.
+//!
+//! The `wp_presentation` global is not part of the
+//! [`default_environment!`](../macro.default_environment.html), add a
+//! [`PresentationTimeState`](struct.PresentationTimeState.html) to your environment to bind
+//! it:
+//!
+//! ```no_run
+//! # use smithay_client_toolkit::{environment, presentation_time::{PresentationTimeState, WpPresentation}};
+//! struct MyEnv {
+//!     presentation: PresentationTimeState,
+//! }
+//!
+//! environment!(MyEnv,
+//!     singles = [
+//!         WpPresentation => presentation,
+//!     ],
+//!     multis = []
+//! );
+//! ```
+
+use std::{cell::Cell, time::Duration};
+
+use wayland_client::{
+    protocol::{wl_output, wl_registry, wl_surface},
+    Attached, DispatchData,
+};
+
+use wayland_protocols::presentation_time::client::wp_presentation;
+use wayland_protocols::presentation_time::client::wp_presentation_feedback;
+pub use wayland_protocols::presentation_time::client::{
+    wp_presentation::WpPresentation, wp_presentation_feedback::Kind,
+};
+
+use crate::environment::{Environment, GlobalHandler};
+use crate::MissingGlobal;
+
+// the clock of the timestamps, stored in the user data of the `wp_presentation`
+type ClockId = Cell<Option<u32>>;
+
+/// A handler for the `wp_presentation` global
+#[derive(Debug, Default)]
+pub struct PresentationTimeState {
+    presentation: Option<Attached<WpPresentation>>,
+}
+
+impl PresentationTimeState {
+    /// Create a new handler
+    pub fn new() -> PresentationTimeState {
+        PresentationTimeState::default()
+    }
+}
+
+impl GlobalHandler<WpPresentation> for PresentationTimeState {
+    fn created(
+        &mut self,
+        registry: Attached<wl_registry::WlRegistry>,
+        id: u32,
+        _version: u32,
+        _: DispatchData,
+    ) {
+        let presentation = registry.bind::<WpPresentation>(1, id);
+        presentation.as_ref().user_data().set(|| ClockId::new(None));
+        presentation.quick_assign(|presentation, event, _| match event {
+            wp_presentation::Event::ClockId { clk_id } => {
+                if let Some(clock) = presentation.as_ref().user_data().get::<ClockId>() {
+                    clock.set(Some(clk_id));
+                }
+            }
+            _ => unreachable!(),
+        });
+        self.presentation = Some((*presentation).clone());
+    }
+
+    fn get(&self) -> Option<Attached<WpPresentation>> {
+        self.presentation.clone()
+    }
+}
+
+/// The timing of a presented frame
+#[derive(Debug, Clone, PartialEq)]
+pub struct PresentationInfo {
+    /// The time at which the frame started being shown
+    pub time: Duration,
+    /// The clock of `time`, like `CLOCK_MONOTONIC`, as a `clockid_t`
+    ///
+    /// This is `None` if the compositor did not announce it yet.
+    pub clock_id: Option<u32>,
+    /// The duration of a refresh cycle of the output, `None` if it is unknown or variable
+    pub refresh: Option<Duration>,
+    /// The value of the vertical retrace counter of the output when the frame was shown
+    ///
+    /// This is only provided if the output has such a counter, when `kind` contains
+    /// `Kind::Vsync`.
+    pub sequence: Option<u64>,
+    /// How the frame was presented
+    pub kind: Kind,
+    /// The outputs the timing relates to
+    pub outputs: Vec<wl_output::WlOutput>,
+}
+
+/// The outcome of the presentation of a commit
+#[derive(Debug, Clone, PartialEq)]
+pub enum Presentation {
+    /// The content of the commit was shown
+    Presented(PresentationInfo),
+    /// The content of the commit was never shown, for example because it was replaced by a
+    /// later commit before the next refresh, or because the surface is not visible
+    Discarded,
+}
+
+/// Request a feedback on the presentation of the next commit of a surface
+///
+/// The callback is invoked once, when the content of the next commit is shown or discarded.
+/// A feedback can be requested for every commit, for example to measure the latency of each
+/// frame.
+pub fn request_feedback<F>(
+    presentation: &Attached<WpPresentation>,
+    surface: &wl_surface::WlSurface,
+    callback: F,
+) where
+    F: FnOnce(Presentation, DispatchData) + 'static,
+{
+    let my_presentation = presentation.detach();
+    let mut callback = Some(callback);
+    let mut outputs = Vec::new();
+    presentation.feedback(surface).quick_assign(move |_, event, ddata| {
+        let presentation = match event {
+            wp_presentation_feedback::Event::SyncOutput { output } => {
+                outputs.push(output);
+                return;
+            }
+            wp_presentation_feedback::Event::Presented {
+                tv_sec_hi,
+                tv_sec_lo,
+                tv_nsec,
+                refresh,
+                seq_hi,
+                seq_lo,
+                flags,
+            } => {
+                let secs = (u64::from(tv_sec_hi) << 32) | u64::from(tv_sec_lo);
+                let sequence = (u64::from(seq_hi) << 32) | u64::from(seq_lo);
+                Presentation::Presented(PresentationInfo {
+                    time: Duration::new(secs, tv_nsec),
+                    clock_id: my_presentation
+                        .as_ref()
+                        .user_data()
+                        .get::<ClockId>()
+                        .and_then(Cell::get),
+                    refresh: if refresh > 0 {
+                        Some(Duration::from_nanos(u64::from(refresh)))
+                    } else {
+                        None
+                    },
+                    sequence: if flags.contains(Kind::Vsync) { Some(sequence) } else { None },
+                    kind: flags,
+                    outputs: std::mem::take(&mut outputs),
+                })
+            }
+            wp_presentation_feedback::Event::Discarded => Presentation::Discarded,
+            _ => unreachable!(),
+        };
+        // the feedback object is destroyed by the server after these events
+        if let Some(callback) = callback.take() {
+            callback(presentation, ddata);
+        }
+    });
+}
+
+impl<E: GlobalHandler<WpPresentation>> Environment<E> {
+    /// Request a feedback on the presentation of the next commit of a surface
+    ///
+    /// See [`request_feedback`](../presentation_time/fn.request_feedback.html). Returns an
+    /// error if the compositor does not support `wp_presentation`.
+    pub fn request_presentation_feedback<F>(
+        &self,
+        surface: &wl_surface::WlSurface,
+        callback: F,
+    ) -> Result<(), MissingGlobal>
+    where
+        F: FnOnce(Presentation, DispatchData) + 'static,
+    {
+        let presentation = self.get_global::<WpPresentation>().ok_or(MissingGlobal)?;
+        request_feedback(&presentation, surface, callback);
+        Ok(())
+    }
+}
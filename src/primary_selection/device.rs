@@ -17,6 +17,7 @@ use crate::primary_selection::source::PrimarySelectionSourceImpl;
 use super::PrimarySelectionDeviceManager;
 use super::PrimarySelectionOffer;
 use super::PrimarySelectionSource;
+use crate::InternalErrorKind;
 
 /// Handle to support primary selection on a given seat.
 ///
@@ -62,7 +63,11 @@ impl PrimarySelectionDeviceInner {
         if let Some(id) = self.know_offers.iter().position(|o| o.offer == offer) {
             self.selection = Some(self.know_offers.swap_remove(id));
         } else {
-            panic!("Compositor set an unknown primary offer for a primary selection.")
+            crate::error_policy::report(
+                InternalErrorKind::UnexpectedEvent,
+                "Compositor set an unknown primary offer for a primary selection.".into(),
+            );
+            self.selection = None;
         }
     }
 }
@@ -71,6 +71,18 @@ impl PrimarySelectionHandler {
 
         Self { inner, _listener: listener }
     }
+
+    /// Destroy the primary selection devices of all seats
+    ///
+    /// This is intended to be used when exiting, see
+    /// [`Environment::shutdown`](../environment/struct.Environment.html#method.shutdown).
+    pub fn shutdown(&mut self) {
+        if let PrimarySelectionDeviceManagerInitState::Ready { ref mut devices, .. } =
+            self.inner.borrow_mut().state
+        {
+            devices.clear();
+        }
+    }
 }
 
 /// An interface trait to forward the primary selection device handler capability.
@@ -135,7 +147,10 @@ impl<E: PrimarySelectionHandling> crate::environment::Environment<E> {
     {
         let manager = match self.get_primary_selection_manager() {
             Some(manager) => manager,
-            None => panic!("[SCTK] primary selection was required"),
+            None => crate::error_policy::report_fatal(
+                crate::InternalErrorKind::MissingGlobal,
+                "primary selection was required".into(),
+            ),
         };
 
         PrimarySelectionSource::new(&manager, mime_types, callback)
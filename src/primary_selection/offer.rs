@@ -12,7 +12,7 @@ use wayland_protocols::{
     },
 };
 
-use crate::data_device::ReadPipe;
+use crate::data_device::{MimeList, MimeStats, ReadPipe};
 
 /// A primary selection offer for receiving data through copy/paste.
 #[derive(Debug)]
@@ -23,12 +23,20 @@ pub struct PrimarySelectionOffer {
 
 impl PrimarySelectionOffer {
     /// Access the list of mime types proposed by this offer.
+    ///
+    /// Duplicated and invalid mime types are not part of this list, which is also
+    /// bounded, see [`MimeLimits`](../data_device/struct.MimeLimits.html).
     pub fn with_mime_types<F, T>(&self, f: F) -> T
     where
         F: FnOnce(&[String]) -> T,
     {
         let inner = self.inner.lock().unwrap();
-        f(&inner.mime_types)
+        f(&inner.mime_types.mime_types)
+    }
+
+    /// Get statistics about the mime types of this offer that were ignored.
+    pub fn mime_stats(&self) -> MimeStats {
+        self.inner.lock().unwrap().mime_types.stats
     }
 
     /// Request to receive the data of a given mime type.
@@ -106,14 +114,14 @@ impl Drop for PrimarySelectionOffer {
 }
 
 /// Inner state for `PrimarySelectionOffer`.
-#[derive(Debug, Default)]
+#[derive(Debug)]
 struct PrimarySelectionOfferInner {
-    mime_types: Vec<String>,
+    mime_types: MimeList,
 }
 
 impl PrimarySelectionOfferInner {
     fn new() -> Self {
-        Self::default()
+        Self { mime_types: MimeList::new() }
     }
 }
 
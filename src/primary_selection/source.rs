@@ -6,9 +6,9 @@ use wayland_protocols::misc::gtk_primary_selection::client::gtk_primary_selectio
     self, GtkPrimarySelectionSource,
 };
 
-use crate::data_device::WritePipe;
+use crate::data_device::{CancelFlag, WritePipe};
 
-use std::os::unix::io::FromRawFd;
+use std::sync::Arc;
 
 use wayland_client::DispatchData;
 
@@ -54,11 +54,18 @@ impl PrimarySelectionSource {
         S: Into<String>,
         It: IntoIterator<Item = S>,
     {
+        let cancelled = Arc::new(CancelFlag::default());
         match manager {
             PrimarySelectionDeviceManager::Zwp(ref manager) => {
                 let source = manager.create_source();
                 source.quick_assign(move |source, event, dispatch_data| {
-                    zwp_primary_source_imp(&source, event, dispatch_data, &mut callback);
+                    zwp_primary_source_imp(
+                        &source,
+                        event,
+                        &cancelled,
+                        dispatch_data,
+                        &mut callback,
+                    );
                 });
 
                 for mime in mime_types {
@@ -70,7 +77,13 @@ impl PrimarySelectionSource {
             PrimarySelectionDeviceManager::Gtk(ref manager) => {
                 let source = manager.create_source();
                 source.quick_assign(move |source, event, dispatch_data| {
-                    gtk_primary_source_imp(&source, event, dispatch_data, &mut callback);
+                    gtk_primary_source_imp(
+                        &source,
+                        event,
+                        &cancelled,
+                        dispatch_data,
+                        &mut callback,
+                    );
                 });
 
                 for mime in mime_types {
@@ -93,6 +106,7 @@ pub(crate) enum PrimarySelectionSourceImpl {
 fn gtk_primary_source_imp<Impl>(
     source: &GtkPrimarySelectionSource,
     event: gtk_primary_selection_source::Event,
+    cancelled: &Arc<CancelFlag>,
     dispatch_data: DispatchData,
     implem: &mut Impl,
 ) where
@@ -102,9 +116,10 @@ fn gtk_primary_source_imp<Impl>(
     let event = match event {
         Event::Send { mime_type, fd } => PrimarySelectionSourceEvent::Send {
             mime_type,
-            pipe: unsafe { FromRawFd::from_raw_fd(fd) },
+            pipe: unsafe { WritePipe::with_cancel_flag(fd, cancelled.clone()) },
         },
         Event::Cancelled => {
+            cancelled.cancel();
             source.destroy();
             PrimarySelectionSourceEvent::Cancelled
         }
@@ -117,6 +132,7 @@ fn gtk_primary_source_imp<Impl>(
 fn zwp_primary_source_imp<Impl>(
     source: &ZwpPrimarySelectionSourceV1,
     event: zwp_primary_selection_source_v1::Event,
+    cancelled: &Arc<CancelFlag>,
     dispatch_data: DispatchData,
     implem: &mut Impl,
 ) where
@@ -126,9 +142,10 @@ fn zwp_primary_source_imp<Impl>(
     let event = match event {
         Event::Send { mime_type, fd } => PrimarySelectionSourceEvent::Send {
             mime_type,
-            pipe: unsafe { FromRawFd::from_raw_fd(fd) },
+            pipe: unsafe { WritePipe::with_cancel_flag(fd, cancelled.clone()) },
         },
         Event::Cancelled => {
+            cancelled.cancel();
             source.destroy();
             PrimarySelectionSourceEvent::Cancelled
         }
@@ -0,0 +1,155 @@
+//! A zero-configuration helper to open a window
+//!
+//! [`run`](fn.run.html) takes care of everything needed to show a window drawn in software:
+//! connecting to the compositor, binding the globals, creating the window and its memory
+//! pool, following its resizes and redrawing it on the frame callbacks. It is meant for
+//! examples, tests and prototypes, and serves as a compact demonstration of how the pieces of
+//! SCTK fit together. Real applications will want to handle these themselves.
+//!
+//! ```no_run
+//! smithay_client_toolkit::quickstart::run("Hello", |canvas, (_width, _height), _time| {
+//!     // fill the window with opaque blue, in the native-endian ARGB8888 format
+//!     for pixel in canvas.chunks_exact_mut(4) {
+//!         pixel.copy_from_slice(&0xFF_00_00_FFu32.to_ne_bytes());
+//!     }
+//!     // do not draw again until the window is resized
+//!     false
+//! })
+//! .unwrap();
+//! ```
+
+use std::{
+    error::Error,
+    time::{Duration, Instant},
+};
+
+use wayland_client::protocol::{wl_callback, wl_shm};
+
+use crate::window::{Event as WEvent, FallbackFrame};
+
+// the fields of the environment are private to the module declaring it
+mod env {
+    use wayland_client::{ConnectError, Display, EventQueue};
+
+    use crate::environment::Environment;
+
+    crate::default_environment!(QuickstartEnv, desktop);
+
+    pub(super) fn connect(
+    ) -> Result<(Environment<QuickstartEnv>, Display, EventQueue), ConnectError> {
+        let display = Display::connect_to_env()?;
+        let mut queue = display.create_event_queue();
+        match crate::new_default_environment!(QuickstartEnv, desktop, with = (display, queue)) {
+            Ok(env) => Ok((env, display, queue)),
+            Err(_) => {
+                if let Some(perr) = display.protocol_error() {
+                    panic!("[SCTK] A protocol error occured during initial setup: {}", perr);
+                }
+                Err(ConnectError::NoCompositorListening)
+            }
+        }
+    }
+}
+
+// the state shared with the callbacks through the dispatch data
+struct State {
+    next_action: Option<WEvent>,
+    // a frame callback was requested with the last commit and did not fire yet
+    waiting_frame: bool,
+}
+
+/// Open a window and draw it until it is closed
+///
+/// The window has the given title and is initially 640x480. The `draw` callback receives the
+/// content of the window to draw, in the `Argb8888` format with a stride of 4 times the
+/// width, its size, and the time elapsed since the start. It returns whether it should be
+/// invoked again on the next frame, for animations, otherwise it is only invoked again when
+/// the window is resized.
+///
+/// Returns when the window is closed, or with an error if the connection to the compositor
+/// failed.
+pub fn run<F>(title: &str, mut draw: F) -> Result<(), Box<dyn Error>>
+where
+    F: FnMut(&mut [u8], (u32, u32), Duration) -> bool,
+{
+    let (env, _display, mut queue) = env::connect()?;
+
+    let surface = env.create_surface().detach();
+    let mut window = env.create_window::<FallbackFrame, _>(
+        surface,
+        None,
+        (640, 480),
+        move |event, mut ddata| {
+            let state = ddata.get::<State>().unwrap();
+            // only keep the most important event, Close > Configure > Refresh
+            let replace = matches!(
+                (&event, &state.next_action),
+                (_, &None)
+                    | (_, &Some(WEvent::Refresh))
+                    | (&WEvent::Configure { .. }, &Some(WEvent::Configure { .. }))
+                    | (&WEvent::Close, _)
+            );
+            if replace {
+                state.next_action = Some(event);
+            }
+        },
+    )?;
+    window.set_title(title.to_string());
+
+    let mut pool = env.create_auto_pool()?;
+    let mut state = State { next_action: None, waiting_frame: false };
+    let mut dimensions = (640, 480);
+    // wl_shell does not send an initial configure, the window can be drawn right away
+    let mut configured = !env.get_shell().map(|shell| shell.needs_configure()).unwrap_or(true);
+    let mut need_redraw = configured;
+    let start = Instant::now();
+
+    loop {
+        match state.next_action.take() {
+            Some(WEvent::Close) => break,
+            Some(WEvent::Refresh) => {
+                window.refresh();
+                window.surface().commit();
+            }
+            Some(WEvent::Configure { new_size, .. }) => {
+                if let Some(size) = new_size {
+                    dimensions = size;
+                }
+                window.resize(dimensions.0, dimensions.1);
+                window.refresh();
+                configured = true;
+                need_redraw = true;
+            }
+            None => {}
+        }
+
+        if configured && need_redraw && !state.waiting_frame {
+            let (width, height) = dimensions;
+            let (canvas, buffer) = pool.buffer(
+                width as i32,
+                height as i32,
+                4 * width as i32,
+                wl_shm::Format::Argb8888,
+            )?;
+            need_redraw = draw(canvas, dimensions, start.elapsed());
+
+            let surface = window.surface();
+            surface.attach(Some(&buffer), 0, 0);
+            if surface.as_ref().version() >= 4 {
+                surface.damage_buffer(0, 0, width as i32, height as i32);
+            } else {
+                surface.damage(0, 0, width as i32, height as i32);
+            }
+            surface.frame().quick_assign(|_, event, mut ddata| {
+                if let wl_callback::Event::Done { .. } = event {
+                    ddata.get::<State>().unwrap().waiting_frame = false;
+                }
+            });
+            state.waiting_frame = true;
+            surface.commit();
+        }
+
+        queue.dispatch(&mut state, |_, _, _| {})?;
+    }
+    Ok(())
+}
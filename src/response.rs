@@ -0,0 +1,180 @@
+use std::{
+    cell::RefCell,
+    error::Error,
+    fmt,
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll, Waker},
+};
+
+use wayland_client::DispatchData;
+
+type ResponseCallback<T> = dyn FnOnce(T, DispatchData);
+
+enum State<T> {
+    Waiting { callback: Option<Box<ResponseCallback<T>>>, waker: Option<Waker> },
+    Ready(T),
+    Cancelled,
+    Done,
+}
+
+/// The error of a [`PendingResponse`](struct.PendingResponse.html) that will never complete
+///
+/// This happens when its [`Responder`](struct.Responder.html) is dropped without responding,
+/// for example because the object the response was expected from was destroyed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ResponseCancelled;
+
+impl Error for ResponseCancelled {}
+
+impl fmt::Display for ResponseCancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("the response was cancelled")
+    }
+}
+
+/// The response to a request, that the server has not sent yet
+///
+/// Some requests are answered by the server with a single event, like the request of an
+/// activation token. The functions sending them return a `PendingResponse`, which gives
+/// access to the response in any of these ways:
+///
+/// - registering a closure with [`on_response`](#method.on_response), invoked when the
+///   response is dispatched,
+/// - awaiting it, as it implements `Future`, which resolves to an error if the response was
+///   cancelled,
+/// - checking for it with [`try_take`](#method.try_take), for example after each dispatch
+///   of the event queue.
+///
+/// Like the responses of the server, the future only completes when the event queue is
+/// dispatched, so it cannot be awaited from the thread dispatching the queue without
+/// dispatching it concurrently.
+pub struct PendingResponse<T> {
+    state: Rc<RefCell<State<T>>>,
+}
+
+/// The sending half of a [`PendingResponse`](struct.PendingResponse.html)
+///
+/// Handlers of protocols not supported by SCTK can use it to provide the same interface
+/// for their own requests.
+pub struct Responder<T> {
+    state: Rc<RefCell<State<T>>>,
+}
+
+impl<T> PendingResponse<T> {
+    /// Create a new pending response, along with the responder completing it
+    pub fn new() -> (PendingResponse<T>, Responder<T>) {
+        let state = Rc::new(RefCell::new(State::Waiting { callback: None, waker: None }));
+        (PendingResponse { state: state.clone() }, Responder { state })
+    }
+
+    /// Whether the response was received and not taken yet
+    pub fn is_ready(&self) -> bool {
+        matches!(*self.state.borrow(), State::Ready(_))
+    }
+
+    /// Whether the responder was dropped without responding
+    ///
+    /// The response will then never be received.
+    pub fn is_cancelled(&self) -> bool {
+        matches!(*self.state.borrow(), State::Cancelled)
+    }
+
+    /// Take the response, if it was received
+    ///
+    /// This returns `Some` only once.
+    pub fn try_take(&self) -> Option<T> {
+        let mut state = self.state.borrow_mut();
+        match std::mem::replace(&mut *state, State::Done) {
+            State::Ready(value) => Some(value),
+            other => {
+                *state = other;
+                None
+            }
+        }
+    }
+
+    /// Invoke a closure with the response once it is received
+    ///
+    /// If the response was already received, the closure is invoked immediately, with an
+    /// empty `DispatchData`. If the response is cancelled, the closure is dropped without
+    /// being invoked.
+    pub fn on_response<F>(self, callback: F)
+    where
+        F: FnOnce(T, DispatchData) + 'static,
+    {
+        if let Some(value) = self.try_take() {
+            callback(value, DispatchData::wrap(&mut ()));
+        } else if let State::Waiting { callback: ref mut cb, .. } = *self.state.borrow_mut() {
+            *cb = Some(Box::new(callback));
+        }
+    }
+}
+
+impl<T> Future for PendingResponse<T> {
+    type Output = Result<T, ResponseCancelled>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Some(value) = self.try_take() {
+            return Poll::Ready(Ok(value));
+        }
+        match *self.state.borrow_mut() {
+            State::Waiting { ref mut waker, .. } => *waker = Some(cx.waker().clone()),
+            State::Cancelled => return Poll::Ready(Err(ResponseCancelled)),
+            _ => {}
+        }
+        Poll::Pending
+    }
+}
+
+impl<T> Responder<T> {
+    /// Complete the pending response
+    ///
+    /// The closure registered with `on_response`, if any, is invoked with `ddata`.
+    ///
+    /// Dropping the responder without responding cancels the response.
+    pub fn respond(self, value: T, ddata: DispatchData) {
+        let previous = std::mem::replace(&mut *self.state.borrow_mut(), State::Done);
+        // the state is not borrowed while invoking the callback or the waker, as they may
+        // access the pending response
+        match previous {
+            State::Waiting { callback: Some(callback), .. } => callback(value, ddata),
+            State::Waiting { callback: None, waker } => {
+                *self.state.borrow_mut() = State::Ready(value);
+                if let Some(waker) = waker {
+                    waker.wake();
+                }
+            }
+            other => *self.state.borrow_mut() = other,
+        }
+    }
+}
+
+impl<T> Drop for Responder<T> {
+    fn drop(&mut self) {
+        let previous = {
+            let mut state = self.state.borrow_mut();
+            match *state {
+                State::Waiting { .. } => std::mem::replace(&mut *state, State::Cancelled),
+                _ => return,
+            }
+        };
+        // the callback is dropped once the state is not borrowed anymore
+        if let State::Waiting { waker: Some(waker), .. } = previous {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> fmt::Debug for PendingResponse<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PendingResponse").field("ready", &self.is_ready()).finish()
+    }
+}
+
+impl<T> fmt::Debug for Responder<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Responder").finish()
+    }
+}
@@ -214,6 +214,14 @@ functions:
     fn xkb_keymap_unref(*mut xkb_keymap) -> (),
     fn xkb_keymap_get_as_string(*mut xkb_keymap, xkb_keymap_format) -> *const c_char,
     fn xkb_keymap_key_repeats(*mut xkb_keymap, xkb_keycode_t) -> c_int,
+    fn xkb_keymap_key_get_name(*mut xkb_keymap, xkb_keycode_t) -> *const c_char,
+    fn xkb_keymap_key_by_name(*mut xkb_keymap, *const c_char) -> xkb_keycode_t,
+    fn xkb_keymap_key_get_syms_by_level(*mut xkb_keymap,
+                                        xkb_keycode_t,
+                                        xkb_layout_index_t,
+                                        xkb_level_index_t,
+                                        *mut *const xkb_keysym_t
+                                       ) -> c_int,
 
     fn xkb_state_new(*mut xkb_keymap) -> *mut xkb_state,
     fn xkb_state_ref(*mut xkb_state) -> *mut xkb_state,
@@ -0,0 +1,194 @@
+use std::{
+    cell::RefCell,
+    fmt,
+    rc::{Rc, Weak},
+};
+
+use wayland_client::{protocol::wl_keyboard, DispatchData};
+
+use super::{Event, KeyState};
+
+/// What a key filter decided to do with an event
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FilterAction {
+    /// Let the event go through to the next layers
+    Pass,
+    /// Consume the event, the next layers will not see it
+    ///
+    /// Only key and repeat events can be consumed, other events always go through
+    /// all the layers.
+    Consume,
+}
+
+type FilterCallback = dyn FnMut(&Event<'_>, &wl_keyboard::WlKeyboard) -> FilterAction;
+
+struct FilterEntry {
+    priority: i32,
+    cb: Weak<RefCell<FilterCallback>>,
+}
+
+/// A prioritized chain of keyboard event filters
+///
+/// This lets you layer your own input routing on top of a mapped keyboard: for example
+/// a global shortcut layer first, then an input method layer, and finally your application.
+/// Each layer sees the events in turn, and can consume key and repeat events so that the
+/// following layers never see them.
+///
+/// The chain is inserted in front of your keyboard callback using the
+/// [`wrap`](#method.wrap) method:
+///
+/// ```no_run
+/// # use smithay_client_toolkit::seat::keyboard::{map_keyboard, Event, FilterAction, KeyFilters};
+/// # fn setup(seat: &wayland_client::Attached<wayland_client::protocol::wl_seat::WlSeat>) {
+/// let filters = KeyFilters::new();
+/// // Consume the Escape key before the application sees it
+/// let _shortcuts = filters.insert(10, |event, _| match event {
+///     Event::Key { keysym, .. } if *keysym == 0xff1b => FilterAction::Consume,
+///     _ => FilterAction::Pass,
+/// });
+/// map_keyboard(seat, None, filters.wrap(|event, _, _| { /* application handling */ }))
+///     .unwrap();
+/// # }
+/// ```
+///
+/// When a key press is consumed, the application will not see the repetitions and
+/// release of this key either. The layers are still shown them, so that the layer which
+/// consumed the press can track the state of the key.
+///
+/// Cloning a `KeyFilters` gives you another handle to the same chain.
+#[derive(Clone)]
+pub struct KeyFilters {
+    inner: Rc<RefCell<FiltersInner>>,
+}
+
+struct FiltersInner {
+    filters: Vec<FilterEntry>,
+    consumed_keys: Vec<u32>,
+}
+
+/// A handle to a key filter
+///
+/// It keeps the filter alive, and the filter is removed from its chain when dropped.
+pub struct KeyFilter {
+    _cb: Rc<RefCell<FilterCallback>>,
+}
+
+impl fmt::Debug for KeyFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyFilter").field("_cb", &"Fn(..) -> { ... }").finish()
+    }
+}
+
+impl fmt::Debug for KeyFilters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inner = self.inner.borrow();
+        f.debug_struct("KeyFilters")
+            .field("priorities", &inner.filters.iter().map(|f| f.priority).collect::<Vec<_>>())
+            .field("consumed_keys", &inner.consumed_keys)
+            .finish()
+    }
+}
+
+impl Default for KeyFilters {
+    fn default() -> KeyFilters {
+        KeyFilters::new()
+    }
+}
+
+impl KeyFilters {
+    /// Create a new empty filter chain
+    pub fn new() -> KeyFilters {
+        KeyFilters {
+            inner: Rc::new(RefCell::new(FiltersInner {
+                filters: Vec::new(),
+                consumed_keys: Vec::new(),
+            })),
+        }
+    }
+
+    /// Insert a filter in the chain
+    ///
+    /// Filters run by decreasing priority, filters of equal priority run in the order in
+    /// which they were inserted.
+    ///
+    /// The filter stays in the chain for as long as the returned
+    /// [`KeyFilter`](struct.KeyFilter.html) is alive.
+    pub fn insert<F>(&self, priority: i32, filter: F) -> KeyFilter
+    where
+        F: FnMut(&Event<'_>, &wl_keyboard::WlKeyboard) -> FilterAction + 'static,
+    {
+        let rc = Rc::new(RefCell::new(filter)) as Rc<RefCell<FilterCallback>>;
+        let mut inner = self.inner.borrow_mut();
+        let pos = inner
+            .filters
+            .iter()
+            .position(|f| f.priority < priority)
+            .unwrap_or_else(|| inner.filters.len());
+        inner.filters.insert(pos, FilterEntry { priority, cb: Rc::downgrade(&rc) });
+        KeyFilter { _cb: rc }
+    }
+
+    /// Run the chain in front of a keyboard callback
+    ///
+    /// The returned closure is meant to be given to
+    /// [`map_keyboard`](fn.map_keyboard.html) or
+    /// [`map_keyboard_repeat`](fn.map_keyboard_repeat.html) in place of your callback,
+    /// which will only be invoked with the events that went through the whole chain.
+    pub fn wrap<F>(
+        &self,
+        mut callback: F,
+    ) -> impl FnMut(Event<'_>, wl_keyboard::WlKeyboard, DispatchData<'_>) + 'static
+    where
+        F: FnMut(Event<'_>, wl_keyboard::WlKeyboard, DispatchData<'_>) + 'static,
+    {
+        let filters = self.clone();
+        move |event, keyboard, ddata| {
+            if filters.filter(&event, &keyboard) {
+                callback(event, keyboard, ddata);
+            }
+        }
+    }
+
+    /// Run an event through the chain, returns whether it went through
+    fn filter(&self, event: &Event<'_>, keyboard: &wl_keyboard::WlKeyboard) -> bool {
+        // Collect the filters first, so that they can modify the chain while running
+        let filters = {
+            let mut inner = self.inner.borrow_mut();
+            inner.filters.retain(|f| f.cb.strong_count() > 0);
+            inner.filters.iter().filter_map(|f| f.cb.upgrade()).collect::<Vec<_>>()
+        };
+
+        let consumable = matches!(event, Event::Key { .. } | Event::Repeat { .. });
+        let mut consumed = false;
+        for filter in filters {
+            let action = (*filter.borrow_mut())(event, keyboard);
+            if consumable && action == FilterAction::Consume {
+                consumed = true;
+                break;
+            }
+        }
+
+        let mut inner = self.inner.borrow_mut();
+        match *event {
+            Event::Key { rawkey, state: KeyState::Pressed, .. } => {
+                inner.consumed_keys.retain(|&k| k != rawkey);
+                if consumed {
+                    inner.consumed_keys.push(rawkey);
+                }
+                !consumed
+            }
+            Event::Key { rawkey, state: KeyState::Released, .. } => {
+                let was_consumed = inner.consumed_keys.contains(&rawkey);
+                inner.consumed_keys.retain(|&k| k != rawkey);
+                !(consumed || was_consumed)
+            }
+            Event::Repeat { rawkey, .. } => !(consumed || inner.consumed_keys.contains(&rawkey)),
+            Event::Leave { .. } => {
+                // All keys are released when the focus is lost
+                inner.consumed_keys.clear();
+                true
+            }
+            _ => true,
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use wayland_client::protocol::wl_surface;
+
+use super::Event;
+
+/// A tracker of the keyboard focus through a hierarchy of surfaces
+///
+/// When a popup or one of its nested popups has the keyboard focus, the input logically
+/// concerns the whole chain of surfaces from the toplevel window to this popup: text input
+/// and shortcuts may need to be routed to any of them. This helper tracks the focused surface
+/// from the events of a keyboard, and gives the chain of surfaces leading to it.
+///
+/// Register your surfaces with their parent and an owner value of your choosing, like an
+/// identifier of the window or popup wrapping them, to be able to translate the focused
+/// surface back to it.
+#[derive(Debug)]
+pub struct FocusChain<T> {
+    // (surface, parent, owner)
+    surfaces: Vec<(wl_surface::WlSurface, Option<wl_surface::WlSurface>, T)>,
+    focused: Option<wl_surface::WlSurface>,
+}
+
+impl<T> FocusChain<T> {
+    /// Create a new, empty, tracker
+    pub fn new() -> FocusChain<T> {
+        FocusChain { surfaces: Vec::new(), focused: None }
+    }
+
+    /// Register a surface
+    ///
+    /// `parent` is the surface it is attached to, like the parent of a popup, or `None`
+    /// for toplevel surfaces. Registering a surface again replaces its parent and owner.
+    pub fn register(
+        &mut self,
+        surface: &wl_surface::WlSurface,
+        parent: Option<&wl_surface::WlSurface>,
+        owner: T,
+    ) {
+        self.unregister(surface);
+        self.surfaces.push((surface.clone(), parent.cloned(), owner));
+    }
+
+    /// Unregister a surface, returning its owner
+    pub fn unregister(&mut self, surface: &wl_surface::WlSurface) -> Option<T> {
+        let idx = self.surfaces.iter().position(|(s, _, _)| s == surface)?;
+        Some(self.surfaces.remove(idx).2)
+    }
+
+    /// Process an event of the keyboard
+    ///
+    /// All events can be given, only the enter and leave ones are used.
+    pub fn handle_event(&mut self, event: &Event) {
+        match *event {
+            Event::Enter { ref surface, .. } => self.focused = Some(surface.clone()),
+            Event::Leave { ref surface, .. } if self.focused.as_ref() == Some(surface) => {
+                self.focused = None
+            }
+            _ => {}
+        }
+    }
+
+    /// The surface that has the keyboard focus, if any
+    pub fn focused(&self) -> Option<&wl_surface::WlSurface> {
+        self.focused.as_ref().filter(|s| s.as_ref().is_alive())
+    }
+
+    /// The owner of the surface that has the keyboard focus
+    ///
+    /// Returns `None` if no surface has the focus, or if it was not registered.
+    pub fn focused_owner(&self) -> Option<&T> {
+        self.focused().and_then(|surface| self.owner(surface))
+    }
+
+    /// The owner of a registered surface
+    pub fn owner(&self, surface: &wl_surface::WlSurface) -> Option<&T> {
+        self.surfaces.iter().find(|(s, _, _)| s == surface).map(|(_, _, owner)| owner)
+    }
+
+    /// The chain of surfaces leading to the focused surface
+    ///
+    /// The chain starts with the toplevel surface and ends with the focused surface, going
+    /// through the registered parents. It is empty if no surface has the focus, and only
+    /// contains the focused surface if it was not registered.
+    pub fn chain(&self) -> Vec<&wl_surface::WlSurface> {
+        let mut chain = Vec::new();
+        let mut current = self.focused();
+        while let Some(surface) = current {
+            // protect against parent cycles
+            if chain.contains(&surface) {
+                break;
+            }
+            chain.push(surface);
+            current = self
+                .surfaces
+                .iter()
+                .find(|(s, _, _)| s == surface)
+                .and_then(|(_, parent, _)| parent.as_ref());
+        }
+        chain.reverse();
+        chain
+    }
+}
+
+impl<T> Default for FocusChain<T> {
+    fn default() -> FocusChain<T> {
+        FocusChain::new()
+    }
+}
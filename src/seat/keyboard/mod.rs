@@ -8,6 +8,9 @@
 //! function which, given a `wl_seat` and a callback, setup keymap interpretation
 //! and key repetition for the `wl_keyboard` of this seat.
 //!
+//! If you need to route keyboard events through several layers before they reach your
+//! application, see [`KeyFilters`](struct.KeyFilters.html).
+//!
 //! Key repetition relies on an event source, that needs to be inserted in your
 //! calloop event loop. Not doing so will prevent key repetition to work
 //! (but the rest of the functionnality will not be affected).
@@ -32,10 +35,16 @@ use wayland_client::{
 
 #[rustfmt::skip]
 mod ffi;
+mod filter;
+mod focus;
+mod physical;
 mod state;
 #[rustfmt::skip]
 pub mod keysyms;
 
+pub use self::filter::{FilterAction, KeyFilter, KeyFilters};
+pub use self::focus::FocusChain;
+pub use self::physical::{physical_key, physical_key_by_name, PhysicalKey};
 use self::state::KbState;
 pub use self::state::{ModifiersState, RMLVO};
 
@@ -153,6 +162,7 @@ where
     };
 
     let state = Rc::new(RefCell::new(rmlvo.map(KbState::from_rmlvo).unwrap_or_else(KbState::new)?));
+    keyboard.as_ref().user_data().set(|| physical::KeymapHandle(Rc::downgrade(&state)));
 
     let callback = Rc::new(RefCell::new(callback));
 
@@ -202,6 +212,7 @@ where
     };
 
     let state = Rc::new(RefCell::new(rmlvo.map(KbState::from_rmlvo).unwrap_or_else(KbState::new)?));
+    keyboard.as_ref().user_data().set(|| physical::KeymapHandle(Rc::downgrade(&state)));
 
     let callback = Rc::new(RefCell::new(callback));
 
@@ -393,12 +404,14 @@ impl KbdHandler {
         keys: Vec<u8>,
         dispatch_data: wayland_client::DispatchData,
     ) {
-        let mut state = self.state.borrow_mut();
         let rawkeys = keys
             .chunks_exact(4)
             .map(|c| u32::from_ne_bytes(c.try_into().unwrap()))
             .collect::<Vec<_>>();
-        let keys: Vec<u32> = rawkeys.iter().map(|k| state.get_one_sym_raw(*k)).collect();
+        let keys: Vec<u32> = {
+            let mut state = self.state.borrow_mut();
+            rawkeys.iter().map(|k| state.get_one_sym_raw(*k)).collect()
+        };
         (&mut *self.callback.borrow_mut())(
             Event::Enter { serial, surface, rawkeys: &rawkeys, keysyms: &keys },
             object,
@@ -498,15 +511,12 @@ impl KbdHandler {
         group: u32,
         dispatch_data: wayland_client::DispatchData,
     ) {
-        {
+        let modifiers = {
             let mut state = self.state.borrow_mut();
             state.update_modifiers(mods_depressed, mods_latched, mods_locked, group);
-            (&mut *self.callback.borrow_mut())(
-                Event::Modifiers { modifiers: state.mods_state() },
-                object,
-                dispatch_data,
-            );
-        }
+            state.mods_state()
+        };
+        (&mut *self.callback.borrow_mut())(Event::Modifiers { modifiers }, object, dispatch_data);
     }
 
     #[cfg_attr(not(feature = "calloop"), allow(unused_variables))]
@@ -576,9 +586,10 @@ impl calloop::EventSource for RepeatSource {
         self.timer.process_events(readiness, token, |(), timer_handle| {
             if let Some(ref mut data) = *current_repeat.borrow_mut() {
                 // there is something to repeat
-                let mut state = state.borrow_mut();
-                let keysym = state.get_one_sym_raw(data.keycode);
-                let utf8 = state.get_utf8_raw(data.keycode);
+                let (keysym, utf8) = {
+                    let mut state = state.borrow_mut();
+                    (state.get_one_sym_raw(data.keycode), state.get_utf8_raw(data.keycode))
+                };
                 let new_time = data.gap + data.time;
                 // Notify the callback.
                 callback(
@@ -0,0 +1,67 @@
+use std::{cell::RefCell, rc::Weak};
+
+use wayland_client::protocol::wl_keyboard;
+
+use super::state::KbState;
+
+// Stored in the user data of the keyboards mapped by SCTK, to reach their keymap
+pub(crate) struct KeymapHandle(pub(crate) Weak<RefCell<KbState>>);
+
+/// A physical key of a keyboard, identified independently of the keyboard layout
+///
+/// The `rawkey` of the keyboard events is the evdev code of the key, which depends on its
+/// position and not on the active layout. This gives it the name the active keymap uses for
+/// this position and the label of the key in the first layout of the keymap, so that games
+/// can bind their controls to positions (like the W, A, S and D keys of a QWERTY keyboard)
+/// while text input keeps using the keysyms of the events.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PhysicalKey {
+    /// The raw value of the key, as given in the keyboard events
+    pub rawkey: u32,
+    /// The xkb name of the key position in the keymap, like `AD02`
+    ///
+    /// This is a stable identifier, suitable for saving key bindings.
+    pub name: String,
+    /// The label of the key in the first layout of the keymap, like `W`
+    ///
+    /// Special keys are labelled with the name of their keysym, like `Shift_L`.
+    pub label: String,
+}
+
+fn with_state<T, F: FnOnce(&KbState) -> Option<T>>(
+    keyboard: &wl_keyboard::WlKeyboard,
+    f: F,
+) -> Option<T> {
+    let handle = keyboard.as_ref().user_data().get::<KeymapHandle>()?;
+    let state = handle.0.upgrade()?;
+    // the state is not borrowed while the keyboard callback runs, but a keymap may be loading
+    let state = state.try_borrow().ok()?;
+    f(&state)
+}
+
+fn key_of(state: &KbState, rawkey: u32) -> Option<PhysicalKey> {
+    let name = state.key_name(rawkey)?;
+    let label = state.key_label(rawkey).unwrap_or_else(|| name.clone());
+    Some(PhysicalKey { rawkey, name, label })
+}
+
+/// Identify the physical key of a raw key value
+///
+/// The key is looked up in the active keymap of a keyboard mapped by
+/// [`map_keyboard`](fn.map_keyboard.html) or
+/// [`map_keyboard_repeat`](fn.map_keyboard_repeat.html), and this can be called from its
+/// callback.
+///
+/// Returns `None` if the keyboard was not mapped by SCTK, if it has not received its keymap
+/// yet, or if the keymap does not name this key.
+pub fn physical_key(keyboard: &wl_keyboard::WlKeyboard, rawkey: u32) -> Option<PhysicalKey> {
+    with_state(keyboard, |state| key_of(state, rawkey))
+}
+
+/// Find a physical key from its xkb name
+///
+/// This is the reverse of [`physical_key`](fn.physical_key.html), to load saved key bindings.
+/// Aliases of the keymap, like `LatQ` for `AD01`, are resolved to the name of the key.
+pub fn physical_key_by_name(keyboard: &wl_keyboard::WlKeyboard, name: &str) -> Option<PhysicalKey> {
+    with_state(keyboard, |state| key_of(state, state.key_by_name(name)?))
+}
@@ -1,5 +1,12 @@
 use memmap2::MmapOptions;
-use std::{env, ffi::CString, fs::File, os::raw::c_char, os::unix::ffi::OsStringExt, ptr};
+use std::{
+    env,
+    ffi::{CStr, CString},
+    fs::File,
+    os::raw::c_char,
+    os::unix::ffi::OsStringExt,
+    ptr,
+};
 
 #[cfg(feature = "dlopen")]
 use super::ffi::XKBCOMMON_HANDLE as XKBH;
@@ -7,6 +14,7 @@ use super::ffi::XKBCOMMON_HANDLE as XKBH;
 use super::ffi::*;
 use super::ffi::{self, xkb_state_component};
 use super::Error;
+use crate::InternalErrorKind;
 
 #[derive(Debug)]
 pub(crate) struct KbState {
@@ -47,7 +55,7 @@ pub struct RMLVO {
 ///
 /// For some modifiers, this means that the key is currently pressed, others are toggled
 /// (like caps lock).
-#[derive(Copy, Clone, Debug, Default)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
 pub struct ModifiersState {
     /// The "control" key
     pub ctrl: bool,
@@ -374,7 +382,12 @@ impl KbState {
         );
 
         if keymap.is_null() {
-            panic!("Received invalid keymap from compositor.");
+            // the keyboard is left without keymap, and ignores the key events
+            crate::error_policy::report(
+                InternalErrorKind::InvalidState,
+                "Received invalid keymap from compositor.".into(),
+            );
+            return;
         }
 
         self.post_init(keymap);
@@ -401,6 +414,89 @@ impl KbState {
         Ok(())
     }
 
+    // The name of a key in the keymap, like `AD02`
+    pub(crate) fn key_name(&self, keycode: u32) -> Option<String> {
+        if !self.ready() {
+            return None;
+        }
+        let name =
+            unsafe { ffi_dispatch!(XKBH, xkb_keymap_key_get_name, self.xkb_keymap, keycode + 8) };
+        if name.is_null() {
+            return None;
+        }
+        Some(unsafe { CStr::from_ptr(name) }.to_string_lossy().into_owned())
+    }
+
+    // The raw value of the key with given name in the keymap
+    pub(crate) fn key_by_name(&self, name: &str) -> Option<u32> {
+        if !self.ready() {
+            return None;
+        }
+        let name = CString::new(name).ok()?;
+        let keycode =
+            unsafe { ffi_dispatch!(XKBH, xkb_keymap_key_by_name, self.xkb_keymap, name.as_ptr()) };
+        // the keycodes of raw keys start at 8
+        keycode.checked_sub(8).filter(|_| keycode != ffi::XKB_KEYCODE_INVALID)
+    }
+
+    // The symbol of a key in the first layout of the keymap, without modifiers
+    pub(crate) fn key_base_sym(&self, keycode: u32) -> u32 {
+        if !self.ready() {
+            return 0;
+        }
+        let mut syms: *const ffi::xkb_keysym_t = ptr::null();
+        let count = unsafe {
+            ffi_dispatch!(
+                XKBH,
+                xkb_keymap_key_get_syms_by_level,
+                self.xkb_keymap,
+                keycode + 8,
+                0,
+                0,
+                &mut syms
+            )
+        };
+        if count < 1 || syms.is_null() {
+            0
+        } else {
+            unsafe { *syms }
+        }
+    }
+
+    // The label of a key in the first layout of the keymap: its text if it is printable, or
+    // the name of its symbol, like `Shift_L`
+    pub(crate) fn key_label(&self, keycode: u32) -> Option<String> {
+        let sym = self.key_base_sym(keycode);
+        if sym == 0 {
+            return None;
+        }
+        let mut buffer = [0u8; 64];
+        let size = unsafe {
+            ffi_dispatch!(XKBH, xkb_keysym_to_utf8, sym, buffer.as_mut_ptr() as *mut _, 8)
+        };
+        // the size includes the final `\0`
+        if size > 1 {
+            let text = String::from_utf8_lossy(&buffer[..size as usize - 1]);
+            if !text.chars().any(char::is_control) {
+                return Some(text.to_uppercase());
+            }
+        }
+        let size = unsafe {
+            ffi_dispatch!(
+                XKBH,
+                xkb_keysym_get_name,
+                sym,
+                buffer.as_mut_ptr() as *mut _,
+                buffer.len()
+            )
+        };
+        if size <= 0 {
+            return None;
+        }
+        let len = (size as usize).min(buffer.len() - 1);
+        Some(String::from_utf8_lossy(&buffer[..len]).into_owned())
+    }
+
     pub(crate) unsafe fn key_repeats(&mut self, xkb_keycode_t: ffi::xkb_keycode_t) -> bool {
         ffi_dispatch!(XKBH, xkb_keymap_key_repeats, self.xkb_keymap, xkb_keycode_t) == 1
     }
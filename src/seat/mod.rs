@@ -9,6 +9,11 @@
 //! a new seat is created of the state of a seat changes, via the
 //! [`Environment::listen_for_seats`](../environment/struct.Environment.html) method.
 //!
+//! On multi-seat setups, the handler can be restricted to a single seat identified by its
+//! name, via the [`Environment::restrict_to_seat`](../environment/struct.Environment.html)
+//! method. The other seats are then hidden: they are not listed and listeners are not
+//! notified about them, so no input device is created for them.
+//!
 //! **Note:** if you don't use the [`default_environment!`](../macro.default_environment.html),
 //! you'll need to implement the [`SeatHandling`](trait.SeatHandling.hmtl) on your
 //! environment struct to access the added methods on
@@ -30,6 +35,11 @@ use wayland_client::{
 
 pub mod keyboard;
 pub mod pointer;
+pub mod recorder;
+pub mod tablet;
+pub mod text_input;
+pub mod timestamp;
+pub mod touch;
 
 type SeatCallback = dyn FnMut(Attached<wl_seat::WlSeat>, &SeatData, DispatchData) + 'static;
 
@@ -101,12 +111,30 @@ impl SeatData {
 pub struct SeatHandler {
     seats: Vec<(u32, Attached<wl_seat::WlSeat>)>,
     listeners: Rc<RefCell<Vec<Weak<RefCell<SeatCallback>>>>>,
+    seat_name: Rc<RefCell<Option<String>>>,
 }
 
 impl SeatHandler {
     /// Create a new SeatHandler
     pub fn new() -> SeatHandler {
-        SeatHandler { seats: Vec::new(), listeners: Rc::new(RefCell::new(Vec::new())) }
+        SeatHandler {
+            seats: Vec::new(),
+            listeners: Rc::new(RefCell::new(Vec::new())),
+            seat_name: Rc::new(RefCell::new(None)),
+        }
+    }
+
+    /// Release all the seats
+    ///
+    /// Seats are released with `wl_seat.release` when the server supports it, and
+    /// forgotten otherwise. This is intended to be used when exiting, see
+    /// [`Environment::shutdown`](../environment/struct.Environment.html#method.shutdown).
+    pub fn shutdown(&mut self) {
+        for (_, seat) in self.seats.drain(..) {
+            if seat.as_ref().version() >= 5 {
+                seat.release();
+            }
+        }
     }
 }
 
@@ -115,10 +143,21 @@ impl fmt::Debug for SeatHandler {
         f.debug_struct("SeatHandler")
             .field("seats", &self.seats)
             .field("listeners", &"Fn(..) -> { ... }")
+            .field("seat_name", &self.seat_name)
             .finish()
     }
 }
 
+// Whether a seat is visible given the name of the seat the handler is restricted to
+//
+// Seats whose name is not yet known are hidden by a restriction.
+fn seat_matches(data: &SeatData, seat_name: &Option<String>) -> bool {
+    match *seat_name {
+        Some(ref name) => data.state.contains(SeatDataState::GOT_NAME) && &data.name == name,
+        None => true,
+    }
+}
+
 /// A handle to an seat listener callback
 ///
 /// Dropping it disables the associated callback and frees the closure.
@@ -139,13 +178,18 @@ impl crate::environment::MultiGlobalHandler<wl_seat::WlSeat> for SeatHandler {
         let seat = registry.bind::<wl_seat::WlSeat>(version, id);
         seat.as_ref().user_data().set_threadsafe(|| Mutex::new(SeatData::new()));
         let cb_listeners = self.listeners.clone();
+        let cb_seat_name = self.seat_name.clone();
         seat.quick_assign(move |seat, event, ddata| {
-            process_seat_event(seat, event, &cb_listeners, ddata)
+            // the name is not borrowed while the listeners run, they may change it
+            let seat_name = cb_seat_name.borrow().clone();
+            process_seat_event(seat, event, &cb_listeners, &seat_name, ddata)
         });
         self.seats.push((id, (*seat).clone()));
     }
     fn removed(&mut self, id: u32, mut ddata: DispatchData) {
         let mut listeners = self.listeners.borrow_mut();
+        // the name is not borrowed while the listeners run, they may change it
+        let seat_name = self.seat_name.borrow().clone();
         self.seats.retain(|&(i, ref seat)| {
             if i != id {
                 true
@@ -154,6 +198,9 @@ impl crate::environment::MultiGlobalHandler<wl_seat::WlSeat> for SeatHandler {
                 let data = seat.as_ref().user_data().get::<Mutex<SeatData>>().unwrap();
                 let mut guard = data.lock().unwrap();
                 guard.defunct = true;
+                if !seat_matches(&guard, &seat_name) {
+                    return false;
+                }
                 // notify the listeners that the seat is dead
                 listeners.retain(|lst| {
                     if let Some(cb) = Weak::upgrade(lst) {
@@ -168,7 +215,12 @@ impl crate::environment::MultiGlobalHandler<wl_seat::WlSeat> for SeatHandler {
         });
     }
     fn get_all(&self) -> Vec<Attached<wl_seat::WlSeat>> {
-        self.seats.iter().map(|(_, s)| s.clone()).collect()
+        let seat_name = self.seat_name.borrow();
+        self.seats
+            .iter()
+            .filter(|(_, s)| with_seat_data(s, |data| seat_matches(data, &seat_name)) == Some(true))
+            .map(|(_, s)| s.clone())
+            .collect()
     }
 }
 
@@ -182,6 +234,7 @@ fn process_seat_event(
     seat: Main<wl_seat::WlSeat>,
     event: wl_seat::Event,
     listeners: &RefCell<Vec<Weak<RefCell<SeatCallback>>>>,
+    seat_name: &Option<String>,
     mut ddata: DispatchData,
 ) {
     let new_data = {
@@ -203,7 +256,7 @@ fn process_seat_event(
         guard.clone()
     };
 
-    if new_data.state.contains(SeatDataState::READY) {
+    if new_data.state.contains(SeatDataState::READY) && seat_matches(&new_data, seat_name) {
         listeners.borrow_mut().retain(|lst| {
             if let Some(cb) = Weak::upgrade(lst) {
                 (&mut *cb.borrow_mut())((*seat).clone(), &new_data, ddata.reborrow());
@@ -262,6 +315,20 @@ pub trait SeatHandling {
         &mut self,
         f: F,
     ) -> SeatListener;
+
+    /// Restrict the handling to the seat with given name
+    ///
+    /// Providing `None` lifts the restriction.
+    ///
+    /// The default implementation does not support restrictions, and only logs a warning.
+    /// Delegate it to your [`SeatHandler`](struct.SeatHandler.html) to support them.
+    fn restrict_to_seat(&mut self, name: Option<String>) {
+        log::warn!(
+            "[SCTK] Cannot restrict the seat handling to {:?}, restrict_to_seat is not delegated \
+             to a SeatHandler.",
+            name
+        );
+    }
 }
 
 impl SeatHandling for SeatHandler {
@@ -273,6 +340,10 @@ impl SeatHandling for SeatHandler {
         self.listeners.borrow_mut().push(Rc::downgrade(&rc));
         SeatListener { _cb: rc }
     }
+
+    fn restrict_to_seat(&mut self, name: Option<String>) {
+        *self.seat_name.borrow_mut() = name;
+    }
 }
 
 impl<E: SeatHandling> crate::environment::Environment<E> {
@@ -296,6 +367,24 @@ impl<E: SeatHandling> crate::environment::Environment<E> {
     ) -> SeatListener {
         self.with_inner(move |inner| SeatHandling::listen(inner, f))
     }
+
+    /// Restrict the seat handling to the seat with given name
+    ///
+    /// This is intended for multi-seat setups, where several users share a computer
+    /// and each has their own seat. Once restricted, the other seats are no longer
+    /// returned by `get_all_seats()` and seat listeners are no longer notified about
+    /// them, so no input device gets created for them. Seats whose name has not yet
+    /// been received are hidden as well.
+    ///
+    /// The name of the seat a session belongs to is typically provided by the
+    /// `XDG_SEAT` environment variable.
+    ///
+    /// Providing `None` lifts the restriction. This does not notify the listeners
+    /// about the seats that become visible or hidden, so you should restrict the
+    /// handling before setting up your input devices.
+    pub fn restrict_to_seat(&self, name: Option<String>) {
+        self.with_inner(move |inner| SeatHandling::restrict_to_seat(inner, name))
+    }
 }
 
 impl<E: crate::environment::MultiGlobalHandler<wl_seat::WlSeat>>
@@ -305,4 +394,13 @@ impl<E: crate::environment::MultiGlobalHandler<wl_seat::WlSeat>>
     pub fn get_all_seats(&self) -> Vec<Attached<wl_seat::WlSeat>> {
         self.get_all_globals::<wl_seat::WlSeat>().into_iter().collect()
     }
+
+    /// Retrieve the seat with given name
+    ///
+    /// Returns `None` if no such seat exists or if its name has not yet been received.
+    pub fn get_seat_by_name(&self, name: &str) -> Option<Attached<wl_seat::WlSeat>> {
+        self.get_all_seats()
+            .into_iter()
+            .find(|seat| with_seat_data(seat, |data| data.name == name) == Some(true))
+    }
 }
@@ -0,0 +1,414 @@
+//! Helpers for the pointer constraints protocol
+//!
+//! The `zwp_pointer_constraints_v1` protocol lets applications lock the pointer in place, or
+//! confine it to a region of a surface. Start with a [`ConstraintBuilder`](struct.ConstraintBuilder.html),
+//! which creates a [`LockedPointer`](struct.LockedPointer.html) or a
+//! [`ConfinedPointer`](struct.ConfinedPointer.html), whose callback is notified when the
+//! compositor activates or deactivates the constraint.
+//!
+//! While the pointer is locked, the `wl_pointer` sends no motion events: games and 3D
+//! viewers should take the movements of the pointer from the relative pointer protocol
+//! (`zwp_relative_pointer_manager_v1`), which keeps reporting them, and use
+//! [`LockedPointer::is_active`](struct.LockedPointer.html#method.is_active) to tell whether
+//! they should be interpreted as camera movements.
+//!
+//! The `zwp_pointer_constraints_v1` global is not part of the
+//! [`default_environment!`](../../../macro.default_environment.html), you can add it to your
+//! environment as a [`SimpleGlobal`](../../../environment/struct.SimpleGlobal.html).
+
+use std::{cell::Cell, fmt, rc::Rc};
+
+use wayland_client::{
+    protocol::{wl_compositor, wl_pointer, wl_region, wl_surface},
+    Attached, DispatchData,
+};
+
+use wayland_protocols::unstable::pointer_constraints::v1::client::{
+    zwp_confined_pointer_v1::{self, ZwpConfinedPointerV1},
+    zwp_locked_pointer_v1::{self, ZwpLockedPointerV1},
+};
+pub use wayland_protocols::unstable::pointer_constraints::v1::client::zwp_pointer_constraints_v1::{
+    Lifetime, ZwpPointerConstraintsV1,
+};
+
+use crate::environment::{Environment, GlobalHandler};
+use crate::{DamageRect, MissingGlobal};
+
+/// A change of the state of a pointer constraint
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ConstraintEvent {
+    /// The pointer was locked in place
+    Locked,
+    /// The pointer lock was deactivated
+    ///
+    /// A lock with a `Oneshot` lifetime is dead after this event and should be dropped.
+    Unlocked,
+    /// The pointer was confined to its region
+    Confined,
+    /// The pointer confinement was deactivated
+    ///
+    /// A confinement with a `Oneshot` lifetime is dead after this event and should be dropped.
+    Unconfined,
+}
+
+/// A builder of pointer constraints
+///
+/// The constraint applies to a pointer within a surface, and is only activated by the
+/// compositor once the pointer is in its region, for example after the user clicked in it.
+/// By default, the region is the whole surface and the constraint has a `Persistent`
+/// lifetime, being reactivated each time the pointer gets back in the region.
+///
+/// A surface can only have one constraint per pointer at a time, creating a second one is a
+/// protocol error.
+#[derive(Debug)]
+pub struct ConstraintBuilder {
+    constraints: Attached<ZwpPointerConstraintsV1>,
+    compositor: Attached<wl_compositor::WlCompositor>,
+    surface: wl_surface::WlSurface,
+    pointer: wl_pointer::WlPointer,
+    rects: Vec<DamageRect>,
+    lifetime: Lifetime,
+}
+
+impl ConstraintBuilder {
+    /// Start building a constraint of a pointer within a surface
+    pub fn new(
+        constraints: Attached<ZwpPointerConstraintsV1>,
+        compositor: Attached<wl_compositor::WlCompositor>,
+        surface: &wl_surface::WlSurface,
+        pointer: &wl_pointer::WlPointer,
+    ) -> ConstraintBuilder {
+        ConstraintBuilder {
+            constraints,
+            compositor,
+            surface: surface.clone(),
+            pointer: pointer.clone(),
+            rects: Vec::new(),
+            lifetime: Lifetime::Persistent,
+        }
+    }
+
+    /// Restrict the constraint to the union of some rectangles, in surface-local coordinates
+    ///
+    /// The empty rectangles are ignored, and the region is the whole surface if all of them
+    /// are.
+    pub fn region(mut self, rects: &[DamageRect]) -> ConstraintBuilder {
+        self.rects = rects.iter().copied().filter(|&(_, _, w, h)| w > 0 && h > 0).collect();
+        self
+    }
+
+    /// Set the lifetime of the constraint
+    ///
+    /// A `Oneshot` constraint is destroyed by the compositor once deactivated, while a
+    /// `Persistent` one is activated again when the pointer gets back in its region.
+    pub fn lifetime(mut self, lifetime: Lifetime) -> ConstraintBuilder {
+        self.lifetime = lifetime;
+        self
+    }
+
+    /// Lock the pointer in place
+    ///
+    /// The callback is invoked with `Locked` and `Unlocked` events.
+    pub fn lock<F>(self, mut callback: F) -> LockedPointer
+    where
+        F: FnMut(ConstraintEvent, DispatchData) + 'static,
+    {
+        let region = self.create_region();
+        let locked = self.constraints.lock_pointer(
+            &self.surface,
+            &self.pointer,
+            region.as_ref(),
+            self.lifetime,
+        );
+        if let Some(region) = region {
+            region.destroy();
+        }
+        let active = Rc::new(Cell::new(false));
+        let my_active = active.clone();
+        locked.quick_assign(move |_, event, ddata| {
+            let event = match event {
+                zwp_locked_pointer_v1::Event::Locked => ConstraintEvent::Locked,
+                zwp_locked_pointer_v1::Event::Unlocked => ConstraintEvent::Unlocked,
+                _ => return,
+            };
+            my_active.set(event == ConstraintEvent::Locked);
+            callback(event, ddata);
+        });
+        LockedPointer { compositor: self.compositor, locked: locked.detach(), active }
+    }
+
+    /// Confine the pointer to the region
+    ///
+    /// The callback is invoked with `Confined` and `Unconfined` events.
+    pub fn confine<F>(self, mut callback: F) -> ConfinedPointer
+    where
+        F: FnMut(ConstraintEvent, DispatchData) + 'static,
+    {
+        let region = self.create_region();
+        let confined = self.constraints.confine_pointer(
+            &self.surface,
+            &self.pointer,
+            region.as_ref(),
+            self.lifetime,
+        );
+        if let Some(region) = region {
+            region.destroy();
+        }
+        let active = Rc::new(Cell::new(false));
+        let my_active = active.clone();
+        confined.quick_assign(move |_, event, ddata| {
+            let event = match event {
+                zwp_confined_pointer_v1::Event::Confined => ConstraintEvent::Confined,
+                zwp_confined_pointer_v1::Event::Unconfined => ConstraintEvent::Unconfined,
+                _ => return,
+            };
+            my_active.set(event == ConstraintEvent::Confined);
+            callback(event, ddata);
+        });
+        let region = ConfineRegion {
+            compositor: self.compositor,
+            confined: confined.detach(),
+            rects: Some(self.rects),
+        };
+        ConfinedPointer { region, active }
+    }
+
+    // the region is copied by the requests, it can be destroyed right after them
+    fn create_region(&self) -> Option<wl_region::WlRegion> {
+        if self.rects.is_empty() {
+            return None;
+        }
+        Some(create_region(&self.compositor, &self.rects))
+    }
+}
+
+fn create_region(
+    compositor: &Attached<wl_compositor::WlCompositor>,
+    rects: &[DamageRect],
+) -> wl_region::WlRegion {
+    let region = compositor.create_region();
+    for &(x, y, w, h) in rects {
+        region.add(x, y, w, h);
+    }
+    region.detach()
+}
+
+/// A pointer locked in place
+///
+/// The lock is destroyed when this is dropped.
+pub struct LockedPointer {
+    compositor: Attached<wl_compositor::WlCompositor>,
+    locked: ZwpLockedPointerV1,
+    active: Rc<Cell<bool>>,
+}
+
+impl LockedPointer {
+    /// The underlying locked pointer object
+    pub fn locked_pointer(&self) -> &ZwpLockedPointerV1 {
+        &self.locked
+    }
+
+    /// Whether the pointer is currently locked
+    pub fn is_active(&self) -> bool {
+        self.active.get()
+    }
+
+    /// Hint the compositor where the cursor is, in surface-local coordinates
+    ///
+    /// Applications drawing their own cursor while the pointer is locked should use this
+    /// to have the real cursor appear there once unlocked. It takes effect with the next
+    /// commit of the surface.
+    pub fn set_cursor_position_hint(&self, x: f64, y: f64) {
+        self.locked.set_cursor_position_hint(x, y);
+    }
+
+    /// Change the region in which the lock can be activated
+    ///
+    /// The empty rectangles are ignored, and the region is the whole surface if all of them
+    /// are. It takes effect with the next commit of the surface.
+    pub fn set_region(&self, rects: &[DamageRect]) {
+        let rects: Vec<DamageRect> =
+            rects.iter().copied().filter(|&(_, _, w, h)| w > 0 && h > 0).collect();
+        if rects.is_empty() {
+            self.locked.set_region(None);
+        } else {
+            let region = create_region(&self.compositor, &rects);
+            self.locked.set_region(Some(&region));
+            region.destroy();
+        }
+    }
+}
+
+impl Drop for LockedPointer {
+    fn drop(&mut self) {
+        self.locked.destroy();
+    }
+}
+
+impl fmt::Debug for LockedPointer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LockedPointer")
+            .field("locked", &self.locked)
+            .field("active", &self.active.get())
+            .finish()
+    }
+}
+
+/// A pointer confined to a region of a surface
+///
+/// Its region can be updated through [`region`](#method.region). The confinement is
+/// destroyed when this is dropped.
+pub struct ConfinedPointer {
+    region: ConfineRegion,
+    active: Rc<Cell<bool>>,
+}
+
+impl ConfinedPointer {
+    /// The helper updating the region of the confinement
+    pub fn region(&mut self) -> &mut ConfineRegion {
+        &mut self.region
+    }
+
+    /// Whether the pointer is currently confined
+    pub fn is_active(&self) -> bool {
+        self.active.get()
+    }
+}
+
+impl Drop for ConfinedPointer {
+    fn drop(&mut self) {
+        self.region.confined.destroy();
+    }
+}
+
+impl fmt::Debug for ConfinedPointer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConfinedPointer")
+            .field("region", &self.region)
+            .field("active", &self.active.get())
+            .finish()
+    }
+}
+
+impl<E> Environment<E>
+where
+    E: GlobalHandler<ZwpPointerConstraintsV1> + GlobalHandler<wl_compositor::WlCompositor>,
+{
+    /// Start building a constraint of a pointer within a surface
+    ///
+    /// See [`ConstraintBuilder`](../seat/pointer/constraints/struct.ConstraintBuilder.html).
+    /// Returns an error if the compositor does not support `zwp_pointer_constraints_v1`.
+    pub fn constrain_pointer(
+        &self,
+        surface: &wl_surface::WlSurface,
+        pointer: &wl_pointer::WlPointer,
+    ) -> Result<ConstraintBuilder, MissingGlobal> {
+        let constraints = self.get_global::<ZwpPointerConstraintsV1>().ok_or(MissingGlobal)?;
+        let compositor = self.require_global::<wl_compositor::WlCompositor>();
+        Ok(ConstraintBuilder::new(constraints, compositor, surface, pointer))
+    }
+
+    /// Lock a pointer in place within a surface
+    ///
+    /// This is a shorthand for a [`constrain_pointer`](#method.constrain_pointer) whose
+    /// constraint covers the whole surface and is persistent.
+    pub fn lock_pointer<F>(
+        &self,
+        surface: &wl_surface::WlSurface,
+        pointer: &wl_pointer::WlPointer,
+        callback: F,
+    ) -> Result<LockedPointer, MissingGlobal>
+    where
+        F: FnMut(ConstraintEvent, DispatchData) + 'static,
+    {
+        Ok(self.constrain_pointer(surface, pointer)?.lock(callback))
+    }
+
+    /// Confine a pointer to a surface
+    ///
+    /// This is a shorthand for a [`constrain_pointer`](#method.constrain_pointer) whose
+    /// constraint covers the whole surface and is persistent.
+    pub fn confine_pointer<F>(
+        &self,
+        surface: &wl_surface::WlSurface,
+        pointer: &wl_pointer::WlPointer,
+        callback: F,
+    ) -> Result<ConfinedPointer, MissingGlobal>
+    where
+        F: FnMut(ConstraintEvent, DispatchData) + 'static,
+    {
+        Ok(self.constrain_pointer(surface, pointer)?.confine(callback))
+    }
+}
+
+/// A helper updating the region a pointer is confined to
+///
+/// Drawing tools often confine the pointer to their canvas, which moves and resizes as the
+/// layout of the application changes. Give the rectangles of the confinement region, in
+/// surface-local coordinates, to [`set_rects`](#method.set_rects) whenever the layout
+/// changes: a new region is only sent to the compositor if it differs from the current one.
+///
+/// The region of a confined pointer is double-buffered state of its surface: the compositor
+/// only applies it with the next commit of the surface, so it should be set before the commit
+/// presenting the new layout.
+#[derive(Debug)]
+pub struct ConfineRegion {
+    compositor: Attached<wl_compositor::WlCompositor>,
+    confined: ZwpConfinedPointerV1,
+    // the rectangles of the last region sent, empty if the pointer is confined to the whole
+    // surface, and `None` if no region was sent yet
+    rects: Option<Vec<DamageRect>>,
+}
+
+impl ConfineRegion {
+    /// Create a helper for a confined pointer
+    ///
+    /// The pointer is assumed to be confined to the region it was created with, which this
+    /// helper does not know: the first region given to it is always sent.
+    pub fn new(
+        compositor: Attached<wl_compositor::WlCompositor>,
+        confined: ZwpConfinedPointerV1,
+    ) -> ConfineRegion {
+        ConfineRegion { compositor, confined, rects: None }
+    }
+
+    /// The confined pointer whose region is updated
+    pub fn confined_pointer(&self) -> &ZwpConfinedPointerV1 {
+        &self.confined
+    }
+
+    /// Confine the pointer to the union of some rectangles
+    ///
+    /// The empty rectangles are ignored, and the pointer is confined to the whole surface if
+    /// all of them are. Returns whether a new region was sent, which takes effect with the
+    /// next commit of the surface.
+    pub fn set_rects(&mut self, rects: &[DamageRect]) -> bool {
+        let rects: Vec<DamageRect> =
+            rects.iter().copied().filter(|&(_, _, w, h)| w > 0 && h > 0).collect();
+        if rects.is_empty() {
+            return self.set_unbounded();
+        }
+        if self.rects.as_ref() == Some(&rects) {
+            return false;
+        }
+        let region = create_region(&self.compositor, &rects);
+        self.confined.set_region(Some(&region));
+        // the region is copied by the request, it can be destroyed right away
+        region.destroy();
+        self.rects = Some(rects);
+        true
+    }
+
+    /// Confine the pointer to the whole surface
+    ///
+    /// Returns whether a new region was sent, which takes effect with the next commit of the
+    /// surface.
+    pub fn set_unbounded(&mut self) -> bool {
+        if self.rects.as_ref().map(Vec::is_empty).unwrap_or(false) {
+            return false;
+        }
+        self.confined.set_region(None);
+        self.rects = Some(Vec::new());
+        true
+    }
+}
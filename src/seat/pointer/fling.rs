@@ -0,0 +1,184 @@
+use std::{collections::VecDeque, fmt};
+
+use wayland_client::protocol::wl_pointer;
+
+// only the axis events of this last period are used to estimate the velocity
+const VELOCITY_WINDOW_MS: u32 = 100;
+// below this speed (in surface units per second) a fling stops
+const MIN_VELOCITY: f64 = 20.0;
+
+#[derive(Debug, Default)]
+struct AxisState {
+    // (time, value) of the recent axis events
+    samples: VecDeque<(u32, f64)>,
+    // current fling velocity, in surface units per second
+    velocity: f64,
+}
+
+impl AxisState {
+    fn push(&mut self, time: u32, value: f64) {
+        while let Some(&(t, _)) = self.samples.front() {
+            if time.wrapping_sub(t) > VELOCITY_WINDOW_MS {
+                self.samples.pop_front();
+            } else {
+                break;
+            }
+        }
+        self.samples.push_back((time, value));
+    }
+
+    fn stop(&mut self, time: u32) {
+        self.velocity = 0.0;
+        let mut samples = std::mem::take(&mut self.samples);
+        samples.retain(|&(t, _)| time.wrapping_sub(t) <= VELOCITY_WINDOW_MS);
+        if let (Some(&(first, _)), Some(&(last, _))) = (samples.front(), samples.back()) {
+            let span = last.wrapping_sub(first);
+            if span > 0 {
+                // the value of the first event was accumulated before the window started
+                let distance: f64 = samples.iter().skip(1).map(|&(_, v)| v).sum();
+                self.velocity = distance * 1000.0 / f64::from(span);
+            }
+        }
+    }
+}
+
+/// A helper for kinetic scrolling
+///
+/// Touchpads report the scrolling of the fingers, and send an `axis_stop` event when
+/// they are lifted. Many applications then keep scrolling for a while, slowing down,
+/// as if the content was flung. This helper implements this behavior: feed it the
+/// events of a pointer using [`handle_event`](#method.handle_event), and call
+/// [`tick`](#method.tick) from your frame callbacks to get the amount to scroll.
+///
+/// The velocity of a fling is estimated from the axis events of the last 100ms before
+/// the fingers were lifted. Only scrolling from a finger (as indicated by `axis_source`)
+/// is continued. Any new axis event stops the current fling.
+pub struct KineticScroll {
+    vertical: AxisState,
+    horizontal: AxisState,
+    finger: bool,
+    last_tick: Option<u32>,
+    decay: Box<dyn FnMut(f64, f64) -> f64>,
+}
+
+impl KineticScroll {
+    /// Create a new kinetic scrolling helper, with the default decay
+    ///
+    /// The velocity of flings decreases exponentially, halving about every 200ms.
+    pub fn new() -> KineticScroll {
+        KineticScroll::with_decay(|velocity, dt| velocity * (-dt / 0.3).exp())
+    }
+
+    /// Create a new kinetic scrolling helper with a custom decay curve
+    ///
+    /// The closure is called at every tick with the current velocity (in surface units per
+    /// second) and the time elapsed since the previous tick (in seconds), and returns the
+    /// new velocity. The fling stops once the speed becomes low enough.
+    pub fn with_decay<F>(decay: F) -> KineticScroll
+    where
+        F: FnMut(f64, f64) -> f64 + 'static,
+    {
+        KineticScroll {
+            vertical: AxisState::default(),
+            horizontal: AxisState::default(),
+            finger: false,
+            last_tick: None,
+            decay: Box::new(decay),
+        }
+    }
+
+    /// Process an event of the pointer
+    ///
+    /// All events can be given, only the axis ones are used.
+    pub fn handle_event(&mut self, event: &wl_pointer::Event) {
+        match *event {
+            wl_pointer::Event::AxisSource { axis_source } => {
+                self.finger = axis_source == wl_pointer::AxisSource::Finger;
+            }
+            wl_pointer::Event::Axis { time, axis, value } => {
+                self.stop();
+                if let Some(state) = self.axis_mut(axis) {
+                    state.push(time, value);
+                }
+            }
+            wl_pointer::Event::AxisStop { time, axis } => {
+                let finger = self.finger;
+                if let Some(state) = self.axis_mut(axis) {
+                    if finger {
+                        state.stop(time);
+                    } else {
+                        state.samples.clear();
+                    }
+                }
+                self.last_tick = None;
+            }
+            _ => {}
+        }
+    }
+
+    /// Whether a fling is in progress
+    ///
+    /// If it is, you need to keep requesting frame callbacks and calling `tick` from them.
+    pub fn is_flinging(&self) -> bool {
+        self.vertical.velocity != 0.0 || self.horizontal.velocity != 0.0
+    }
+
+    /// Stop the current fling, if any
+    pub fn stop(&mut self) {
+        self.vertical.velocity = 0.0;
+        self.horizontal.velocity = 0.0;
+    }
+
+    /// Advance the current fling
+    ///
+    /// `time` is a timestamp in milliseconds, like the one given to frame callbacks. Returns
+    /// the amount to scroll since the previous tick, as `(horizontal, vertical)` in the same
+    /// units as the axis events, or `None` if no fling is in progress.
+    pub fn tick(&mut self, time: u32) -> Option<(f64, f64)> {
+        if !self.is_flinging() {
+            self.last_tick = None;
+            return None;
+        }
+        let dt = match self.last_tick {
+            Some(last) => f64::from(time.wrapping_sub(last)) / 1000.0,
+            None => 0.0,
+        };
+        self.last_tick = Some(time);
+        let mut delta = [0.0; 2];
+        for (state, delta) in [&mut self.horizontal, &mut self.vertical].iter_mut().zip(&mut delta)
+        {
+            *delta = state.velocity * dt;
+            state.velocity = (self.decay)(state.velocity, dt);
+            if state.velocity.abs() < MIN_VELOCITY {
+                state.velocity = 0.0;
+            }
+        }
+        Some((delta[0], delta[1]))
+    }
+
+    fn axis_mut(&mut self, axis: wl_pointer::Axis) -> Option<&mut AxisState> {
+        match axis {
+            wl_pointer::Axis::VerticalScroll => Some(&mut self.vertical),
+            wl_pointer::Axis::HorizontalScroll => Some(&mut self.horizontal),
+            _ => None,
+        }
+    }
+}
+
+impl Default for KineticScroll {
+    fn default() -> KineticScroll {
+        KineticScroll::new()
+    }
+}
+
+impl fmt::Debug for KineticScroll {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KineticScroll")
+            .field("vertical", &self.vertical)
+            .field("horizontal", &self.horizontal)
+            .field("finger", &self.finger)
+            .field("last_tick", &self.last_tick)
+            .field("decay", &"Fn(f64, f64) -> f64 { ... }")
+            .finish()
+    }
+}
@@ -0,0 +1,44 @@
+use wayland_client::protocol::wl_pointer;
+
+/// A buffer grouping the events of a pointer into frames
+///
+/// Since version 5 of `wl_seat`, the events of a pointer are grouped in frames: all the events
+/// of a frame happened at the same time and should be processed together, for example the
+/// horizontal and vertical scrolling of a diagonal scroll, or the leave and enter events of a
+/// pointer moving from a surface to another.
+///
+/// Give all the events of a pointer to [`push`](#method.push), which returns the events of a
+/// frame once it is complete, without the `frame` event itself. With older pointers, which do
+/// not send `frame` events, each event is returned as its own frame.
+#[derive(Debug)]
+pub struct PointerFrameBuffer {
+    events: Vec<wl_pointer::Event>,
+    frames: bool,
+}
+
+impl PointerFrameBuffer {
+    /// Create a buffer for the events of given pointer
+    pub fn new(pointer: &wl_pointer::WlPointer) -> PointerFrameBuffer {
+        PointerFrameBuffer { events: Vec::new(), frames: pointer.as_ref().version() >= 5 }
+    }
+
+    /// Process an event of the pointer
+    ///
+    /// Returns the events of the frame if this event completed it.
+    pub fn push(&mut self, event: wl_pointer::Event) -> Option<Vec<wl_pointer::Event>> {
+        if let wl_pointer::Event::Frame = event {
+            return Some(std::mem::take(&mut self.events));
+        }
+        self.events.push(event);
+        if self.frames {
+            None
+        } else {
+            Some(std::mem::take(&mut self.events))
+        }
+    }
+
+    /// Whether some events are waiting for the end of their frame
+    pub fn is_pending(&self) -> bool {
+        !self.events.is_empty()
+    }
+}
@@ -0,0 +1,246 @@
+use std::{cell::RefCell, fmt, io, rc::Rc, time::Duration};
+
+use wayland_client::{
+    protocol::{wl_pointer, wl_surface},
+    DispatchData,
+};
+
+use crate::DamageRect;
+
+/// An event of a [`HoverWatcher`](struct.HoverWatcher.html)
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HoverEvent {
+    /// The pointer rested over the rectangle with this id, its tooltip should be shown
+    Show(usize),
+    /// The tooltip of the rectangle with this id should be hidden
+    Hide(usize),
+}
+
+type HoverCallback = dyn FnMut(HoverEvent, DispatchData);
+
+struct Inner {
+    delay: Duration,
+    rects: Vec<(usize, wl_surface::WlSurface, DamageRect)>,
+    surface: Option<wl_surface::WlSurface>,
+    // the id of the rectangle under the pointer
+    hovered: Option<usize>,
+    shown: bool,
+    // the tooltip was dismissed, and is not shown again until another rectangle is hovered
+    dismissed: bool,
+    // identifies the last scheduled timeout, to ignore the older ones
+    generation: u64,
+}
+
+/// A helper scheduling the tooltips of the regions hovered by a pointer
+///
+/// Register the rectangles having a tooltip with [`add_rect`](#method.add_rect), in
+/// surface-local coordinates, and give the events of a pointer to
+/// [`handle_pointer_event`](#method.handle_pointer_event). Once the pointer rested over a
+/// rectangle for the dwell delay, the callback receives a `HoverEvent::Show` with its id, and
+/// a `HoverEvent::Hide` when the pointer leaves it or a button is pressed.
+///
+/// Moving the pointer within a rectangle before its tooltip is shown restarts the delay. The
+/// delay is measured with a calloop timer, inserted in the event loop by
+/// [`new`](#method.new).
+pub struct HoverWatcher {
+    inner: Rc<RefCell<Inner>>,
+    callback: Rc<RefCell<HoverCallback>>,
+    timer: calloop::timer::TimerHandle<u64>,
+}
+
+impl HoverWatcher {
+    /// Create a new watcher showing tooltips after given dwell delay
+    ///
+    /// Its timer is inserted in the event loop, the returned token can be used to remove it
+    /// once the watcher is dropped.
+    pub fn new<F, Data: 'static>(
+        loop_handle: &calloop::LoopHandle<'static, Data>,
+        delay: Duration,
+        callback: F,
+    ) -> io::Result<(HoverWatcher, calloop::RegistrationToken)>
+    where
+        F: FnMut(HoverEvent, DispatchData) + 'static,
+    {
+        let inner = Rc::new(RefCell::new(Inner {
+            delay,
+            rects: Vec::new(),
+            surface: None,
+            hovered: None,
+            shown: false,
+            dismissed: false,
+            generation: 0,
+        }));
+        let callback = Rc::new(RefCell::new(callback)) as Rc<RefCell<HoverCallback>>;
+        let timer = calloop::timer::Timer::new()?;
+        let handle = timer.handle();
+
+        let my_inner = Rc::downgrade(&inner);
+        let my_callback = Rc::downgrade(&callback);
+        let token = loop_handle
+            .insert_source(timer, move |generation, _, ddata| {
+                let (inner, callback) = match (my_inner.upgrade(), my_callback.upgrade()) {
+                    (Some(inner), Some(callback)) => (inner, callback),
+                    _ => return,
+                };
+                let id = {
+                    let mut inner = inner.borrow_mut();
+                    match inner.hovered {
+                        Some(id) if inner.generation == generation && !inner.shown => {
+                            inner.shown = true;
+                            id
+                        }
+                        _ => return,
+                    }
+                };
+                (*callback.borrow_mut())(HoverEvent::Show(id), DispatchData::wrap(ddata));
+            })
+            .map_err(|e| e.error)?;
+
+        Ok((HoverWatcher { inner, callback, timer: handle }, token))
+    }
+
+    /// Change the dwell delay
+    pub fn set_delay(&mut self, delay: Duration) {
+        self.inner.borrow_mut().delay = delay;
+    }
+
+    /// Register a rectangle of a surface having a tooltip
+    ///
+    /// If several rectangles contain the pointer, the one added last is hovered.
+    pub fn add_rect(&mut self, id: usize, surface: &wl_surface::WlSurface, rect: DamageRect) {
+        self.inner.borrow_mut().rects.push((id, surface.clone(), rect));
+    }
+
+    /// Unregister the rectangles with given id
+    ///
+    /// Their tooltip is hidden if it was shown, the callback then receives the `Hide` event
+    /// with `ddata` right away. This must not be called from the callback.
+    pub fn remove_rect(&mut self, id: usize, ddata: DispatchData) {
+        self.inner.borrow_mut().rects.retain(|&(rect_id, _, _)| rect_id != id);
+        if self.inner.borrow().hovered == Some(id) {
+            self.set_hovered(None, ddata);
+        }
+    }
+
+    /// Unregister all the rectangles, for example before a new layout
+    ///
+    /// The tooltip is hidden if one was shown, the callback then receives the `Hide` event
+    /// with `ddata` right away. This must not be called from the callback.
+    pub fn clear(&mut self, ddata: DispatchData) {
+        self.inner.borrow_mut().rects.clear();
+        self.set_hovered(None, ddata);
+    }
+
+    /// The id of the rectangle whose tooltip is shown, if any
+    pub fn shown(&self) -> Option<usize> {
+        let inner = self.inner.borrow();
+        if inner.shown {
+            inner.hovered
+        } else {
+            None
+        }
+    }
+
+    /// Process an event of the pointer
+    ///
+    /// All events can be given, the `Hide` events it causes are given to the callback right
+    /// away. This must not be called from the callback.
+    pub fn handle_pointer_event(&mut self, event: &wl_pointer::Event, ddata: DispatchData) {
+        match *event {
+            wl_pointer::Event::Enter { ref surface, surface_x, surface_y, .. } => {
+                self.inner.borrow_mut().surface = Some(surface.clone());
+                self.motion(surface_x, surface_y, ddata);
+            }
+            wl_pointer::Event::Motion { surface_x, surface_y, .. } => {
+                self.motion(surface_x, surface_y, ddata);
+            }
+            wl_pointer::Event::Leave { .. } => {
+                self.inner.borrow_mut().surface = None;
+                self.set_hovered(None, ddata);
+            }
+            wl_pointer::Event::Button { state: wl_pointer::ButtonState::Pressed, .. } => {
+                let hide = {
+                    let mut inner = self.inner.borrow_mut();
+                    inner.dismissed = inner.hovered.is_some();
+                    self.timer.cancel_all_timeouts();
+                    let shown = inner.hovered.filter(|_| inner.shown);
+                    inner.shown = false;
+                    shown
+                };
+                if let Some(id) = hide {
+                    (*self.callback.borrow_mut())(HoverEvent::Hide(id), ddata);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn motion(&mut self, x: f64, y: f64, ddata: DispatchData) {
+        let hovered = {
+            let inner = self.inner.borrow();
+            let surface = match inner.surface {
+                Some(ref surface) => surface,
+                None => return,
+            };
+            inner
+                .rects
+                .iter()
+                .rev()
+                .find(|&&(_, ref s, (rx, ry, rw, rh))| {
+                    s == surface
+                        && x >= f64::from(rx)
+                        && x < f64::from(rx + rw)
+                        && y >= f64::from(ry)
+                        && y < f64::from(ry + rh)
+                })
+                .map(|&(id, _, _)| id)
+        };
+        if hovered.is_some() && hovered == self.inner.borrow().hovered {
+            let mut inner = self.inner.borrow_mut();
+            if !inner.shown && !inner.dismissed {
+                // the pointer must rest for the whole delay
+                self.schedule(&mut inner);
+            }
+            return;
+        }
+        self.set_hovered(hovered, ddata);
+    }
+
+    fn set_hovered(&mut self, hovered: Option<usize>, ddata: DispatchData) {
+        let hide = {
+            let mut inner = self.inner.borrow_mut();
+            let hide = inner.hovered.filter(|_| inner.shown);
+            inner.hovered = hovered;
+            inner.shown = false;
+            inner.dismissed = false;
+            if hovered.is_some() {
+                self.schedule(&mut inner);
+            } else {
+                self.timer.cancel_all_timeouts();
+            }
+            hide
+        };
+        if let Some(id) = hide {
+            (*self.callback.borrow_mut())(HoverEvent::Hide(id), ddata);
+        }
+    }
+
+    fn schedule(&self, inner: &mut Inner) {
+        inner.generation = inner.generation.wrapping_add(1);
+        self.timer.cancel_all_timeouts();
+        self.timer.add_timeout(inner.delay, inner.generation);
+    }
+}
+
+impl fmt::Debug for HoverWatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inner = self.inner.borrow();
+        f.debug_struct("HoverWatcher")
+            .field("delay", &inner.delay)
+            .field("rects", &inner.rects)
+            .field("hovered", &inner.hovered)
+            .field("shown", &inner.shown)
+            .field("callback", &"Fn(..) -> { ... }")
+            .finish()
+    }
+}
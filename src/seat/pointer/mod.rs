@@ -1,5 +1,19 @@
 //! Utilities to work with pointers and their icons
 
+pub mod constraints;
+mod fling;
+mod frame;
+#[cfg(feature = "calloop")]
+mod hover;
+pub mod relative;
+mod scroll;
 mod theme;
+mod visibility;
 
-pub use self::theme::{ThemeManager, ThemeSpec, ThemedPointer};
+pub use self::fling::KineticScroll;
+pub use self::frame::PointerFrameBuffer;
+#[cfg(feature = "calloop")]
+pub use self::hover::{HoverEvent, HoverWatcher};
+pub use self::scroll::{ScrollAccumulator, ScrollAmount, ScrollPolicy, ScrollUnit};
+pub use self::theme::{CustomCursor, ThemeManager, ThemeSpec, ThemedPointer};
+pub use self::visibility::HideWhileTyping;
@@ -0,0 +1,237 @@
+//! Helpers for the relative pointer protocol
+//!
+//! The `zwp_relative_pointer_manager_v1` protocol reports the movements of a pointer as
+//! deltas, before and after the acceleration applied by the compositor. These keep coming
+//! when the pointer hits the edge of the screen or is locked in place with the
+//! [`constraints`](../constraints/index.html), which makes them suitable for first-person
+//! camera controls.
+//!
+//! The manager global is not part of the
+//! [`default_environment!`](../../../macro.default_environment.html), add a
+//! [`RelativePointerState`](struct.RelativePointerState.html) to your environment to bind it:
+//!
+//! ```no_run
+//! # use smithay_client_toolkit::{environment, seat::pointer::relative::{RelativePointerState, ZwpRelativePointerManagerV1}};
+//! struct MyEnv {
+//!     relative_pointer: RelativePointerState,
+//! }
+//!
+//! environment!(MyEnv,
+//!     singles = [
+//!         ZwpRelativePointerManagerV1 => relative_pointer,
+//!     ],
+//!     multis = []
+//! );
+//! ```
+
+use std::{cell::RefCell, rc::Rc, time::Duration};
+
+use wayland_client::{
+    protocol::{wl_pointer, wl_registry},
+    Attached, DispatchData,
+};
+
+pub use wayland_protocols::unstable::relative_pointer::v1::client::zwp_relative_pointer_manager_v1::ZwpRelativePointerManagerV1;
+use wayland_protocols::unstable::relative_pointer::v1::client::zwp_relative_pointer_v1::{
+    self, ZwpRelativePointerV1,
+};
+
+use crate::environment::{Environment, GlobalHandler};
+use crate::MissingGlobal;
+
+/// A handler for the `zwp_relative_pointer_manager_v1` global
+#[derive(Debug, Default)]
+pub struct RelativePointerState {
+    manager: Option<Attached<ZwpRelativePointerManagerV1>>,
+}
+
+impl RelativePointerState {
+    /// Create a new handler
+    pub fn new() -> RelativePointerState {
+        RelativePointerState::default()
+    }
+}
+
+impl GlobalHandler<ZwpRelativePointerManagerV1> for RelativePointerState {
+    fn created(
+        &mut self,
+        registry: Attached<wl_registry::WlRegistry>,
+        id: u32,
+        _version: u32,
+        _: DispatchData,
+    ) {
+        // only version 1 of the protocol exists
+        let manager = registry.bind::<ZwpRelativePointerManagerV1>(1, id);
+        self.manager = Some((*manager).clone());
+    }
+
+    fn get(&self) -> Option<Attached<ZwpRelativePointerManagerV1>> {
+        self.manager.clone()
+    }
+}
+
+/// A relative motion of a pointer
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RelativeMotion {
+    /// The horizontal motion, accelerated like the motion of the cursor
+    pub dx: f64,
+    /// The vertical motion, accelerated like the motion of the cursor
+    pub dy: f64,
+    /// The horizontal motion, without acceleration
+    pub dx_unaccel: f64,
+    /// The vertical motion, without acceleration
+    pub dy_unaccel: f64,
+    /// The timestamp of the motion, with microsecond granularity and an undefined base
+    pub time: Duration,
+}
+
+/// Statistics about the relative motions of a pointer
+///
+/// These are collected by each [`RelativePointer`](struct.RelativePointer.html), and can be
+/// used for sensitivity calibration, for example by asking the user to move the pointer by a
+/// known physical distance, or to show the polling rate of their mouse.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct RelativeMotionStats {
+    /// Number of relative motion events
+    pub events: u64,
+    /// Total unaccelerated travel along each axis, as sums of the absolute deltas
+    pub unaccel_travel: (f64, f64),
+    /// Total unaccelerated distance traveled
+    pub unaccel_distance: f64,
+    /// Total accelerated distance traveled
+    pub accel_distance: f64,
+    /// Timestamp of the first event
+    pub first_time: Option<Duration>,
+    /// Timestamp of the last event
+    pub last_time: Option<Duration>,
+}
+
+impl RelativeMotionStats {
+    /// Average number of events per second
+    ///
+    /// This is `None` until at least two events with different timestamps were received.
+    pub fn event_rate(&self) -> Option<f64> {
+        let elapsed = self.last_time?.checked_sub(self.first_time?)?.as_secs_f64();
+        if elapsed > 0.0 {
+            // the first event marks the start of the measure
+            Some((self.events - 1) as f64 / elapsed)
+        } else {
+            None
+        }
+    }
+
+    /// The ratio of the accelerated distance to the unaccelerated one
+    ///
+    /// This is the average effect of the pointer acceleration, `None` if the pointer did not
+    /// move.
+    pub fn acceleration_ratio(&self) -> Option<f64> {
+        if self.unaccel_distance > 0.0 {
+            Some(self.accel_distance / self.unaccel_distance)
+        } else {
+            None
+        }
+    }
+
+    fn record(&mut self, motion: &RelativeMotion) {
+        self.events += 1;
+        self.unaccel_travel.0 += motion.dx_unaccel.abs();
+        self.unaccel_travel.1 += motion.dy_unaccel.abs();
+        self.unaccel_distance += motion.dx_unaccel.hypot(motion.dy_unaccel);
+        self.accel_distance += motion.dx.hypot(motion.dy);
+        self.first_time.get_or_insert(motion.time);
+        self.last_time = Some(motion.time);
+    }
+}
+
+/// The relative motions of a pointer
+///
+/// The callback it was created with receives the relative motions of the pointer. They are
+/// sent along with the `wl_pointer` events of the same frame, and do not depend on the
+/// pointer focus. The relative pointer is destroyed when this is dropped.
+///
+/// Statistics about the motions are collected along the way, see
+/// [`stats`](#method.stats).
+#[derive(Debug)]
+pub struct RelativePointer {
+    relative: ZwpRelativePointerV1,
+    stats: Rc<RefCell<RelativeMotionStats>>,
+}
+
+impl RelativePointer {
+    /// Start receiving the relative motions of a pointer
+    pub fn new<F>(
+        manager: &Attached<ZwpRelativePointerManagerV1>,
+        pointer: &wl_pointer::WlPointer,
+        mut callback: F,
+    ) -> RelativePointer
+    where
+        F: FnMut(RelativeMotion, DispatchData) + 'static,
+    {
+        let stats = Rc::new(RefCell::new(RelativeMotionStats::default()));
+        let my_stats = stats.clone();
+        let relative = manager.get_relative_pointer(pointer);
+        relative.quick_assign(move |_, event, ddata| match event {
+            zwp_relative_pointer_v1::Event::RelativeMotion {
+                utime_hi,
+                utime_lo,
+                dx,
+                dy,
+                dx_unaccel,
+                dy_unaccel,
+            } => {
+                let utime = (u64::from(utime_hi) << 32) | u64::from(utime_lo);
+                let motion = RelativeMotion {
+                    dx,
+                    dy,
+                    dx_unaccel,
+                    dy_unaccel,
+                    time: Duration::from_micros(utime),
+                };
+                my_stats.borrow_mut().record(&motion);
+                callback(motion, ddata);
+            }
+            _ => unreachable!(),
+        });
+        RelativePointer { relative: relative.detach(), stats }
+    }
+
+    /// The statistics of the motions received since the creation or the last reset
+    pub fn stats(&self) -> RelativeMotionStats {
+        *self.stats.borrow()
+    }
+
+    /// Reset the statistics, for example at the start of a calibration
+    pub fn reset_stats(&self) {
+        *self.stats.borrow_mut() = RelativeMotionStats::default();
+    }
+
+    /// The underlying relative pointer object
+    pub fn relative_pointer(&self) -> &ZwpRelativePointerV1 {
+        &self.relative
+    }
+}
+
+impl Drop for RelativePointer {
+    fn drop(&mut self) {
+        self.relative.destroy();
+    }
+}
+
+impl<E: GlobalHandler<ZwpRelativePointerManagerV1>> Environment<E> {
+    /// Start receiving the relative motions of a pointer
+    ///
+    /// See [`RelativePointer`](../seat/pointer/relative/struct.RelativePointer.html). Returns an
+    /// error if the compositor does not support `zwp_relative_pointer_manager_v1`.
+    pub fn get_relative_pointer<F>(
+        &self,
+        pointer: &wl_pointer::WlPointer,
+        callback: F,
+    ) -> Result<RelativePointer, MissingGlobal>
+    where
+        F: FnMut(RelativeMotion, DispatchData) + 'static,
+    {
+        let manager = self.get_global::<ZwpRelativePointerManagerV1>().ok_or(MissingGlobal)?;
+        Ok(RelativePointer::new(&manager, pointer, callback))
+    }
+}
@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+
+use wayland_client::protocol::wl_pointer;
+
+/// An accumulator of scroll events
+///
+/// Wayland reports scrolling with high resolution, as amounts of surface units, along with
+/// the number of steps for wheel scrolling. This helper accumulates the axis events of a
+/// pointer, that you can then consume either as pixels, for canvases, or as whole lines,
+/// for widgets like lists.
+///
+/// When consumed as lines, the fraction of line left over is kept for the next events, so
+/// slow touchpad scrolling eventually scrolls a line without rounding drift. Widgets each
+/// keep their own leftover in a *bucket*, identified by an arbitrary `u64` key of your
+/// choosing, so that scrolling one widget does not affect another. Use
+/// [`reset_bucket`](#method.reset_bucket) when the pointer moves to another widget to
+/// drop the leftover of the previous one.
+///
+/// Alternatively, [`take_scroll`](#method.take_scroll) gives the scrolling in the unit
+/// chosen for its source by a [`ScrollPolicy`](struct.ScrollPolicy.html), so that wheels
+/// scroll by lines and touchpads by pixels, like in other desktop applications.
+#[derive(Debug, Default)]
+pub struct ScrollAccumulator {
+    // pending (horizontal, vertical) amounts, in surface units
+    pending: (f64, f64),
+    // pending (horizontal, vertical) wheel steps
+    pending_discrete: (i32, i32),
+    // source of the pending scrolling, only sent by pointers of version 5 or more
+    source: Option<wl_pointer::AxisSource>,
+    // leftover fraction of lines of each bucket
+    remainders: HashMap<u64, (f64, f64)>,
+    policy: ScrollPolicy,
+}
+
+/// The unit scrolling is reported in
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ScrollUnit {
+    /// Lines, for example of text
+    Lines,
+    /// Surface units, for smooth scrolling
+    Pixels,
+}
+
+/// The unit scrolling is reported in, for each source of scrolling
+///
+/// The default policy, consistent with most desktop applications, reports wheels in lines
+/// and touchpads and other continuous sources in pixels. Scrolling whose source is unknown,
+/// as with pointers older than version 5, is reported in lines if it comes with wheel steps
+/// and in pixels otherwise.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ScrollPolicy {
+    /// Unit of the scrolling of a mouse wheel
+    pub wheel: ScrollUnit,
+    /// Unit of the scrolling of a tilted mouse wheel
+    pub wheel_tilt: ScrollUnit,
+    /// Unit of the scrolling with fingers, like on a touchpad
+    pub finger: ScrollUnit,
+    /// Unit of continuous scrolling, like moving a pointer with a button held
+    pub continuous: ScrollUnit,
+}
+
+impl Default for ScrollPolicy {
+    fn default() -> ScrollPolicy {
+        ScrollPolicy {
+            wheel: ScrollUnit::Lines,
+            wheel_tilt: ScrollUnit::Lines,
+            finger: ScrollUnit::Pixels,
+            continuous: ScrollUnit::Pixels,
+        }
+    }
+}
+
+/// An amount of scrolling, in the unit chosen by a [`ScrollPolicy`](struct.ScrollPolicy.html)
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ScrollAmount {
+    /// Scrolling by lines, possibly by fractions of lines
+    Lines {
+        /// horizontal number of lines
+        horizontal: f64,
+        /// vertical number of lines
+        vertical: f64,
+    },
+    /// Scrolling by surface units
+    Pixels {
+        /// horizontal amount
+        horizontal: f64,
+        /// vertical amount
+        vertical: f64,
+    },
+}
+
+impl ScrollAccumulator {
+    /// Create a new accumulator
+    pub fn new() -> ScrollAccumulator {
+        ScrollAccumulator::default()
+    }
+
+    /// Process an event of the pointer
+    ///
+    /// All events can be given, only the axis ones are used.
+    pub fn handle_event(&mut self, event: &wl_pointer::Event) {
+        match *event {
+            wl_pointer::Event::Axis { axis, value, .. } => match axis {
+                wl_pointer::Axis::HorizontalScroll => self.pending.0 += value,
+                wl_pointer::Axis::VerticalScroll => self.pending.1 += value,
+                _ => {}
+            },
+            wl_pointer::Event::AxisDiscrete { axis, discrete } => match axis {
+                wl_pointer::Axis::HorizontalScroll => self.pending_discrete.0 += discrete,
+                wl_pointer::Axis::VerticalScroll => self.pending_discrete.1 += discrete,
+                _ => {}
+            },
+            wl_pointer::Event::AxisSource { axis_source } => self.source = Some(axis_source),
+            _ => {}
+        }
+    }
+
+    /// Whether some scrolling is waiting to be consumed
+    pub fn has_pending(&self) -> bool {
+        self.pending != (0.0, 0.0) || self.pending_discrete != (0, 0)
+    }
+
+    /// Consume the pending scrolling as amounts of surface units
+    ///
+    /// Returns `(horizontal, vertical)` amounts.
+    pub fn take_pixels(&mut self) -> (f64, f64) {
+        self.pending_discrete = (0, 0);
+        self.source = None;
+        std::mem::take(&mut self.pending)
+    }
+
+    /// Consume the pending scrolling as whole lines, for the widget of given bucket
+    ///
+    /// `line_size` is the amount of surface units a line represents. Wheel steps are always
+    /// counted as one line each, and on an axis with wheel steps the leftover of the bucket
+    /// is dropped. Otherwise the leftover fraction is kept in the bucket for the next call.
+    ///
+    /// Returns `(horizontal, vertical)` numbers of lines.
+    pub fn take_lines(&mut self, bucket: u64, line_size: f64) -> (i32, i32) {
+        let pixels = std::mem::take(&mut self.pending);
+        let discrete = std::mem::take(&mut self.pending_discrete);
+        self.source = None;
+        let remainder = self.remainders.entry(bucket).or_insert((0.0, 0.0));
+        let axis = |pixels: f64, discrete: i32, remainder: &mut f64| {
+            if discrete != 0 {
+                *remainder = 0.0;
+                return discrete;
+            }
+            if line_size <= 0.0 {
+                return 0;
+            }
+            let total = *remainder + pixels / line_size;
+            let lines = total.trunc();
+            *remainder = total - lines;
+            lines as i32
+        };
+        let horizontal = axis(pixels.0, discrete.0, &mut remainder.0);
+        let vertical = axis(pixels.1, discrete.1, &mut remainder.1);
+        (horizontal, vertical)
+    }
+
+    /// Set the policy choosing the unit of [`take_scroll`](#method.take_scroll)
+    pub fn set_policy(&mut self, policy: ScrollPolicy) {
+        self.policy = policy;
+    }
+
+    /// The policy choosing the unit of [`take_scroll`](#method.take_scroll)
+    pub fn policy(&self) -> ScrollPolicy {
+        self.policy
+    }
+
+    /// Consume the pending scrolling, in the unit chosen by the policy for its source
+    ///
+    /// `line_size` is the amount of surface units a line represents. When scrolling by
+    /// lines, wheel steps are counted as one line each, and other scrolling is converted to
+    /// fractions of lines. Returns `None` if no scrolling is pending.
+    pub fn take_scroll(&mut self, line_size: f64) -> Option<ScrollAmount> {
+        if !self.has_pending() {
+            return None;
+        }
+        let pixels = std::mem::take(&mut self.pending);
+        let discrete = std::mem::take(&mut self.pending_discrete);
+        let unit = match self.source.take() {
+            Some(wl_pointer::AxisSource::Wheel) => self.policy.wheel,
+            Some(wl_pointer::AxisSource::WheelTilt) => self.policy.wheel_tilt,
+            Some(wl_pointer::AxisSource::Finger) => self.policy.finger,
+            Some(wl_pointer::AxisSource::Continuous) => self.policy.continuous,
+            _ if discrete != (0, 0) => ScrollUnit::Lines,
+            _ => ScrollUnit::Pixels,
+        };
+        Some(match unit {
+            ScrollUnit::Pixels => ScrollAmount::Pixels { horizontal: pixels.0, vertical: pixels.1 },
+            ScrollUnit::Lines => {
+                let axis = |pixels: f64, discrete: i32| {
+                    if discrete != 0 {
+                        f64::from(discrete)
+                    } else if line_size > 0.0 {
+                        pixels / line_size
+                    } else {
+                        0.0
+                    }
+                };
+                ScrollAmount::Lines {
+                    horizontal: axis(pixels.0, discrete.0),
+                    vertical: axis(pixels.1, discrete.1),
+                }
+            }
+        })
+    }
+
+    /// Drop the leftover fraction of line of a bucket
+    ///
+    /// Call this when the pointer leaves the widget of this bucket, so that scrolling it
+    /// again starts from a clean state.
+    pub fn reset_bucket(&mut self, bucket: u64) {
+        self.remainders.remove(&bucket);
+    }
+
+    /// Drop all the pending scrolling and the leftovers of all buckets
+    pub fn reset(&mut self) {
+        self.pending = (0.0, 0.0);
+        self.pending_discrete = (0, 0);
+        self.source = None;
+        self.remainders.clear();
+    }
+}
@@ -5,7 +5,7 @@ use std::{
     rc::{Rc, Weak},
 };
 use wayland_client::{
-    protocol::{wl_compositor, wl_pointer, wl_seat, wl_shm, wl_surface},
+    protocol::{wl_buffer, wl_callback, wl_compositor, wl_pointer, wl_seat, wl_shm, wl_surface},
     Attached, DispatchData,
 };
 use wayland_cursor::{Cursor, CursorTheme};
@@ -32,6 +32,26 @@ pub enum ThemeSpec<'a> {
     System,
 }
 
+/// A cursor image provided by the application
+///
+/// See [`ThemeManager::register_custom_cursor`](struct.ThemeManager.html#method.register_custom_cursor).
+#[derive(Debug, Clone)]
+pub struct CustomCursor {
+    /// The buffer containing the cursor image
+    ///
+    /// It is attached to the cursor surface every time the cursor is set, and must thus
+    /// be kept alive, and not be modified, as long as the cursor is registered.
+    pub buffer: wl_buffer::WlBuffer,
+    /// Size of the buffer, in pixels
+    pub size: (u32, u32),
+    /// Scale of the buffer
+    ///
+    /// A buffer of size `(64, 64)` with a scale of 2 is shown as a 32x32 cursor.
+    pub scale: u32,
+    /// Position of the hotspot, in pixels of the buffer
+    pub hotspot: (u32, u32),
+}
+
 /// Wrapper managing a system theme for pointer images
 ///
 /// You can use it to initialize new pointers in order
@@ -59,6 +79,43 @@ impl ThemeManager {
         ThemeManager { compositor, themes: Rc::new(RefCell::new(ScaledThemeList::new(theme, shm))) }
     }
 
+    /// Register a custom cursor image under a name
+    ///
+    /// Custom cursors override the cursors of the theme: setting a pointer to this name
+    /// will show the provided image, whether the theme has a cursor of that name or not.
+    /// Registering a cursor under an already registered name replaces it.
+    ///
+    /// Pointers currently showing this cursor are not updated until it is set again.
+    pub fn register_custom_cursor(&self, name: &str, cursor: CustomCursor) {
+        let mut themes = self.themes.borrow_mut();
+        themes.custom.retain(|(n, _)| n != name);
+        themes.custom.push((name.into(), cursor));
+    }
+
+    /// Set the cursor shown over the content of windows
+    ///
+    /// Frames that theme the pointer over the content of their window, because no
+    /// `ThemeManager` was given to the window, show this cursor when the pointer goes
+    /// back from the decorations to the content. It defaults to `left_ptr`.
+    pub fn set_content_cursor(&self, name: &str) {
+        self.themes.borrow_mut().content_cursor = name.into();
+    }
+
+    /// Get the cursor shown over the content of windows
+    pub fn content_cursor(&self) -> String {
+        self.themes.borrow().content_cursor.clone()
+    }
+
+    /// Unregister a custom cursor image
+    ///
+    /// The cursor of the theme with this name, if any, will be used again. Returns
+    /// the custom cursor if one was registered with this name.
+    pub fn unregister_custom_cursor(&self, name: &str) -> Option<CustomCursor> {
+        let mut themes = self.themes.borrow_mut();
+        let idx = themes.custom.iter().position(|(n, _)| n == name)?;
+        Some(themes.custom.remove(idx).1)
+    }
+
     /// Wrap a pointer to theme it
     pub fn theme_pointer(&self, pointer: wl_pointer::WlPointer) -> ThemedPointer {
         let surface = self.compositor.create_surface();
@@ -66,8 +123,11 @@ impl ThemeManager {
             surface: surface.detach(),
             themes: self.themes.clone(),
             last_serial: 0,
+            enter_serial: None,
             current_cursor: "left_ptr".into(),
             scale_factor: 1,
+            animation: 0,
+            hidden: false,
         }));
         let my_pointer = pointer.clone();
         let winner = Rc::downgrade(&inner);
@@ -105,13 +165,23 @@ impl ThemeManager {
             surface: surface.detach(),
             themes: self.themes.clone(),
             last_serial: 0,
+            enter_serial: None,
             current_cursor: "left_ptr".into(),
             scale_factor: 1,
+            animation: 0,
+            hidden: false,
         }));
 
         let inner2 = inner.clone();
         let pointer = seat.get_pointer();
         pointer.quick_assign(move |ptr, event, ddata| {
+            match event {
+                wl_pointer::Event::Enter { serial, .. } => {
+                    inner2.borrow_mut().enter_serial = Some(serial);
+                }
+                wl_pointer::Event::Leave { .. } => inner2.borrow_mut().enter_serial = None,
+                _ => {}
+            }
             callback(event, ThemedPointer { pointer: ptr.detach(), inner: inner2.clone() }, ddata)
         });
 
@@ -140,6 +210,8 @@ struct ScaledThemeList {
     name: String,
     size: u32,
     themes: Vec<(u32, CursorTheme)>,
+    custom: Vec<(String, CustomCursor)>,
+    content_cursor: String,
 }
 
 impl ScaledThemeList {
@@ -153,7 +225,14 @@ impl ScaledThemeList {
                 (name, size)
             }
         };
-        ScaledThemeList { shm, name, size, themes: vec![] }
+        ScaledThemeList {
+            shm,
+            name,
+            size,
+            themes: vec![],
+            custom: vec![],
+            content_cursor: "left_ptr".into(),
+        }
     }
 
     fn get_cursor(&mut self, name: &str, scale: u32) -> Option<&Cursor> {
@@ -177,6 +256,8 @@ impl fmt::Debug for ScaledThemeList {
             .field("size", &self.size)
             // Wayland-cursor needs to implement debug
             .field("themes", &"[...]")
+            .field("custom", &self.custom)
+            .field("content_cursor", &self.content_cursor)
             .finish()
     }
 }
@@ -187,19 +268,84 @@ struct PointerInner {
     themes: Rc<RefCell<ScaledThemeList>>,
     current_cursor: String,
     last_serial: u32,
+    // only known if the events of the pointer are dispatched by SCTK
+    enter_serial: Option<u32>,
     scale_factor: i32,
+    // incremented when the cursor changes, to stop the animation of the previous one
+    animation: u32,
+    hidden: bool,
 }
 
 impl PointerInner {
+    // Pick the serial of a `set_cursor` request, preferring the one given by the application
+    fn use_serial(&mut self, serial: Option<u32>) {
+        match (serial, self.enter_serial) {
+            (Some(s), enter_serial) => {
+                if let Some(enter_serial) = enter_serial {
+                    // serials are increasing, a serial older than the last enter is stale
+                    crate::strict::check(
+                        "wl_pointer.set_cursor",
+                        s.wrapping_sub(enter_serial) as i32 >= 0,
+                        || {
+                            format!(
+                                "serial {} predates the last pointer enter (serial {})",
+                                s, enter_serial
+                            )
+                        },
+                    );
+                }
+                self.last_serial = s;
+            }
+            (None, Some(enter_serial)) => self.last_serial = enter_serial,
+            (None, None) => {}
+        }
+    }
+
     fn update_cursor(&self, pointer: &wl_pointer::WlPointer) -> Result<(), CursorNotFound> {
+        if self.hidden {
+            return Ok(());
+        }
+        self.draw_frame(pointer, 0)
+    }
+
+    fn draw_frame(
+        &self,
+        pointer: &wl_pointer::WlPointer,
+        elapsed: u32,
+    ) -> Result<(), CursorNotFound> {
         let mut themes = self.themes.borrow_mut();
+        // custom cursors take precedence over the theme
+        if let Some((_, custom)) = themes.custom.iter().find(|(n, _)| *n == self.current_cursor) {
+            self.attach_cursor(pointer, &custom.buffer, custom.size, custom.hotspot, custom.scale);
+            return Ok(());
+        }
         let scale = self.scale_factor as u32;
         let cursor = themes.get_cursor(&self.current_cursor, scale).ok_or(CursorNotFound)?;
-        let image = &cursor[0];
-        let (w, h) = image.dimensions();
-        let (hx, hy) = image.hotspot();
+        let image = &cursor[cursor.frame_and_duration(elapsed).frame_index];
+        self.attach_cursor(pointer, image, image.dimensions(), image.hotspot(), scale);
+        Ok(())
+    }
+
+    fn is_animated(&self) -> bool {
+        let mut themes = self.themes.borrow_mut();
+        if themes.custom.iter().any(|(n, _)| *n == self.current_cursor) {
+            return false;
+        }
+        let scale = self.scale_factor as u32;
+        themes.get_cursor(&self.current_cursor, scale).map(|c| c.image_count() > 1).unwrap_or(false)
+    }
+
+    fn attach_cursor(
+        &self,
+        pointer: &wl_pointer::WlPointer,
+        buffer: &wl_buffer::WlBuffer,
+        (w, h): (u32, u32),
+        (hx, hy): (u32, u32),
+        scale: u32,
+    ) {
+        let scale = scale.max(1);
         self.surface.set_buffer_scale(scale as i32);
-        self.surface.attach(Some(image), 0, 0);
+        self.surface.attach(Some(buffer), 0, 0);
         if self.surface.as_ref().version() >= 4 {
             self.surface.damage_buffer(0, 0, w as i32, h as i32);
         } else {
@@ -214,7 +360,6 @@ impl PointerInner {
             hx as i32 / scale as i32,
             hy as i32 / scale as i32,
         );
-        Ok(())
     }
 }
 
@@ -234,19 +379,97 @@ pub struct ThemedPointer {
 impl ThemedPointer {
     /// Change the cursor to the given cursor name
     ///
-    /// Possible names depend on the theme. Does nothing and returns
+    /// Possible names depend on the theme, and on the custom cursors registered
+    /// to the `ThemeManager`, which take precedence over it. Does nothing and returns
     /// `Err` if given name is not available.
     ///
-    /// If this is done as an answer to an input event, you need to provide
-    /// the associated serial otherwise the server may ignore the request.
+    /// If the cursor of the theme has several frames, like the `wait` cursor of most themes,
+    /// it is animated from the frame callbacks of the cursor surface until the cursor changes.
+    ///
+    /// The protocol requires the serial of the last `enter` event of the pointer. If
+    /// `serial` is `None` and the pointer was created with
+    /// [`ThemeManager::theme_pointer_with_impl`](struct.ThemeManager.html#method.theme_pointer_with_impl),
+    /// SCTK uses the serial it tracked from the last `enter` event, while the pointer is on one
+    /// of your surfaces. Otherwise you need to provide it, or the server may ignore the request.
+    ///
+    /// This shows the cursor if it was hidden with [`hide_cursor`](#method.hide_cursor).
     pub fn set_cursor(&self, name: &str, serial: Option<u32>) -> Result<(), CursorNotFound> {
         let mut inner = self.inner.borrow_mut();
-        if let Some(s) = serial {
-            inner.last_serial = s;
-        }
+        inner.use_serial(serial);
         inner.current_cursor = name.into();
+        inner.hidden = false;
+        inner.animation = inner.animation.wrapping_add(1);
+        if inner.is_animated() {
+            // the frame callback must be requested before the cursor surface is committed
+            animate_cursor(&inner, &self.inner, &self.pointer, None);
+        }
         inner.update_cursor(&self.pointer)
     }
+
+    /// The serial of the last `enter` event of this pointer
+    ///
+    /// This is `None` once the pointer left your surfaces, and is only known if the pointer was created with
+    /// [`ThemeManager::theme_pointer_with_impl`](struct.ThemeManager.html#method.theme_pointer_with_impl).
+    pub fn enter_serial(&self) -> Option<u32> {
+        self.inner.borrow().enter_serial
+    }
+
+    /// Hide the cursor
+    ///
+    /// The cursor stays hidden, even if the scale factor changes, until it is shown again with
+    /// [`show_cursor`](#method.show_cursor) or [`set_cursor`](#method.set_cursor). The
+    /// `serial` argument is handled as for `set_cursor`.
+    pub fn hide_cursor(&self, serial: Option<u32>) {
+        let mut inner = self.inner.borrow_mut();
+        inner.use_serial(serial);
+        inner.hidden = true;
+        // stop the animation of the cursor
+        inner.animation = inner.animation.wrapping_add(1);
+        self.pointer.set_cursor(inner.last_serial, None, 0, 0);
+    }
+
+    /// Show the cursor hidden with [`hide_cursor`](#method.hide_cursor) again
+    ///
+    /// The last cursor set with `set_cursor` is restored.
+    pub fn show_cursor(&self, serial: Option<u32>) -> Result<(), CursorNotFound> {
+        let name = self.inner.borrow().current_cursor.clone();
+        self.set_cursor(&name, serial)
+    }
+
+    /// Whether the cursor is hidden
+    pub fn is_cursor_hidden(&self) -> bool {
+        self.inner.borrow().hidden
+    }
+}
+
+// Animate the cursor from the frame callbacks of its surface, `start` is the time of the first frame
+fn animate_cursor(
+    inner: &PointerInner,
+    handle: &Rc<RefCell<PointerInner>>,
+    pointer: &wl_pointer::WlPointer,
+    start: Option<u32>,
+) {
+    let animation = inner.animation;
+    let handle = Rc::downgrade(handle);
+    let pointer = pointer.clone();
+    inner.surface.frame().quick_assign(move |_, event, _| {
+        let time = match event {
+            wl_callback::Event::Done { callback_data } => callback_data,
+            _ => return,
+        };
+        let handle = match Weak::upgrade(&handle) {
+            Some(handle) => handle,
+            None => return,
+        };
+        let inner = handle.borrow();
+        if inner.animation != animation || !inner.is_animated() {
+            return;
+        }
+        let start = start.unwrap_or(time);
+        animate_cursor(&inner, &handle, &pointer, Some(start));
+        // errors cannot be handled here, the cursor just stops being animated
+        let _ = inner.draw_frame(&pointer, time.wrapping_sub(start));
+    });
 }
 
 impl Deref for ThemedPointer {
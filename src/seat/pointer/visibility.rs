@@ -0,0 +1,114 @@
+use wayland_client::protocol::wl_pointer;
+
+use super::ThemedPointer;
+use crate::seat::keyboard::{keysyms, Event as KbdEvent, KeyState};
+
+/// A policy hiding the cursor while typing
+///
+/// Video players and text editors commonly hide the cursor when the keyboard is used, so that
+/// it does not cover the content, and show it again as soon as the pointer is used. This
+/// helper implements this policy for a set of themed pointers: give it the events of your
+/// keyboards and pointers, and it hides and shows their cursors accordingly.
+///
+/// Pressing modifier keys alone does not hide the cursor, as they are often used along with
+/// the pointer.
+#[derive(Debug)]
+pub struct HideWhileTyping {
+    pointers: Vec<ThemedPointer>,
+    enabled: bool,
+}
+
+impl HideWhileTyping {
+    /// Create a new policy, with no pointer
+    pub fn new() -> HideWhileTyping {
+        HideWhileTyping { pointers: Vec::new(), enabled: true }
+    }
+
+    /// Add a pointer whose cursor is handled by this policy
+    pub fn add_pointer(&mut self, pointer: ThemedPointer) {
+        self.pointers.push(pointer);
+    }
+
+    /// Remove a pointer from this policy
+    ///
+    /// Its cursor is shown again if it was hidden.
+    pub fn remove_pointer(&mut self, pointer: &wl_pointer::WlPointer) {
+        self.pointers.retain(|p| {
+            if **p == *pointer {
+                show(p, None);
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    /// Enable or disable the policy
+    ///
+    /// When disabled, the cursors are shown and are not hidden anymore until the policy is
+    /// enabled again, for example while a menu that needs the pointer is open.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.pointers.iter().for_each(|p| show(p, None));
+        }
+    }
+
+    /// Process an event of a keyboard
+    ///
+    /// All events can be given, the cursors are hidden on key presses and repetitions.
+    pub fn handle_keyboard_event(&mut self, event: &KbdEvent) {
+        let keysym = match *event {
+            KbdEvent::Key { keysym, state: KeyState::Pressed, .. } => keysym,
+            KbdEvent::Repeat { keysym, .. } => keysym,
+            _ => return,
+        };
+        if !self.enabled || is_modifier(keysym) {
+            return;
+        }
+        for pointer in &self.pointers {
+            if !pointer.is_cursor_hidden() {
+                pointer.hide_cursor(None);
+            }
+        }
+    }
+
+    /// Process an event of a pointer
+    ///
+    /// All events can be given, the cursor of the pointer is shown again when it enters a
+    /// surface, moves, or when a button or axis is used.
+    pub fn handle_pointer_event(
+        &mut self,
+        pointer: &wl_pointer::WlPointer,
+        event: &wl_pointer::Event,
+    ) {
+        let serial = match *event {
+            wl_pointer::Event::Enter { serial, .. } => Some(serial),
+            wl_pointer::Event::Motion { .. }
+            | wl_pointer::Event::Button { .. }
+            | wl_pointer::Event::Axis { .. } => None,
+            _ => return,
+        };
+        if let Some(p) = self.pointers.iter().find(|p| ***p == *pointer) {
+            show(p, serial);
+        }
+    }
+
+    /// Whether the cursor of any of the pointers is hidden
+    pub fn is_hidden(&self) -> bool {
+        self.pointers.iter().any(|p| p.is_cursor_hidden())
+    }
+}
+
+fn show(pointer: &ThemedPointer, serial: Option<u32>) {
+    if pointer.is_cursor_hidden() {
+        // errors cannot be handled here, the cursor was valid when it was set
+        let _ = pointer.show_cursor(serial);
+    }
+}
+
+fn is_modifier(keysym: u32) -> bool {
+    (keysyms::XKB_KEY_Shift_L..=keysyms::XKB_KEY_Hyper_R).contains(&keysym)
+        || keysym == keysyms::XKB_KEY_ISO_Level3_Shift
+        || keysym == keysyms::XKB_KEY_Mode_switch
+}
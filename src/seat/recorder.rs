@@ -0,0 +1,560 @@
+//! Recording and replay of input events
+//!
+//! This module provides an [`InputRecorder`](struct.InputRecorder.html), which captures
+//! the keyboard events produced by a mapped keyboard and the pointer events of a `wl_pointer`
+//! and can store them in a compact binary log, and an [`InputReplay`](struct.InputReplay.html)
+//! reading such a log back, to feed its events to your input handling code.
+//!
+//! This is intended to make input-handling bugs reproducible: a user can record a session
+//! exhibiting the bug and attach the log to their report. To avoid leaking information about
+//! the session, the recorded events are anonymized: surfaces are identified by their order
+//! of appearance rather than their protocol id, event timestamps are rebased to start at 0,
+//! and no serial is recorded. The keys are redacted as well, so that typed text like passwords
+//! is not recorded, unless explicitly enabled with
+//! [`InputRecorder::set_record_keys`](struct.InputRecorder.html#method.set_record_keys).
+//!
+//! As the replayed events are not associated to actual protocol objects, they are given as
+//! [`RecordedEvent`](enum.RecordedEvent.html), in which surfaces are represented by their
+//! anonymized index.
+
+use std::{
+    io::{self, Read, Write},
+    time::{Duration, Instant},
+};
+
+use wayland_client::protocol::{wl_pointer, wl_surface};
+
+use super::keyboard::{self, KeyState, ModifiersState};
+
+const MAGIC: &[u8; 8] = b"SCTKINP1";
+
+/// An input event, as recorded by an `InputRecorder`
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecordedEvent {
+    /// The keyboard focus has entered a surface
+    KeyboardEnter {
+        /// anonymized index of the surface
+        surface: u32,
+        /// raw values of the currently pressed keys
+        rawkeys: Vec<u32>,
+        /// interpreted symbols of the currently pressed keys
+        keysyms: Vec<u32>,
+    },
+    /// The keyboard focus has left a surface
+    KeyboardLeave {
+        /// anonymized index of the surface
+        surface: u32,
+    },
+    /// The key modifiers have changed state
+    Modifiers {
+        /// current state of the modifiers
+        modifiers: ModifiersState,
+    },
+    /// A key event occurred
+    Key {
+        /// rebased time of the event
+        time: u32,
+        /// raw value of the key
+        rawkey: u32,
+        /// interpreted symbol of the key
+        keysym: u32,
+        /// new state of the key
+        state: KeyState,
+        /// utf8 interpretation of the entered text
+        utf8: Option<String>,
+    },
+    /// A key repetition event
+    Repeat {
+        /// rebased time of the event
+        time: u32,
+        /// raw value of the key
+        rawkey: u32,
+        /// interpreted symbol of the key
+        keysym: u32,
+        /// utf8 interpretation of the entered text
+        utf8: Option<String>,
+    },
+    /// The pointer has entered a surface
+    PointerEnter {
+        /// anonymized index of the surface
+        surface: u32,
+        /// horizontal position in the surface
+        surface_x: f64,
+        /// vertical position in the surface
+        surface_y: f64,
+    },
+    /// The pointer has left a surface
+    PointerLeave {
+        /// anonymized index of the surface
+        surface: u32,
+    },
+    /// The pointer has moved
+    PointerMotion {
+        /// rebased time of the event
+        time: u32,
+        /// horizontal position in the surface
+        surface_x: f64,
+        /// vertical position in the surface
+        surface_y: f64,
+    },
+    /// A pointer button was pressed or released
+    PointerButton {
+        /// rebased time of the event
+        time: u32,
+        /// button code, as defined by the linux kernel
+        button: u32,
+        /// new state of the button
+        state: wl_pointer::ButtonState,
+    },
+    /// A scroll occurred
+    PointerAxis {
+        /// rebased time of the event
+        time: u32,
+        /// the scrolled axis
+        axis: wl_pointer::Axis,
+        /// the scroll length
+        value: f64,
+    },
+    /// The source of the following scroll events
+    PointerAxisSource {
+        /// the source of the scroll
+        axis_source: wl_pointer::AxisSource,
+    },
+    /// A scroll on an axis stopped
+    PointerAxisStop {
+        /// rebased time of the event
+        time: u32,
+        /// the axis whose scroll stopped
+        axis: wl_pointer::Axis,
+    },
+    /// A discrete scroll occurred
+    PointerAxisDiscrete {
+        /// the scrolled axis
+        axis: wl_pointer::Axis,
+        /// the number of scroll steps
+        discrete: i32,
+    },
+    /// End of a group of pointer events
+    PointerFrame,
+}
+
+/// A recorder of input events
+///
+/// Feed it the events you receive from your mapped keyboards and pointers, using
+/// `record_keyboard()` and `record_pointer()`, and save them with `write_to()`.
+///
+/// By default, the keys of the keyboard events are redacted: their raw values and keysyms
+/// are recorded as 0, and their text as `None`. Only the timing of the key events and the
+/// modifiers are kept.
+#[derive(Debug)]
+pub struct InputRecorder {
+    start: Instant,
+    record_keys: bool,
+    time_base: Option<u32>,
+    surfaces: Vec<wl_surface::WlSurface>,
+    events: Vec<(Duration, RecordedEvent)>,
+}
+
+impl InputRecorder {
+    /// Create a new recorder
+    ///
+    /// The delays of the recorded events are measured from the creation of the recorder.
+    pub fn new() -> InputRecorder {
+        InputRecorder {
+            start: Instant::now(),
+            record_keys: false,
+            time_base: None,
+            surfaces: Vec::new(),
+            events: Vec::new(),
+        }
+    }
+
+    /// Set whether the keys of the keyboard events are recorded
+    ///
+    /// This is disabled by default. Only enable it when the user agreed to share the text
+    /// they type during the recording, as it may contain passwords or other private data.
+    pub fn set_record_keys(&mut self, record_keys: bool) {
+        self.record_keys = record_keys;
+    }
+
+    /// Record an event from a mapped keyboard
+    pub fn record_keyboard(&mut self, event: &keyboard::Event<'_>) {
+        let keys = self.record_keys;
+        let redact = |value: u32| if keys { value } else { 0 };
+        let redact_text = |text: &Option<String>| if keys { text.clone() } else { None };
+        let event = match *event {
+            keyboard::Event::Enter { ref surface, rawkeys, keysyms, .. } => {
+                RecordedEvent::KeyboardEnter {
+                    surface: self.surface_index(surface),
+                    rawkeys: rawkeys.iter().map(|&k| redact(k)).collect(),
+                    keysyms: keysyms.iter().map(|&k| redact(k)).collect(),
+                }
+            }
+            keyboard::Event::Leave { ref surface, .. } => {
+                RecordedEvent::KeyboardLeave { surface: self.surface_index(surface) }
+            }
+            keyboard::Event::Modifiers { modifiers } => RecordedEvent::Modifiers { modifiers },
+            keyboard::Event::Key { time, rawkey, keysym, state, ref utf8, .. } => {
+                RecordedEvent::Key {
+                    time: self.rebase(time),
+                    rawkey: redact(rawkey),
+                    keysym: redact(keysym),
+                    state,
+                    utf8: redact_text(utf8),
+                }
+            }
+            keyboard::Event::Repeat { time, rawkey, keysym, ref utf8 } => RecordedEvent::Repeat {
+                time: self.rebase(time),
+                rawkey: redact(rawkey),
+                keysym: redact(keysym),
+                utf8: redact_text(utf8),
+            },
+        };
+        self.push(event);
+    }
+
+    /// Record an event from a `wl_pointer`
+    pub fn record_pointer(&mut self, event: &wl_pointer::Event) {
+        use wl_pointer::Event;
+        let event = match *event {
+            Event::Enter { ref surface, surface_x, surface_y, .. } => RecordedEvent::PointerEnter {
+                surface: self.surface_index(surface),
+                surface_x,
+                surface_y,
+            },
+            Event::Leave { ref surface, .. } => {
+                RecordedEvent::PointerLeave { surface: self.surface_index(surface) }
+            }
+            Event::Motion { time, surface_x, surface_y } => {
+                RecordedEvent::PointerMotion { time: self.rebase(time), surface_x, surface_y }
+            }
+            Event::Button { time, button, state, .. } => {
+                RecordedEvent::PointerButton { time: self.rebase(time), button, state }
+            }
+            Event::Axis { time, axis, value } => {
+                RecordedEvent::PointerAxis { time: self.rebase(time), axis, value }
+            }
+            Event::AxisSource { axis_source } => RecordedEvent::PointerAxisSource { axis_source },
+            Event::AxisStop { time, axis } => {
+                RecordedEvent::PointerAxisStop { time: self.rebase(time), axis }
+            }
+            Event::AxisDiscrete { axis, discrete } => {
+                RecordedEvent::PointerAxisDiscrete { axis, discrete }
+            }
+            Event::Frame => RecordedEvent::PointerFrame,
+            _ => return,
+        };
+        self.push(event);
+    }
+
+    /// Access the events recorded so far, with their delay since the creation of the recorder
+    pub fn events(&self) -> &[(Duration, RecordedEvent)] {
+        &self.events
+    }
+
+    /// Write the recorded events to a binary log
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        for (delay, event) in &self.events {
+            write_u32(&mut writer, delay.as_millis() as u32)?;
+            write_event(&mut writer, event)?;
+        }
+        writer.flush()
+    }
+
+    fn push(&mut self, event: RecordedEvent) {
+        self.events.push((self.start.elapsed(), event));
+    }
+
+    fn rebase(&mut self, time: u32) -> u32 {
+        let base = *self.time_base.get_or_insert(time);
+        time.wrapping_sub(base)
+    }
+
+    fn surface_index(&mut self, surface: &wl_surface::WlSurface) -> u32 {
+        let position = self.surfaces.iter().position(|s| s.as_ref().equals(surface.as_ref()));
+        let index = match position {
+            Some(index) => index,
+            None => {
+                self.surfaces.push(surface.clone());
+                self.surfaces.len() - 1
+            }
+        };
+        index as u32
+    }
+}
+
+/// A log of input events to replay
+///
+/// It can be read from the log written by an `InputRecorder`.
+#[derive(Debug)]
+pub struct InputReplay {
+    events: Vec<(Duration, RecordedEvent)>,
+}
+
+impl InputReplay {
+    /// Read a binary log written by `InputRecorder::write_to()`
+    pub fn read_from<R: Read>(mut reader: R) -> io::Result<InputReplay> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not an input log"));
+        }
+        let mut events = Vec::new();
+        loop {
+            let delay = match read_u32(&mut reader) {
+                Ok(delay) => Duration::from_millis(delay.into()),
+                Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e),
+            };
+            events.push((delay, read_event(&mut reader)?));
+        }
+        Ok(InputReplay { events })
+    }
+
+    /// Access the events of the log, with their delay since the start of the recording
+    ///
+    /// You can use the delays to schedule the replay of each event, for example with
+    /// calloop timers, if timing matters for the issue you are investigating.
+    pub fn events(&self) -> &[(Duration, RecordedEvent)] {
+        &self.events
+    }
+
+    /// Feed all the events of the log to given callback, in order
+    pub fn replay<F: FnMut(&RecordedEvent)>(&self, mut f: F) {
+        for (_, event) in &self.events {
+            f(event);
+        }
+    }
+}
+
+/*
+ * Binary encoding
+ */
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn write_u32<W: Write>(w: &mut W, v: u32) -> io::Result<()> {
+    w.write_all(&v.to_le_bytes())
+}
+
+fn write_f64<W: Write>(w: &mut W, v: f64) -> io::Result<()> {
+    w.write_all(&v.to_le_bytes())
+}
+
+fn write_u32s<W: Write>(w: &mut W, v: &[u32]) -> io::Result<()> {
+    write_u32(w, v.len() as u32)?;
+    for &x in v {
+        write_u32(w, x)?;
+    }
+    Ok(())
+}
+
+fn write_string<W: Write>(w: &mut W, s: &Option<String>) -> io::Result<()> {
+    match *s {
+        Some(ref s) => {
+            w.write_all(&[1])?;
+            write_u32(w, s.len() as u32)?;
+            w.write_all(s.as_bytes())
+        }
+        None => w.write_all(&[0]),
+    }
+}
+
+fn read_u8<R: Read>(r: &mut R) -> io::Result<u8> {
+    let mut buf = [0u8; 1];
+    r.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_u32<R: Read>(r: &mut R) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    r.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_f64<R: Read>(r: &mut R) -> io::Result<f64> {
+    let mut buf = [0u8; 8];
+    r.read_exact(&mut buf)?;
+    Ok(f64::from_le_bytes(buf))
+}
+
+fn read_u32s<R: Read>(r: &mut R) -> io::Result<Vec<u32>> {
+    let len = read_u32(r)?;
+    (0..len).map(|_| read_u32(r)).collect()
+}
+
+fn read_string<R: Read>(r: &mut R) -> io::Result<Option<String>> {
+    if read_u8(r)? == 0 {
+        return Ok(None);
+    }
+    let len = read_u32(r)? as usize;
+    let mut buf = Vec::new();
+    r.take(len as u64).read_to_end(&mut buf)?;
+    if buf.len() != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    String::from_utf8(buf).map(Some).map_err(|_| invalid_data("invalid utf8 text"))
+}
+
+fn read_axis<R: Read>(r: &mut R) -> io::Result<wl_pointer::Axis> {
+    wl_pointer::Axis::from_raw(read_u32(r)?).ok_or_else(|| invalid_data("invalid axis"))
+}
+
+fn modifiers_to_bits(m: &ModifiersState) -> u8 {
+    (m.ctrl as u8)
+        | (m.alt as u8) << 1
+        | (m.shift as u8) << 2
+        | (m.caps_lock as u8) << 3
+        | (m.logo as u8) << 4
+        | (m.num_lock as u8) << 5
+}
+
+fn modifiers_from_bits(bits: u8) -> ModifiersState {
+    ModifiersState {
+        ctrl: bits & 1 != 0,
+        alt: bits & 1 << 1 != 0,
+        shift: bits & 1 << 2 != 0,
+        caps_lock: bits & 1 << 3 != 0,
+        logo: bits & 1 << 4 != 0,
+        num_lock: bits & 1 << 5 != 0,
+    }
+}
+
+fn write_event<W: Write>(w: &mut W, event: &RecordedEvent) -> io::Result<()> {
+    match *event {
+        RecordedEvent::KeyboardEnter { surface, ref rawkeys, ref keysyms } => {
+            w.write_all(&[0])?;
+            write_u32(w, surface)?;
+            write_u32s(w, rawkeys)?;
+            write_u32s(w, keysyms)
+        }
+        RecordedEvent::KeyboardLeave { surface } => {
+            w.write_all(&[1])?;
+            write_u32(w, surface)
+        }
+        RecordedEvent::Modifiers { ref modifiers } => {
+            w.write_all(&[2, modifiers_to_bits(modifiers)])
+        }
+        RecordedEvent::Key { time, rawkey, keysym, state, ref utf8 } => {
+            w.write_all(&[3])?;
+            write_u32(w, time)?;
+            write_u32(w, rawkey)?;
+            write_u32(w, keysym)?;
+            write_u32(w, state.to_raw())?;
+            write_string(w, utf8)
+        }
+        RecordedEvent::Repeat { time, rawkey, keysym, ref utf8 } => {
+            w.write_all(&[4])?;
+            write_u32(w, time)?;
+            write_u32(w, rawkey)?;
+            write_u32(w, keysym)?;
+            write_string(w, utf8)
+        }
+        RecordedEvent::PointerEnter { surface, surface_x, surface_y } => {
+            w.write_all(&[5])?;
+            write_u32(w, surface)?;
+            write_f64(w, surface_x)?;
+            write_f64(w, surface_y)
+        }
+        RecordedEvent::PointerLeave { surface } => {
+            w.write_all(&[6])?;
+            write_u32(w, surface)
+        }
+        RecordedEvent::PointerMotion { time, surface_x, surface_y } => {
+            w.write_all(&[7])?;
+            write_u32(w, time)?;
+            write_f64(w, surface_x)?;
+            write_f64(w, surface_y)
+        }
+        RecordedEvent::PointerButton { time, button, state } => {
+            w.write_all(&[8])?;
+            write_u32(w, time)?;
+            write_u32(w, button)?;
+            write_u32(w, state.to_raw())
+        }
+        RecordedEvent::PointerAxis { time, axis, value } => {
+            w.write_all(&[9])?;
+            write_u32(w, time)?;
+            write_u32(w, axis.to_raw())?;
+            write_f64(w, value)
+        }
+        RecordedEvent::PointerAxisSource { axis_source } => {
+            w.write_all(&[10])?;
+            write_u32(w, axis_source.to_raw())
+        }
+        RecordedEvent::PointerAxisStop { time, axis } => {
+            w.write_all(&[11])?;
+            write_u32(w, time)?;
+            write_u32(w, axis.to_raw())
+        }
+        RecordedEvent::PointerAxisDiscrete { axis, discrete } => {
+            w.write_all(&[12])?;
+            write_u32(w, axis.to_raw())?;
+            write_u32(w, discrete as u32)
+        }
+        RecordedEvent::PointerFrame => w.write_all(&[13]),
+    }
+}
+
+fn read_event<R: Read>(r: &mut R) -> io::Result<RecordedEvent> {
+    let event = match read_u8(r)? {
+        0 => RecordedEvent::KeyboardEnter {
+            surface: read_u32(r)?,
+            rawkeys: read_u32s(r)?,
+            keysyms: read_u32s(r)?,
+        },
+        1 => RecordedEvent::KeyboardLeave { surface: read_u32(r)? },
+        2 => RecordedEvent::Modifiers { modifiers: modifiers_from_bits(read_u8(r)?) },
+        3 => RecordedEvent::Key {
+            time: read_u32(r)?,
+            rawkey: read_u32(r)?,
+            keysym: read_u32(r)?,
+            state: KeyState::from_raw(read_u32(r)?)
+                .ok_or_else(|| invalid_data("invalid key state"))?,
+            utf8: read_string(r)?,
+        },
+        4 => RecordedEvent::Repeat {
+            time: read_u32(r)?,
+            rawkey: read_u32(r)?,
+            keysym: read_u32(r)?,
+            utf8: read_string(r)?,
+        },
+        5 => RecordedEvent::PointerEnter {
+            surface: read_u32(r)?,
+            surface_x: read_f64(r)?,
+            surface_y: read_f64(r)?,
+        },
+        6 => RecordedEvent::PointerLeave { surface: read_u32(r)? },
+        7 => RecordedEvent::PointerMotion {
+            time: read_u32(r)?,
+            surface_x: read_f64(r)?,
+            surface_y: read_f64(r)?,
+        },
+        8 => RecordedEvent::PointerButton {
+            time: read_u32(r)?,
+            button: read_u32(r)?,
+            state: wl_pointer::ButtonState::from_raw(read_u32(r)?)
+                .ok_or_else(|| invalid_data("invalid button state"))?,
+        },
+        9 => RecordedEvent::PointerAxis {
+            time: read_u32(r)?,
+            axis: read_axis(r)?,
+            value: read_f64(r)?,
+        },
+        10 => RecordedEvent::PointerAxisSource {
+            axis_source: wl_pointer::AxisSource::from_raw(read_u32(r)?)
+                .ok_or_else(|| invalid_data("invalid axis source"))?,
+        },
+        11 => RecordedEvent::PointerAxisStop { time: read_u32(r)?, axis: read_axis(r)? },
+        12 => RecordedEvent::PointerAxisDiscrete {
+            axis: read_axis(r)?,
+            discrete: read_u32(r)? as i32,
+        },
+        13 => RecordedEvent::PointerFrame,
+        _ => return Err(invalid_data("unknown event kind")),
+    };
+    Ok(event)
+}
@@ -0,0 +1,615 @@
+//! Support for drawing tablets
+//!
+//! The `zwp_tablet_manager_v2` protocol reports the tablets of a seat, the tools used on them
+//! (like styluses and erasers, with their pressure and tilt), and their pads with buttons,
+//! rings and strips. A [`TabletSeat`](struct.TabletSeat.html) describes these devices as they
+//! are added, and forwards their events to a single callback.
+//!
+//! The manager global is not part of the
+//! [`default_environment!`](../../macro.default_environment.html), add a
+//! [`TabletState`](struct.TabletState.html) to your environment to bind it:
+//!
+//! ```no_run
+//! # use smithay_client_toolkit::{environment, seat::tablet::{TabletState, ZwpTabletManagerV2}};
+//! struct MyEnv {
+//!     tablet: TabletState,
+//! }
+//!
+//! environment!(MyEnv,
+//!     singles = [
+//!         ZwpTabletManagerV2 => tablet,
+//!     ],
+//!     multis = []
+//! );
+//! ```
+
+use std::{
+    cell::RefCell,
+    fmt,
+    rc::{Rc, Weak},
+};
+
+use wayland_client::{
+    protocol::{wl_registry, wl_seat, wl_surface},
+    Attached, DispatchData, Main,
+};
+
+pub use wayland_protocols::unstable::tablet::v2::client::{
+    zwp_tablet_manager_v2::ZwpTabletManagerV2,
+    zwp_tablet_pad_ring_v2::Source as RingSource,
+    zwp_tablet_pad_strip_v2::Source as StripSource,
+    zwp_tablet_tool_v2::{Capability as ToolCapability, Type as ToolType},
+};
+use wayland_protocols::unstable::tablet::v2::client::{
+    zwp_tablet_pad_group_v2::{self, ZwpTabletPadGroupV2},
+    zwp_tablet_pad_ring_v2::{self, ZwpTabletPadRingV2},
+    zwp_tablet_pad_strip_v2::{self, ZwpTabletPadStripV2},
+    zwp_tablet_pad_v2::{self, ZwpTabletPadV2},
+    zwp_tablet_seat_v2::{self, ZwpTabletSeatV2},
+    zwp_tablet_tool_v2::{self, ZwpTabletToolV2},
+    zwp_tablet_v2::{self, ZwpTabletV2},
+};
+
+use crate::environment::{Environment, GlobalHandler};
+use crate::MissingGlobal;
+
+/// A handler for the `zwp_tablet_manager_v2` global
+#[derive(Debug, Default)]
+pub struct TabletState {
+    manager: Option<Attached<ZwpTabletManagerV2>>,
+}
+
+impl TabletState {
+    /// Create a new handler
+    pub fn new() -> TabletState {
+        TabletState::default()
+    }
+}
+
+impl GlobalHandler<ZwpTabletManagerV2> for TabletState {
+    fn created(
+        &mut self,
+        registry: Attached<wl_registry::WlRegistry>,
+        id: u32,
+        _version: u32,
+        _: DispatchData,
+    ) {
+        let manager = registry.bind::<ZwpTabletManagerV2>(1, id);
+        self.manager = Some((*manager).clone());
+    }
+
+    fn get(&self) -> Option<Attached<ZwpTabletManagerV2>> {
+        self.manager.clone()
+    }
+}
+
+/// The description of a tablet
+#[derive(Debug, Clone, Default)]
+pub struct TabletInfo {
+    /// The tablet object, identifying it in the events of the tools and pads
+    pub tablet: Option<ZwpTabletV2>,
+    /// The name of the tablet
+    pub name: Option<String>,
+    /// The USB vendor and product ids of the tablet
+    pub id: Option<(u32, u32)>,
+    /// The system paths of the device files of the tablet, like `/dev/input/event3`
+    pub paths: Vec<String>,
+}
+
+/// The description of a tool, like a stylus or an eraser
+#[derive(Debug, Clone)]
+pub struct TabletTool {
+    /// The tool object, identifying it in the events and used to set its cursor
+    pub tool: ZwpTabletToolV2,
+    /// The type of the tool
+    pub tool_type: Option<ToolType>,
+    /// The serial number of the tool, to recognize it across tablets and sessions
+    pub hardware_serial: Option<u64>,
+    /// The Wacom tool id of the tool
+    pub hardware_id_wacom: Option<u64>,
+    /// The axes and buttons supported by the tool, in addition to its position
+    pub capabilities: Vec<ToolCapability>,
+}
+
+/// A group of the controls of a pad, sharing a mode
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PadGroup {
+    /// The buttons of the group
+    pub buttons: Vec<u32>,
+    /// The indices of the rings of the group, as given in the `Ring` events of the pad
+    pub rings: Vec<usize>,
+    /// The indices of the strips of the group, as given in the `Strip` events of the pad
+    pub strips: Vec<usize>,
+    /// The number of modes of the group, or 0 if it has no modes
+    pub modes: u32,
+}
+
+/// The description of a pad, the buttons and touch controls of a tablet
+#[derive(Debug, Clone)]
+pub struct TabletPad {
+    /// The pad object, identifying it in the events
+    pub pad: ZwpTabletPadV2,
+    /// The number of buttons of the pad
+    pub buttons: u32,
+    /// The system paths of the device files of the pad
+    pub paths: Vec<String>,
+    /// The groups of controls of the pad
+    pub groups: Vec<PadGroup>,
+}
+
+/// An event of a tablet tool
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToolEvent {
+    /// The tool came in proximity of a tablet, over a surface
+    ProximityIn {
+        /// serial of the event, to set the cursor of the tool
+        serial: u32,
+        /// the tablet the tool is over
+        tablet: ZwpTabletV2,
+        /// the surface the tool is over
+        surface: wl_surface::WlSurface,
+    },
+    /// The tool left the proximity of the tablet, or the surface
+    ProximityOut,
+    /// The tool touched the tablet
+    Down {
+        /// serial of the event
+        serial: u32,
+    },
+    /// The tool stopped touching the tablet
+    Up,
+    /// The tool moved, in surface-local coordinates
+    Motion {
+        /// horizontal position
+        x: f64,
+        /// vertical position
+        y: f64,
+    },
+    /// The pressure of the tool, from 0 to 1
+    Pressure(f64),
+    /// The distance of the tool from the tablet, from 0 to 1
+    Distance(f64),
+    /// The tilt of the tool, in degrees from the perpendicular to the tablet
+    Tilt {
+        /// tilt towards the right of the tablet
+        x: f64,
+        /// tilt towards the bottom of the tablet
+        y: f64,
+    },
+    /// The rotation of the tool around its axis, in degrees clockwise
+    Rotation(f64),
+    /// The position of the slider of the tool, from -1 to 1
+    Slider(f64),
+    /// The wheel of the tool was rotated
+    Wheel {
+        /// rotation in degrees
+        degrees: f64,
+        /// number of logical clicks
+        clicks: i32,
+    },
+    /// A button of the tool changed state
+    Button {
+        /// serial of the event
+        serial: u32,
+        /// the button code, as defined in `linux/input-event-codes.h`
+        button: u32,
+        /// whether the button is now pressed
+        pressed: bool,
+    },
+    /// The previous events form a single hardware event, which happened at this time
+    Frame {
+        /// timestamp in milliseconds
+        time: u32,
+    },
+}
+
+/// An event of a tablet pad
+#[derive(Debug, Clone, PartialEq)]
+pub enum PadEvent {
+    /// The pad got the focus of a surface
+    Enter {
+        /// serial of the event
+        serial: u32,
+        /// the tablet of the pad
+        tablet: ZwpTabletV2,
+        /// the focused surface
+        surface: wl_surface::WlSurface,
+    },
+    /// The pad lost the focus of a surface
+    Leave {
+        /// serial of the event
+        serial: u32,
+        /// the surface that was focused
+        surface: wl_surface::WlSurface,
+    },
+    /// A button of the pad changed state
+    Button {
+        /// timestamp in milliseconds
+        time: u32,
+        /// the index of the button
+        button: u32,
+        /// whether the button is now pressed
+        pressed: bool,
+    },
+    /// A ring of the pad was used
+    Ring {
+        /// the index of the ring
+        ring: usize,
+        /// the new angle of the ring in degrees clockwise from the top, `None` when the
+        /// interaction stopped
+        angle: Option<f64>,
+        /// what is interacting with the ring, if known
+        source: Option<RingSource>,
+        /// timestamp in milliseconds
+        time: u32,
+    },
+    /// A strip of the pad was used
+    Strip {
+        /// the index of the strip
+        strip: usize,
+        /// the new position on the strip, from 0 at the top or left to 1, `None` when the
+        /// interaction stopped
+        position: Option<f64>,
+        /// what is interacting with the strip, if known
+        source: Option<StripSource>,
+        /// timestamp in milliseconds
+        time: u32,
+    },
+    /// A group of the pad changed mode
+    ModeSwitch {
+        /// the index of the group in the description of the pad
+        group: usize,
+        /// timestamp in milliseconds
+        time: u32,
+        /// serial of the event
+        serial: u32,
+        /// the new mode
+        mode: u32,
+    },
+}
+
+/// An event of a tablet seat
+#[derive(Debug, Clone)]
+pub enum TabletEvent {
+    /// A tablet was added
+    TabletAdded(TabletInfo),
+    /// A tablet was removed
+    TabletRemoved(ZwpTabletV2),
+    /// A tool was used for the first time
+    ToolAdded(TabletTool),
+    /// A tool was removed, with its tablet
+    ToolRemoved(ZwpTabletToolV2),
+    /// An event of a tool
+    Tool {
+        /// the tool
+        tool: ZwpTabletToolV2,
+        /// the event
+        event: ToolEvent,
+    },
+    /// A pad was added
+    PadAdded(TabletPad),
+    /// A pad was removed
+    PadRemoved(ZwpTabletPadV2),
+    /// An event of a pad
+    Pad {
+        /// the pad
+        pad: ZwpTabletPadV2,
+        /// the event
+        event: PadEvent,
+    },
+}
+
+type TabletCallback = dyn FnMut(TabletEvent, DispatchData);
+
+/// The tablets of a seat
+///
+/// The callback it was created with is notified of the tablets, tools and pads of the seat,
+/// and of their events. The tools are only announced when first used. The tablet seat is
+/// destroyed when this is dropped, after which the callback is no longer invoked.
+pub struct TabletSeat {
+    tablet_seat: ZwpTabletSeatV2,
+    _callback: Rc<RefCell<TabletCallback>>,
+}
+
+impl TabletSeat {
+    /// Start tracking the tablets of a seat
+    pub fn new<F>(
+        manager: &Attached<ZwpTabletManagerV2>,
+        seat: &wl_seat::WlSeat,
+        callback: F,
+    ) -> TabletSeat
+    where
+        F: FnMut(TabletEvent, DispatchData) + 'static,
+    {
+        let callback = Rc::new(RefCell::new(callback)) as Rc<RefCell<TabletCallback>>;
+        let my_callback = Rc::downgrade(&callback);
+        let tablet_seat = manager.get_tablet_seat(seat);
+        tablet_seat.quick_assign(move |_, event, _| match event {
+            zwp_tablet_seat_v2::Event::TabletAdded { id } => assign_tablet(id, my_callback.clone()),
+            zwp_tablet_seat_v2::Event::ToolAdded { id } => assign_tool(id, my_callback.clone()),
+            zwp_tablet_seat_v2::Event::PadAdded { id } => assign_pad(id, my_callback.clone()),
+            _ => unreachable!(),
+        });
+        TabletSeat { tablet_seat: tablet_seat.detach(), _callback: callback }
+    }
+
+    /// The underlying tablet seat object
+    pub fn tablet_seat(&self) -> &ZwpTabletSeatV2 {
+        &self.tablet_seat
+    }
+}
+
+impl Drop for TabletSeat {
+    fn drop(&mut self) {
+        self.tablet_seat.destroy();
+    }
+}
+
+impl fmt::Debug for TabletSeat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TabletSeat")
+            .field("tablet_seat", &self.tablet_seat)
+            .field("callback", &"Fn(..) -> { ... }")
+            .finish()
+    }
+}
+
+fn send(callback: &Weak<RefCell<TabletCallback>>, event: TabletEvent, ddata: DispatchData) {
+    if let Some(callback) = callback.upgrade() {
+        (*callback.borrow_mut())(event, ddata);
+    }
+}
+
+fn assign_tablet(tablet: Main<ZwpTabletV2>, callback: Weak<RefCell<TabletCallback>>) {
+    let mut info = TabletInfo::default();
+    tablet.quick_assign(move |tablet, event, ddata| match event {
+        zwp_tablet_v2::Event::Name { name } => info.name = Some(name),
+        zwp_tablet_v2::Event::Id { vid, pid } => info.id = Some((vid, pid)),
+        zwp_tablet_v2::Event::Path { path } => info.paths.push(path),
+        zwp_tablet_v2::Event::Done => {
+            info.tablet = Some(tablet.detach());
+            send(&callback, TabletEvent::TabletAdded(info.clone()), ddata);
+        }
+        zwp_tablet_v2::Event::Removed => {
+            send(&callback, TabletEvent::TabletRemoved(tablet.detach()), ddata);
+            tablet.destroy();
+        }
+        _ => unreachable!(),
+    });
+}
+
+fn assign_tool(tool: Main<ZwpTabletToolV2>, callback: Weak<RefCell<TabletCallback>>) {
+    let mut info = TabletTool {
+        tool: tool.detach(),
+        tool_type: None,
+        hardware_serial: None,
+        hardware_id_wacom: None,
+        capabilities: Vec::new(),
+    };
+    tool.quick_assign(move |tool, event, ddata| {
+        use zwp_tablet_tool_v2::Event;
+        let event = match event {
+            Event::Type { tool_type } => {
+                info.tool_type = Some(tool_type);
+                return;
+            }
+            Event::HardwareSerial { hardware_serial_hi, hardware_serial_lo } => {
+                let serial = (u64::from(hardware_serial_hi) << 32) | u64::from(hardware_serial_lo);
+                info.hardware_serial = Some(serial);
+                return;
+            }
+            Event::HardwareIdWacom { hardware_id_hi, hardware_id_lo } => {
+                let id = (u64::from(hardware_id_hi) << 32) | u64::from(hardware_id_lo);
+                info.hardware_id_wacom = Some(id);
+                return;
+            }
+            Event::Capability { capability } => {
+                info.capabilities.push(capability);
+                return;
+            }
+            Event::Done => {
+                send(&callback, TabletEvent::ToolAdded(info.clone()), ddata);
+                return;
+            }
+            Event::Removed => {
+                send(&callback, TabletEvent::ToolRemoved(tool.detach()), ddata);
+                tool.destroy();
+                return;
+            }
+            Event::ProximityIn { serial, tablet, surface } => {
+                ToolEvent::ProximityIn { serial, tablet, surface }
+            }
+            Event::ProximityOut => ToolEvent::ProximityOut,
+            Event::Down { serial } => ToolEvent::Down { serial },
+            Event::Up => ToolEvent::Up,
+            Event::Motion { x, y } => ToolEvent::Motion { x, y },
+            Event::Pressure { pressure } => ToolEvent::Pressure(f64::from(pressure) / 65535.0),
+            Event::Distance { distance } => ToolEvent::Distance(f64::from(distance) / 65535.0),
+            Event::Tilt { tilt_x, tilt_y } => ToolEvent::Tilt { x: tilt_x, y: tilt_y },
+            Event::Rotation { degrees } => ToolEvent::Rotation(degrees),
+            Event::Slider { position } => ToolEvent::Slider(f64::from(position) / 65535.0),
+            Event::Wheel { degrees, clicks } => ToolEvent::Wheel { degrees, clicks },
+            Event::Button { serial, button, state } => ToolEvent::Button {
+                serial,
+                button,
+                pressed: state == zwp_tablet_tool_v2::ButtonState::Pressed,
+            },
+            Event::Frame { time } => ToolEvent::Frame { time },
+            _ => unreachable!(),
+        };
+        send(&callback, TabletEvent::Tool { tool: tool.detach(), event }, ddata);
+    });
+}
+
+#[derive(Default)]
+struct PadInner {
+    buttons: u32,
+    paths: Vec<String>,
+    groups: Vec<Rc<RefCell<PadGroup>>>,
+    rings: usize,
+    strips: usize,
+}
+
+fn assign_pad(pad: Main<ZwpTabletPadV2>, callback: Weak<RefCell<TabletCallback>>) {
+    let inner = Rc::new(RefCell::new(PadInner::default()));
+    pad.quick_assign(move |pad, event, ddata| {
+        use zwp_tablet_pad_v2::Event;
+        let event = match event {
+            Event::Group { pad_group } => {
+                let group = Rc::new(RefCell::new(PadGroup::default()));
+                let index = {
+                    let mut inner = inner.borrow_mut();
+                    inner.groups.push(group.clone());
+                    inner.groups.len() - 1
+                };
+                assign_group(pad_group, pad.detach(), index, group, &inner, callback.clone());
+                return;
+            }
+            Event::Path { path } => {
+                inner.borrow_mut().paths.push(path);
+                return;
+            }
+            Event::Buttons { buttons } => {
+                inner.borrow_mut().buttons = buttons;
+                return;
+            }
+            Event::Done => {
+                let description = {
+                    let inner = inner.borrow();
+                    TabletPad {
+                        pad: pad.detach(),
+                        buttons: inner.buttons,
+                        paths: inner.paths.clone(),
+                        groups: inner.groups.iter().map(|g| g.borrow().clone()).collect(),
+                    }
+                };
+                send(&callback, TabletEvent::PadAdded(description), ddata);
+                return;
+            }
+            Event::Removed => {
+                send(&callback, TabletEvent::PadRemoved(pad.detach()), ddata);
+                pad.destroy();
+                return;
+            }
+            Event::Enter { serial, tablet, surface } => PadEvent::Enter { serial, tablet, surface },
+            Event::Leave { serial, surface } => PadEvent::Leave { serial, surface },
+            Event::Button { time, button, state } => PadEvent::Button {
+                time,
+                button,
+                pressed: state == zwp_tablet_pad_v2::ButtonState::Pressed,
+            },
+            _ => unreachable!(),
+        };
+        send(&callback, TabletEvent::Pad { pad: pad.detach(), event }, ddata);
+    });
+}
+
+fn assign_group(
+    group: Main<ZwpTabletPadGroupV2>,
+    pad: ZwpTabletPadV2,
+    index: usize,
+    description: Rc<RefCell<PadGroup>>,
+    pad_inner: &Rc<RefCell<PadInner>>,
+    callback: Weak<RefCell<TabletCallback>>,
+) {
+    let pad_inner = pad_inner.clone();
+    group.quick_assign(move |_, event, ddata| match event {
+        zwp_tablet_pad_group_v2::Event::Buttons { buttons } => {
+            description.borrow_mut().buttons = buttons
+                .chunks_exact(4)
+                .map(|b| u32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+                .collect();
+        }
+        zwp_tablet_pad_group_v2::Event::Ring { ring } => {
+            let index = {
+                let mut pad_inner = pad_inner.borrow_mut();
+                pad_inner.rings += 1;
+                pad_inner.rings - 1
+            };
+            description.borrow_mut().rings.push(index);
+            assign_ring(ring, pad.clone(), index, callback.clone());
+        }
+        zwp_tablet_pad_group_v2::Event::Strip { strip } => {
+            let index = {
+                let mut pad_inner = pad_inner.borrow_mut();
+                pad_inner.strips += 1;
+                pad_inner.strips - 1
+            };
+            description.borrow_mut().strips.push(index);
+            assign_strip(strip, pad.clone(), index, callback.clone());
+        }
+        zwp_tablet_pad_group_v2::Event::Modes { modes } => description.borrow_mut().modes = modes,
+        zwp_tablet_pad_group_v2::Event::Done => {}
+        zwp_tablet_pad_group_v2::Event::ModeSwitch { time, serial, mode } => {
+            let event = PadEvent::ModeSwitch { group: index, time, serial, mode };
+            send(&callback, TabletEvent::Pad { pad: pad.clone(), event }, ddata);
+        }
+        _ => unreachable!(),
+    });
+}
+
+fn assign_ring(
+    ring: Main<ZwpTabletPadRingV2>,
+    pad: ZwpTabletPadV2,
+    index: usize,
+    callback: Weak<RefCell<TabletCallback>>,
+) {
+    // the state accumulated until the next frame
+    let mut angle = None;
+    let mut source = None;
+    ring.quick_assign(move |_, event, ddata| match event {
+        zwp_tablet_pad_ring_v2::Event::Source { source: s } => source = Some(s),
+        zwp_tablet_pad_ring_v2::Event::Angle { degrees } => angle = Some(Some(degrees)),
+        zwp_tablet_pad_ring_v2::Event::Stop => angle = Some(None),
+        zwp_tablet_pad_ring_v2::Event::Frame { time } => {
+            if let Some(angle) = angle.take() {
+                let event = PadEvent::Ring { ring: index, angle, source: source.take(), time };
+                send(&callback, TabletEvent::Pad { pad: pad.clone(), event }, ddata);
+            }
+        }
+        _ => unreachable!(),
+    });
+}
+
+fn assign_strip(
+    strip: Main<ZwpTabletPadStripV2>,
+    pad: ZwpTabletPadV2,
+    index: usize,
+    callback: Weak<RefCell<TabletCallback>>,
+) {
+    // the state accumulated until the next frame
+    let mut position = None;
+    let mut source = None;
+    strip.quick_assign(move |_, event, ddata| match event {
+        zwp_tablet_pad_strip_v2::Event::Source { source: s } => source = Some(s),
+        zwp_tablet_pad_strip_v2::Event::Position { position: p } => {
+            position = Some(Some(f64::from(p) / 65535.0))
+        }
+        zwp_tablet_pad_strip_v2::Event::Stop => position = Some(None),
+        zwp_tablet_pad_strip_v2::Event::Frame { time } => {
+            if let Some(position) = position.take() {
+                let event = PadEvent::Strip { strip: index, position, source: source.take(), time };
+                send(&callback, TabletEvent::Pad { pad: pad.clone(), event }, ddata);
+            }
+        }
+        _ => unreachable!(),
+    });
+}
+
+impl<E: GlobalHandler<ZwpTabletManagerV2>> Environment<E> {
+    /// Start tracking the tablets of a seat
+    ///
+    /// See [`TabletSeat`](../seat/tablet/struct.TabletSeat.html). Returns an error if the
+    /// compositor does not support `zwp_tablet_manager_v2`.
+    pub fn get_tablet_seat<F>(
+        &self,
+        seat: &wl_seat::WlSeat,
+        callback: F,
+    ) -> Result<TabletSeat, MissingGlobal>
+    where
+        F: FnMut(TabletEvent, DispatchData) + 'static,
+    {
+        let manager = self.get_global::<ZwpTabletManagerV2>().ok_or(MissingGlobal)?;
+        Ok(TabletSeat::new(&manager, seat, callback))
+    }
+}
@@ -0,0 +1,357 @@
+//! Support for input methods with the text input protocol
+//!
+//! The `zwp_text_input_manager_v3` protocol lets applications receive text from input
+//! methods, like the ones used to type CJK languages or on-screen keyboards. Each seat gets a
+//! [`TextInput`](struct.TextInput.html), which is enabled while a text field of the focused
+//! surface is active, and whose callback receives the text composed by the input method.
+//!
+//! The manager global is not part of the
+//! [`default_environment!`](../../macro.default_environment.html), add a
+//! [`TextInputState`](struct.TextInputState.html) to your environment to bind it:
+//!
+//! ```no_run
+//! # use smithay_client_toolkit::{environment, seat::text_input::{TextInputState, ZwpTextInputManagerV3}};
+//! struct MyEnv {
+//!     text_input: TextInputState,
+//! }
+//!
+//! environment!(MyEnv,
+//!     singles = [
+//!         ZwpTextInputManagerV3 => text_input,
+//!     ],
+//!     multis = []
+//! );
+//! ```
+
+use std::{cell::RefCell, rc::Rc};
+
+use wayland_client::{
+    protocol::{wl_registry, wl_seat, wl_surface},
+    Attached, DispatchData,
+};
+
+use wayland_protocols::unstable::text_input::v3::client::zwp_text_input_v3::{
+    self, ZwpTextInputV3,
+};
+pub use wayland_protocols::unstable::text_input::v3::client::{
+    zwp_text_input_manager_v3::ZwpTextInputManagerV3,
+    zwp_text_input_v3::{ChangeCause, ContentHint, ContentPurpose},
+};
+
+use crate::environment::{Environment, GlobalHandler};
+use crate::{DamageRect, MissingGlobal};
+
+/// A handler for the `zwp_text_input_manager_v3` global
+#[derive(Debug, Default)]
+pub struct TextInputState {
+    manager: Option<Attached<ZwpTextInputManagerV3>>,
+}
+
+impl TextInputState {
+    /// Create a new handler
+    pub fn new() -> TextInputState {
+        TextInputState::default()
+    }
+}
+
+impl GlobalHandler<ZwpTextInputManagerV3> for TextInputState {
+    fn created(
+        &mut self,
+        registry: Attached<wl_registry::WlRegistry>,
+        id: u32,
+        _version: u32,
+        _: DispatchData,
+    ) {
+        let manager = registry.bind::<ZwpTextInputManagerV3>(1, id);
+        self.manager = Some((*manager).clone());
+    }
+
+    fn get(&self) -> Option<Attached<ZwpTextInputManagerV3>> {
+        self.manager.clone()
+    }
+}
+
+/// The text being composed by an input method
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Preedit {
+    /// The text, to be displayed at the cursor position in place of the selected text
+    pub text: String,
+    /// The cursor within the text, as a range of byte offsets
+    ///
+    /// This is `None` if the cursor should be hidden. An empty range is a plain cursor.
+    pub cursor: Option<(usize, usize)>,
+}
+
+/// An update of the text, sent by the input method
+///
+/// The changes must be applied in the order of the fields: first replace the previous
+/// pre-edit text by the cursor, then delete the surrounding text, insert the committed text,
+/// and finally insert the new pre-edit text at the cursor.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TextInputUpdate {
+    /// The number of bytes to delete before and after the cursor
+    ///
+    /// The deletion also covers the current selection.
+    pub delete_surrounding_text: Option<(u32, u32)>,
+    /// The text to insert at the cursor
+    pub commit_string: Option<String>,
+    /// The new pre-edit text, `None` if there is no more text being composed
+    pub preedit: Option<Preedit>,
+    /// Whether the input method took the last state of the text input into account
+    ///
+    /// If not, the update was computed from an older state, and the application may need to
+    /// send its current state again.
+    pub is_current: bool,
+}
+
+/// An event of a text input
+#[derive(Debug, Clone, PartialEq)]
+pub enum TextInputEvent {
+    /// The text input gained the focus of a surface
+    ///
+    /// It should be enabled if the surface has an active text field.
+    Enter(wl_surface::WlSurface),
+    /// The text input lost the focus of a surface
+    ///
+    /// It is implicitly disabled, and the pre-edit text should be removed.
+    Leave(wl_surface::WlSurface),
+    /// The input method updated the text
+    Update(TextInputUpdate),
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    focus: Option<wl_surface::WlSurface>,
+    // the number of commit requests, which the serials of the done events are compared to
+    commits: u32,
+    // the update accumulated until the next done event
+    pending: TextInputUpdate,
+}
+
+/// The text input of a seat
+///
+/// Enable it with [`enable`](#method.enable) when a text field is focused, and describe the
+/// text field with the other requests, like [`set_surrounding_text`](#method.set_surrounding_text).
+/// All of these are double-buffered, and only applied by [`commit`](#method.commit).
+///
+/// The text input is destroyed when this is dropped.
+#[derive(Debug)]
+pub struct TextInput {
+    text_input: ZwpTextInputV3,
+    inner: Rc<RefCell<Inner>>,
+}
+
+impl TextInput {
+    /// Create the text input of a seat
+    pub fn new<F>(
+        manager: &Attached<ZwpTextInputManagerV3>,
+        seat: &wl_seat::WlSeat,
+        mut callback: F,
+    ) -> TextInput
+    where
+        F: FnMut(TextInputEvent, DispatchData) + 'static,
+    {
+        let inner = Rc::new(RefCell::new(Inner::default()));
+        let my_inner = Rc::downgrade(&inner);
+        let text_input = manager.get_text_input(seat);
+        text_input.quick_assign(move |_, event, ddata| {
+            let inner = match my_inner.upgrade() {
+                Some(inner) => inner,
+                None => return,
+            };
+            let event = {
+                let mut inner = inner.borrow_mut();
+                match event {
+                    zwp_text_input_v3::Event::Enter { surface } => {
+                        inner.focus = Some(surface.clone());
+                        TextInputEvent::Enter(surface)
+                    }
+                    zwp_text_input_v3::Event::Leave { surface } => {
+                        inner.focus = None;
+                        TextInputEvent::Leave(surface)
+                    }
+                    zwp_text_input_v3::Event::PreeditString { text, cursor_begin, cursor_end } => {
+                        inner.pending.preedit = text.map(|text| Preedit {
+                            text,
+                            cursor: if cursor_begin < 0 || cursor_end < 0 {
+                                None
+                            } else {
+                                Some((cursor_begin as usize, cursor_end as usize))
+                            },
+                        });
+                        return;
+                    }
+                    zwp_text_input_v3::Event::CommitString { text } => {
+                        inner.pending.commit_string = text;
+                        return;
+                    }
+                    zwp_text_input_v3::Event::DeleteSurroundingText {
+                        before_length,
+                        after_length,
+                    } => {
+                        inner.pending.delete_surrounding_text = Some((before_length, after_length));
+                        return;
+                    }
+                    zwp_text_input_v3::Event::Done { serial } => {
+                        let mut update = std::mem::take(&mut inner.pending);
+                        update.is_current = serial == inner.commits;
+                        TextInputEvent::Update(update)
+                    }
+                    _ => unreachable!(),
+                }
+            };
+            callback(event, ddata);
+        });
+        TextInput { text_input: text_input.detach(), inner }
+    }
+
+    /// The underlying text input object
+    pub fn text_input(&self) -> &ZwpTextInputV3 {
+        &self.text_input
+    }
+
+    /// The surface focused by this text input, if any
+    pub fn focus(&self) -> Option<wl_surface::WlSurface> {
+        self.inner.borrow().focus.clone()
+    }
+
+    /// Enable the text input for the focused surface
+    ///
+    /// This resets the state of the text input, the other requests describing the text
+    /// field should follow, before the commit.
+    pub fn enable(&self) {
+        self.text_input.enable();
+    }
+
+    /// Disable the text input, when no text field is active anymore
+    pub fn disable(&self) {
+        self.text_input.disable();
+    }
+
+    /// Describe the text around the cursor
+    ///
+    /// `cursor` and `anchor` are byte offsets in `text`, the selection being between them.
+    /// The text should be limited to a few sentences around the cursor. Longer text is
+    /// truncated to the 4000 bytes around the selection, as recommended by the protocol, on
+    /// character boundaries, with `cursor` and `anchor` adjusted accordingly. If the
+    /// selection itself is longer, the text around the cursor is kept and the anchor is moved
+    /// to the end of it.
+    pub fn set_surrounding_text(&self, text: String, cursor: i32, anchor: i32) {
+        let (text, cursor, anchor) = truncate_surrounding_text(text, cursor, anchor);
+        self.text_input.set_surrounding_text(text, cursor, anchor);
+    }
+
+    /// Signal what caused the last change of the surrounding text
+    pub fn set_text_change_cause(&self, cause: ChangeCause) {
+        self.text_input.set_text_change_cause(cause);
+    }
+
+    /// Describe the content of the text field
+    pub fn set_content_type(&self, hint: ContentHint, purpose: ContentPurpose) {
+        self.text_input.set_content_type(hint, purpose);
+    }
+
+    /// Set the rectangle of the cursor, in surface-local coordinates
+    ///
+    /// The input method uses it to place its popups next to the text being composed.
+    pub fn set_cursor_rectangle(&self, rect: DamageRect) {
+        let (x, y, width, height) = rect;
+        self.text_input.set_cursor_rectangle(x, y, width, height);
+    }
+
+    /// Apply the pending state of the text input
+    pub fn commit(&self) {
+        let mut inner = self.inner.borrow_mut();
+        inner.commits = inner.commits.wrapping_add(1);
+        self.text_input.commit();
+    }
+}
+
+// the maximum size of the surrounding text recommended by the protocol
+const MAX_SURROUNDING_TEXT: usize = 4000;
+
+fn truncate_surrounding_text(text: String, cursor: i32, anchor: i32) -> (String, i32, i32) {
+    if text.len() <= MAX_SURROUNDING_TEXT {
+        return (text, cursor, anchor);
+    }
+    let clamp = |offset: i32| (offset.max(0) as usize).min(text.len());
+    let (cursor, anchor) = (clamp(cursor), clamp(anchor));
+    let (low, high) = (cursor.min(anchor), cursor.max(anchor));
+    // center the kept text on the selection, or on the cursor if the selection is too long
+    let start = if high - low <= MAX_SURROUNDING_TEXT {
+        low.saturating_sub((MAX_SURROUNDING_TEXT - (high - low)) / 2)
+    } else {
+        cursor.saturating_sub(MAX_SURROUNDING_TEXT / 2)
+    };
+    let mut start = start.min(text.len() - MAX_SURROUNDING_TEXT);
+    let mut end = start + MAX_SURROUNDING_TEXT;
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let relative = |offset: usize| (offset.max(start).min(end) - start) as i32;
+    (text[start..end].to_owned(), relative(cursor), relative(anchor))
+}
+
+impl Drop for TextInput {
+    fn drop(&mut self) {
+        self.text_input.destroy();
+    }
+}
+
+impl<E: GlobalHandler<ZwpTextInputManagerV3>> Environment<E> {
+    /// Create the text input of a seat
+    ///
+    /// See [`TextInput`](../seat/text_input/struct.TextInput.html). Returns an error if the
+    /// compositor does not support `zwp_text_input_manager_v3`.
+    pub fn get_text_input<F>(
+        &self,
+        seat: &wl_seat::WlSeat,
+        callback: F,
+    ) -> Result<TextInput, MissingGlobal>
+    where
+        F: FnMut(TextInputEvent, DispatchData) + 'static,
+    {
+        let manager = self.get_global::<ZwpTextInputManagerV3>().ok_or(MissingGlobal)?;
+        Ok(TextInput::new(&manager, seat, callback))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncate_keeps_short_text() {
+        let text = "héllo".to_owned();
+        assert_eq!(truncate_surrounding_text(text.clone(), 3, 1), (text, 3, 1));
+    }
+
+    #[test]
+    fn truncate_centers_on_the_selection() {
+        let text = "a".repeat(10_000);
+        let (kept, cursor, anchor) = truncate_surrounding_text(text, 5000, 5100);
+        assert_eq!(kept.len(), MAX_SURROUNDING_TEXT);
+        assert_eq!((cursor, anchor), (1950, 2050));
+    }
+
+    #[test]
+    fn truncate_long_selection_keeps_the_cursor() {
+        let text = "a".repeat(10_000);
+        let (kept, cursor, anchor) = truncate_surrounding_text(text, 9000, 0);
+        assert_eq!(kept.len(), MAX_SURROUNDING_TEXT);
+        assert_eq!((cursor, anchor), (3000, 0));
+    }
+
+    #[test]
+    fn truncate_on_char_boundaries() {
+        // two bytes per char, with the cursor in the middle of one
+        let text = "é".repeat(3000);
+        let (kept, cursor, anchor) = truncate_surrounding_text(text, 3001, 3001);
+        assert_eq!(kept.len(), 3998);
+        assert!(kept.chars().all(|c| c == 'é'));
+        assert_eq!((cursor, anchor), (1999, 1999));
+    }
+}
@@ -0,0 +1,130 @@
+//! Normalization of the timestamps of input events
+//!
+//! The events of input devices carry a timestamp in milliseconds, whose base is unspecified
+//! and which wraps around every 49 days. The [`TimestampNormalizer`](struct.TimestampNormalizer.html)
+//! converts them to a `Duration` since the first event it saw, giving animation and gesture
+//! code a consistent timebase, and estimates the `CLOCK_MONOTONIC` time of the events.
+
+use std::time::Duration;
+
+use nix::time::{clock_gettime, ClockId};
+
+/// A converter of input event timestamps into a monotonic timebase
+///
+/// Give the timestamps of the events of all your input devices to
+/// [`normalize`](#method.normalize), which returns the time elapsed since the first event it
+/// processed, accounting for the wraparound of the timestamps. Use a single normalizer for
+/// the whole connection, so that the times of all the devices can be compared.
+///
+/// Most compositors take the timestamps from `CLOCK_MONOTONIC`, the normalizer estimates the
+/// offset between the two clocks from the time the events are processed, so that
+/// [`to_monotonic`](#method.to_monotonic) can correlate the events with other clocks. If the
+/// compositor supports `zwp_input_timestamps_v1`, give the high-resolution timestamps it sends
+/// to [`set_high_resolution`](#method.set_high_resolution) for an exact correlation.
+#[derive(Debug, Default)]
+pub struct TimestampNormalizer {
+    // the last timestamp and its extended value, in milliseconds since the first event
+    last: Option<(u32, i64)>,
+    // CLOCK_MONOTONIC minus the extended timestamps, in milliseconds
+    offset: Option<i64>,
+    // whether the offset comes from high-resolution timestamps
+    precise: bool,
+}
+
+impl TimestampNormalizer {
+    /// Create a new normalizer
+    pub fn new() -> TimestampNormalizer {
+        TimestampNormalizer::default()
+    }
+
+    /// Convert the timestamp of an event to the time elapsed since the first event
+    ///
+    /// The events of different devices may be slightly out of order, a timestamp older than
+    /// the first one gives a zero duration.
+    pub fn normalize(&mut self, time: u32) -> Duration {
+        let extended = self.extend(time);
+        if !self.precise {
+            if let Some(now) = monotonic_millis() {
+                // events are processed after they happened, the smallest difference is the
+                // closest to the actual offset
+                let offset = now - extended;
+                self.offset = Some(self.offset.map(|o| o.min(offset)).unwrap_or(offset));
+            }
+        }
+        Duration::from_millis(extended.max(0) as u64)
+    }
+
+    /// Record the high-resolution timestamp of an event
+    ///
+    /// These are sent by the `zwp_input_timestamps_v1` objects in their `timestamp` event,
+    /// right before the event with the given `time` they apply to, and are taken from
+    /// `CLOCK_MONOTONIC`.
+    pub fn set_high_resolution(&mut self, time: u32, tv_sec_hi: u32, tv_sec_lo: u32, tv_nsec: u32) {
+        let secs = (u64::from(tv_sec_hi) << 32) | u64::from(tv_sec_lo);
+        let millis = (secs * 1000 + u64::from(tv_nsec) / 1_000_000) as i64;
+        let extended = self.extend(time);
+        self.offset = Some(millis - extended);
+        self.precise = true;
+    }
+
+    /// Estimate the `CLOCK_MONOTONIC` time of an event
+    ///
+    /// `time` is a duration returned by [`normalize`](#method.normalize). Returns `None` if
+    /// no event was processed yet. Without high-resolution timestamps, the result is only
+    /// meaningful if the compositor takes the timestamps from `CLOCK_MONOTONIC`.
+    pub fn to_monotonic(&self, time: Duration) -> Option<Duration> {
+        let monotonic = time.as_millis() as i64 + self.offset?;
+        if monotonic < 0 {
+            return None;
+        }
+        Some(Duration::from_millis(monotonic as u64))
+    }
+
+    /// Whether the correlation with `CLOCK_MONOTONIC` comes from high-resolution timestamps
+    ///
+    /// If not, it is an estimation, accurate to the latency of the event delivery.
+    pub fn is_precise(&self) -> bool {
+        self.precise
+    }
+
+    // Extend a timestamp to the milliseconds since the first event
+    fn extend(&mut self, time: u32) -> i64 {
+        let extended = match self.last {
+            // the difference is signed, to handle both the wraparound and small reorderings
+            Some((last, last_extended)) => {
+                last_extended + i64::from(time.wrapping_sub(last) as i32)
+            }
+            None => 0,
+        };
+        self.last = Some((time, extended));
+        extended
+    }
+}
+
+fn monotonic_millis() -> Option<i64> {
+    let now = clock_gettime(ClockId::CLOCK_MONOTONIC).ok()?;
+    Some(now.tv_sec() as i64 * 1000 + now.tv_nsec() as i64 / 1_000_000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extend_handles_the_wraparound() {
+        let mut normalizer = TimestampNormalizer::new();
+        assert_eq!(normalizer.extend(std::u32::MAX - 10), 0);
+        assert_eq!(normalizer.extend(5), 16);
+        // slightly reordered events go back in time
+        assert_eq!(normalizer.extend(3), 14);
+        assert_eq!(normalizer.extend(std::u32::MAX), 10);
+    }
+
+    #[test]
+    fn normalize_clamps_older_events() {
+        let mut normalizer = TimestampNormalizer::new();
+        assert_eq!(normalizer.normalize(100), Duration::from_millis(0));
+        assert_eq!(normalizer.normalize(90), Duration::from_millis(0));
+        assert_eq!(normalizer.normalize(150), Duration::from_millis(50));
+    }
+}
@@ -0,0 +1,165 @@
+//! Utilities to work with touchscreens
+//!
+//! The entry point of this module is the [`map_touch`](fn.map_touch.html) function which,
+//! given a `wl_seat` and a callback, sets up the `wl_touch` of this seat. The events of
+//! `wl_touch` only identify the touch points by their id, this module tracks the state of
+//! each touch point so that all events describe the point they concern completely.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use wayland_client::{
+    protocol::{wl_seat, wl_surface, wl_touch},
+    Attached, DispatchData,
+};
+
+/// A point of contact with a touchscreen
+#[derive(Debug, Clone)]
+pub struct TouchPoint {
+    /// Id of the touch point
+    ///
+    /// It is unique among the points currently in contact with the touchscreen, but may be
+    /// reused once the point is lifted.
+    pub id: i32,
+    /// Surface the touch point went down on
+    ///
+    /// A touch point stays associated to this surface until it is lifted, even if it moves
+    /// outside of it.
+    pub surface: wl_surface::WlSurface,
+    /// Position of the touch point, in surface coordinates
+    pub position: (f64, f64),
+    /// Length of the major and minor axes of the ellipse of contact, in surface units
+    ///
+    /// Only known if the touchscreen reports it.
+    pub shape: Option<(f64, f64)>,
+    /// Angle between the major axis of the ellipse of contact and the vertical axis, in degrees
+    ///
+    /// Only known if the touchscreen reports it.
+    pub orientation: Option<f64>,
+}
+
+/// Events received from a mapped touchscreen
+#[derive(Debug)]
+pub enum Event {
+    /// A new touch point went down
+    Down {
+        /// serial number of the event
+        serial: u32,
+        /// time of the event
+        time: u32,
+        /// the new touch point
+        point: TouchPoint,
+    },
+    /// A touch point was lifted
+    Up {
+        /// serial number of the event
+        serial: u32,
+        /// time of the event
+        time: u32,
+        /// the touch point, as it was last known
+        point: TouchPoint,
+    },
+    /// A touch point moved
+    Motion {
+        /// time of the event
+        time: u32,
+        /// the touch point, with its new position
+        point: TouchPoint,
+    },
+    /// The shape of the ellipse of contact of a touch point changed
+    Shape {
+        /// the touch point, with its new shape
+        point: TouchPoint,
+    },
+    /// The orientation of the ellipse of contact of a touch point changed
+    Orientation {
+        /// the touch point, with its new orientation
+        point: TouchPoint,
+    },
+    /// The end of a set of events that logically belong together
+    ///
+    /// For example the motion of several touch points is sent as several `Motion` events
+    /// followed by a `Frame`.
+    Frame,
+    /// The compositor took over the touch sequence
+    ///
+    /// This happens for example when it recognized a gesture. All the touch points are
+    /// cancelled, and no further events will be sent for them.
+    Cancel {
+        /// the touch points that were in contact with the touchscreen
+        points: Vec<TouchPoint>,
+    },
+}
+
+/// The seat given to [`map_touch`](fn.map_touch.html) does not have the touch capability
+#[derive(Debug, Copy, Clone)]
+pub struct NoTouch;
+
+impl std::error::Error for NoTouch {}
+
+impl fmt::Display for NoTouch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("the seat does not have the touch capability")
+    }
+}
+
+/// Implement a touchscreen, tracking its touch points
+///
+/// This requires you to provide a callback to receive the events of the touchscreen.
+///
+/// Returns an error if the provided seat does not have the touch capability.
+pub fn map_touch<F>(
+    seat: &Attached<wl_seat::WlSeat>,
+    mut callback: F,
+) -> Result<wl_touch::WlTouch, NoTouch>
+where
+    F: FnMut(Event, wl_touch::WlTouch, DispatchData) + 'static,
+{
+    let has_touch = super::with_seat_data(seat, |data| data.has_touch).unwrap_or(false);
+    if !has_touch {
+        return Err(NoTouch);
+    }
+
+    let touch = seat.get_touch();
+    let mut points: HashMap<i32, TouchPoint> = HashMap::new();
+    touch.quick_assign(move |touch, event, ddata| {
+        if let Some(event) = track_event(&mut points, event) {
+            callback(event, touch.detach(), ddata);
+        }
+    });
+
+    Ok(touch.detach())
+}
+
+fn track_event(points: &mut HashMap<i32, TouchPoint>, event: wl_touch::Event) -> Option<Event> {
+    use wl_touch::Event as E;
+    // events about unknown points, which went down before the touchscreen was mapped, are ignored
+    let event = match event {
+        E::Down { serial, time, surface, id, x, y } => {
+            let point =
+                TouchPoint { id, surface, position: (x, y), shape: None, orientation: None };
+            points.insert(id, point.clone());
+            Event::Down { serial, time, point }
+        }
+        E::Up { serial, time, id } => Event::Up { serial, time, point: points.remove(&id)? },
+        E::Motion { time, id, x, y } => {
+            let point = points.get_mut(&id)?;
+            point.position = (x, y);
+            Event::Motion { time, point: point.clone() }
+        }
+        E::Shape { id, major, minor } => {
+            let point = points.get_mut(&id)?;
+            point.shape = Some((major, minor));
+            Event::Shape { point: point.clone() }
+        }
+        E::Orientation { id, orientation } => {
+            let point = points.get_mut(&id)?;
+            point.orientation = Some(orientation);
+            Event::Orientation { point: point.clone() }
+        }
+        E::Frame => Event::Frame,
+        E::Cancel => Event::Cancel { points: points.drain().map(|(_, point)| point).collect() },
+        _ => return None,
+    };
+    Some(event)
+}
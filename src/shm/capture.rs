@@ -0,0 +1,79 @@
+use std::io;
+
+use wayland_client::protocol::wl_shm;
+
+use super::MemPool;
+
+/// Convert the contents of an SHM buffer into an RGBA image
+///
+/// This lets an application save what it last presented, like a "save screenshot of
+/// the canvas" feature, without going through the capture protocols of the compositor.
+///
+/// `data` holds the pixels of the buffer, with the given dimensions, stride (in bytes)
+/// and format. The returned image is `width * height * 4` bytes of 8-bit RGBA pixels,
+/// without padding between rows and with straight (non premultiplied) alpha.
+///
+/// Only the 32-bit `Argb8888`, `Xrgb8888`, `Abgr8888` and `Xbgr8888` formats are supported.
+/// An error is returned for other formats, or if `data` is too small for the buffer.
+pub fn to_rgba(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    stride: u32,
+    format: wl_shm::Format,
+) -> io::Result<Vec<u8>> {
+    // position of the red, green, blue and alpha bytes in a pixel, and whether alpha is used
+    let (layout, alpha) = match format {
+        wl_shm::Format::Argb8888 => ([2, 1, 0, 3], true),
+        wl_shm::Format::Xrgb8888 => ([2, 1, 0, 3], false),
+        wl_shm::Format::Abgr8888 => ([0, 1, 2, 3], true),
+        wl_shm::Format::Xbgr8888 => ([0, 1, 2, 3], false),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unsupported buffer format {:?}", format),
+            ))
+        }
+    };
+    let (width, height, stride) = (width as usize, height as usize, stride as usize);
+    if stride < width * 4 || (height > 0 && data.len() < stride * (height - 1) + width * 4) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "buffer data is too small"));
+    }
+
+    let mut image = Vec::with_capacity(width * height * 4);
+    for row in 0..height {
+        for pixel in data[row * stride..][..width * 4].chunks_exact(4) {
+            let a = if alpha { pixel[layout[3]] } else { 255 };
+            for &channel in &layout[..3] {
+                // wl_shm pixels have premultiplied alpha
+                let value = match a {
+                    0 => 0,
+                    255 => pixel[channel],
+                    a => (u32::from(pixel[channel]) * 255 / u32::from(a)).min(255) as u8,
+                };
+                image.push(value);
+            }
+            image.push(a);
+        }
+    }
+    Ok(image)
+}
+
+impl MemPool {
+    /// Convert the contents of a buffer of this pool into an RGBA image
+    ///
+    /// The parameters are the ones the buffer was created with, see
+    /// [`buffer`](#method.buffer) and the [`to_rgba`](fn.to_rgba.html) function
+    /// for details.
+    pub fn to_rgba(
+        &mut self,
+        offset: usize,
+        width: u32,
+        height: u32,
+        stride: u32,
+        format: wl_shm::Format,
+    ) -> io::Result<Vec<u8>> {
+        let data = self.mmap().get(offset..).unwrap_or(&[]);
+        to_rgba(data, width, height, stride, format)
+    }
+}
@@ -0,0 +1,97 @@
+use std::io;
+
+use wayland_client::protocol::wl_shm::Format;
+
+/// The number of bytes used by a pixel of a format
+///
+/// This is `None` for the formats that do not store their pixels in a single plane of
+/// fixed-size pixels, like planar or tiled YUV formats, whose buffers can not be described
+/// by a single stride.
+///
+/// Formats storing several pixels in a block, like `Yuyv`, report the average size of a
+/// pixel, the width of their buffers must then be a multiple of the number of pixels per
+/// block.
+pub fn bytes_per_pixel(format: Format) -> Option<u32> {
+    use Format::*;
+    let bpp = match format {
+        C8 | R8 | Rgb332 | Bgr233 => 1,
+        Xrgb4444 | Xbgr4444 | Rgbx4444 | Bgrx4444 | Argb4444 | Abgr4444 | Rgba4444 | Bgra4444
+        | Xrgb1555 | Xbgr1555 | Rgbx5551 | Bgrx5551 | Argb1555 | Abgr1555 | Rgba5551 | Bgra5551
+        | Rgb565 | Bgr565 | R16 | Rg88 | Gr88 => 2,
+        // packed 4:2:2, two pixels in 4 bytes
+        Yuyv | Yvyu | Uyvy | Vyuy => 2,
+        Rgb888 | Bgr888 | Vuy888 => 3,
+        Argb8888 | Xrgb8888 | Xbgr8888 | Rgbx8888 | Bgrx8888 | Abgr8888 | Rgba8888 | Bgra8888
+        | Xrgb2101010 | Xbgr2101010 | Rgbx1010102 | Bgrx1010102 | Argb2101010 | Abgr2101010
+        | Rgba1010102 | Bgra1010102 | Rg1616 | Gr1616 | Ayuv | Xyuv8888 | Vuy101010 | Y410
+        | Xvyu2101010 => 4,
+        // packed 4:2:2 with 16-bit components, two pixels in 8 bytes
+        Y210 | Y212 | Y216 => 4,
+        Xrgb16161616f | Xbgr16161616f | Argb16161616f | Abgr16161616f | Xrgb16161616
+        | Xbgr16161616 | Argb16161616 | Abgr16161616 | Axbxgxrx106106106106 | Y412 | Y416
+        | Xvyu1216161616 | Xvyu16161616 => 8,
+        _ => return None,
+    };
+    Some(bpp)
+}
+
+/// The minimum stride of a buffer of a format, in bytes
+///
+/// This is the size of a row of `width` pixels without padding, which is also the stride
+/// the pools of SCTK expect by default. `None` is returned if the bytes per pixel of the
+/// format are unknown, see [`bytes_per_pixel`](fn.bytes_per_pixel.html), or if the stride
+/// overflows.
+pub fn min_stride(format: Format, width: i32) -> Option<i32> {
+    let bpp = bytes_per_pixel(format)?;
+    width.checked_mul(bpp as i32)
+}
+
+/// Check that a stride is valid for buffers of a format
+///
+/// The stride must be large enough for a row of `width` pixels. The check passes for
+/// formats whose bytes per pixel are unknown, as the stride can not be checked then.
+///
+/// This is the check done by all the pools of SCTK when the `strict` cargo feature is
+/// enabled.
+pub fn check_stride(format: Format, width: i32, stride: i32) -> io::Result<()> {
+    let too_small = match (bytes_per_pixel(format), min_stride(format, width)) {
+        (None, _) => false,
+        (Some(_), Some(min)) => stride < min,
+        // the width alone overflows
+        (Some(_), None) => true,
+    };
+    if too_small {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("stride {} is too small for {} pixels of format {:?}", stride, width, format),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bytes_per_pixel_of_formats() {
+        assert_eq!(bytes_per_pixel(Format::Argb8888), Some(4));
+        assert_eq!(bytes_per_pixel(Format::Rgb888), Some(3));
+        assert_eq!(bytes_per_pixel(Format::Rgb565), Some(2));
+        assert_eq!(bytes_per_pixel(Format::Yuyv), Some(2));
+        assert_eq!(bytes_per_pixel(Format::Nv12), None);
+    }
+
+    #[test]
+    fn check_stride_of_formats() {
+        assert!(check_stride(Format::Argb8888, 10, 40).is_ok());
+        assert!(check_stride(Format::Argb8888, 10, 64).is_ok());
+        assert!(check_stride(Format::Argb8888, 10, 39).is_err());
+        assert!(check_stride(Format::Rgb888, 10, 30).is_ok());
+        // the stride of unknown formats can not be checked
+        assert!(check_stride(Format::Nv12, 10, 1).is_ok());
+        assert_eq!(min_stride(Format::Argb8888, std::i32::MAX), None);
+        assert!(check_stride(Format::Argb8888, std::i32::MAX, std::i32::MAX).is_err());
+    }
+}
@@ -4,7 +4,7 @@ use std::{
     fmt,
     fs::File,
     io,
-    os::unix::io::{FromRawFd, RawFd},
+    os::unix::io::{AsRawFd, FromRawFd, RawFd},
     rc::Rc,
     time::SystemTime,
     time::UNIX_EPOCH,
@@ -100,38 +100,94 @@ impl DoubleMemPool {
     }
 }
 
-#[derive(Debug)]
-struct Inner {
-    file: File,
-    len: usize,
-    pool: Main<wl_shm_pool::WlShmPool>,
-    mmap: MmapMut,
+/// A provider of the memory backing a pool
+///
+/// The memory pools use a memfd (or a `shm_open` file on platforms lacking it) by default,
+/// see [`DefaultBackend`](struct.DefaultBackend.html). Implementing this trait lets you
+/// control how this memory is created, for example to back it with huge pages, apply custom
+/// seals, or pre-fault the pages of the mapping. SCTK still handles the `wl_shm_pool` side.
+///
+/// The file must be shareable with the compositor, so it needs to be backed by memory rather
+/// than a regular file on disk.
+pub trait PoolBackend {
+    /// Create the file backing a new pool, with a size of at least `size` bytes
+    fn create(&mut self, size: usize) -> io::Result<File>;
+
+    /// Grow the file backing the pool to `new_size` bytes
+    ///
+    /// Pools never shrink, so `new_size` is always larger than the previous size.
+    fn resize(&mut self, file: &File, new_size: usize) -> io::Result<()> {
+        file.set_len(new_size as u64)
+    }
+
+    /// Map the file backing the pool in memory
+    ///
+    /// This is called after the pool is created and whenever it is resized. The `memmap2`
+    /// crate is re-exported in [`reexports`](../reexports/index.html) to implement it.
+    fn map(&mut self, file: &File) -> io::Result<MmapMut> {
+        unsafe { MmapMut::map_mut(file) }
+    }
+}
+
+/// The default backend of the memory pools
+///
+/// It creates sealed memfd files on Linux, and falls back to `shm_open` elsewhere.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct DefaultBackend;
+
+impl PoolBackend for DefaultBackend {
+    fn create(&mut self, size: usize) -> io::Result<File> {
+        let file = unsafe { File::from_raw_fd(create_shm_fd()?) };
+        file.set_len(size as u64)?;
+        Ok(file)
+    }
+}
+
+pub(super) struct Inner {
+    pub(super) file: File,
+    pub(super) len: usize,
+    pub(super) pool: Main<wl_shm_pool::WlShmPool>,
+    pub(super) mmap: MmapMut,
+    backend: Box<dyn PoolBackend>,
 }
 
 impl Inner {
-    fn new(shm: Attached<wl_shm::WlShm>) -> io::Result<Self> {
-        let mem_fd = create_shm_fd()?;
-        let mem_file = unsafe { File::from_raw_fd(mem_fd) };
-        mem_file.set_len(4096)?;
+    pub(super) fn new(
+        shm: Attached<wl_shm::WlShm>,
+        mut backend: Box<dyn PoolBackend>,
+    ) -> io::Result<Self> {
+        let mem_file = backend.create(4096)?;
 
-        let pool = shm.create_pool(mem_fd, 4096);
+        let pool = shm.create_pool(mem_file.as_raw_fd(), 4096);
 
-        let mmap = unsafe { MmapMut::map_mut(&mem_file).unwrap() };
+        let mmap = backend.map(&mem_file)?;
 
-        Ok(Inner { file: mem_file, len: 4096, pool, mmap })
+        Ok(Inner { file: mem_file, len: 4096, pool, mmap, backend })
     }
 
-    fn resize(&mut self, newsize: usize) -> io::Result<()> {
+    pub(super) fn resize(&mut self, newsize: usize) -> io::Result<()> {
         if newsize > self.len {
-            self.file.set_len(newsize as u64)?;
+            self.backend.resize(&self.file, newsize)?;
             self.pool.resize(newsize as i32);
             self.len = newsize;
-            self.mmap = unsafe { MmapMut::map_mut(&self.file).unwrap() };
+            self.mmap = self.backend.map(&self.file)?;
         }
         Ok(())
     }
 }
 
+impl fmt::Debug for Inner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Inner")
+            .field("file", &self.file)
+            .field("len", &self.len)
+            .field("pool", &self.pool)
+            .field("mmap", &self.mmap)
+            .field("backend", &"dyn PoolBackend")
+            .finish()
+    }
+}
+
 impl Drop for Inner {
     fn drop(&mut self) {
         self.pool.destroy();
@@ -166,9 +222,24 @@ impl MemPool {
     pub fn new<F>(shm: Attached<wl_shm::WlShm>, callback: F) -> io::Result<MemPool>
     where
         F: FnMut(wayland_client::DispatchData) + 'static,
+    {
+        Self::with_backend(shm, DefaultBackend, callback)
+    }
+
+    /// Create a new memory pool associated with given shm, using a custom backend
+    ///
+    /// See [`PoolBackend`](trait.PoolBackend.html) for details.
+    pub fn with_backend<B, F>(
+        shm: Attached<wl_shm::WlShm>,
+        backend: B,
+        callback: F,
+    ) -> io::Result<MemPool>
+    where
+        B: PoolBackend + 'static,
+        F: FnMut(wayland_client::DispatchData) + 'static,
     {
         Ok(MemPool {
-            inner: Inner::new(shm)?,
+            inner: Inner::new(shm, Box::new(backend))?,
             buffer_count: Rc::new(RefCell::new(0)),
             callback: Rc::new(RefCell::new(callback)),
         })
@@ -201,6 +272,10 @@ impl MemPool {
     /// - `format`: the encoding format of the pixels. Using a format that was not
     ///   advertised to the `wl_shm` global by the server is a protocol error and will
     ///   terminate your connection
+    ///
+    /// With the `strict` cargo feature, invalid parameters are logged, but the request is
+    /// still sent as this method can not return an error. Use
+    /// [`try_buffer`](#method.try_buffer) to not send it.
     pub fn buffer(
         &self,
         offset: i32,
@@ -208,6 +283,43 @@ impl MemPool {
         height: i32,
         stride: i32,
         format: wl_shm::Format,
+    ) -> wl_buffer::WlBuffer {
+        // the error was already logged
+        let _ = crate::strict::check_buffer(
+            offset,
+            width,
+            height,
+            stride,
+            format,
+            Some(self.inner.len),
+        );
+        self.create_buffer(offset, width, height, stride, format)
+    }
+
+    /// Create a new buffer to this pool, checking its parameters
+    ///
+    /// This is [`buffer`](#method.buffer), except that with the `strict` cargo feature,
+    /// invalid parameters are logged and an error is returned instead of sending the
+    /// request. Without the feature, this never fails.
+    pub fn try_buffer(
+        &self,
+        offset: i32,
+        width: i32,
+        height: i32,
+        stride: i32,
+        format: wl_shm::Format,
+    ) -> io::Result<wl_buffer::WlBuffer> {
+        crate::strict::check_buffer(offset, width, height, stride, format, Some(self.inner.len))?;
+        Ok(self.create_buffer(offset, width, height, stride, format))
+    }
+
+    fn create_buffer(
+        &self,
+        offset: i32,
+        width: i32,
+        height: i32,
+        stride: i32,
+        format: wl_shm::Format,
     ) -> wl_buffer::WlBuffer {
         *self.buffer_count.borrow_mut() += 1;
         let my_buffer_count = self.buffer_count.clone();
@@ -300,9 +412,25 @@ impl AutoMemPool {
     /// All buffers will be aligned to at least the value of (align), which must be a power of two
     /// not greater than 4096.
     pub fn with_min_align(shm: Attached<wl_shm::WlShm>, align: usize) -> io::Result<AutoMemPool> {
+        Self::with_backend(shm, align, DefaultBackend)
+    }
+
+    /// Create a new memory pool associated with the given shm, using a custom backend
+    ///
+    /// The alignment requirements are the same as for
+    /// [`with_min_align`](#method.with_min_align). See [`PoolBackend`](trait.PoolBackend.html)
+    /// for details about the backend.
+    pub fn with_backend<B>(
+        shm: Attached<wl_shm::WlShm>,
+        align: usize,
+        backend: B,
+    ) -> io::Result<AutoMemPool>
+    where
+        B: PoolBackend + 'static,
+    {
         assert!(align.is_power_of_two());
         assert!(align <= 4096);
-        let inner = Inner::new(shm)?;
+        let inner = Inner::new(shm, Box::new(backend))?;
         let free_list = Rc::new(RefCell::new(vec![(0, inner.len)]));
         Ok(AutoMemPool { inner, align, free_list })
     }
@@ -328,64 +456,6 @@ impl AutoMemPool {
         Ok(())
     }
 
-    fn alloc(&mut self, size: usize) -> io::Result<usize> {
-        let mut free = self.free_list.borrow_mut();
-        for (offset, len) in free.iter_mut() {
-            if *len >= size {
-                let rv = *offset;
-                *len -= size;
-                *offset += size;
-                return Ok(rv);
-            }
-        }
-        let mut rv = self.inner.len;
-        let mut pop_tail = false;
-        if let Some((start, len)) = free.last() {
-            if start + len == self.inner.len {
-                rv -= len;
-                pop_tail = true;
-            }
-        }
-        // resize like Vec::reserve, always at least doubling
-        let target = std::cmp::max(rv + size, self.inner.len * 2);
-        self.inner.resize(target)?;
-        // adjust the end of the freelist here
-        if pop_tail {
-            free.pop();
-        }
-        if target > rv + size {
-            free.push((rv + size, target - rv - size));
-        }
-        Ok(rv)
-    }
-
-    fn free(free_list: &RefCell<Vec<(usize, usize)>>, mut offset: usize, mut len: usize) {
-        let mut free = free_list.borrow_mut();
-        let mut nf = Vec::with_capacity(free.len() + 1);
-        for &(ioff, ilen) in free.iter() {
-            if ioff + ilen == offset {
-                offset = ioff;
-                len += ilen;
-                continue;
-            }
-            if ioff == offset + len {
-                len += ilen;
-                continue;
-            }
-            if ioff > offset + len && len != 0 {
-                nf.push((offset, len));
-                len = 0;
-            }
-            if ilen != 0 {
-                nf.push((ioff, ilen));
-            }
-        }
-        if len != 0 {
-            nf.push((offset, len));
-        }
-        *free = nf;
-    }
-
     /// Create a new buffer in this pool
     ///
     /// The parameters are:
@@ -396,6 +466,9 @@ impl AutoMemPool {
     /// - `format`: the encoding format of the pixels. Using a format that was not
     ///   advertised to the `wl_shm` global by the server is a protocol error and will
     ///   terminate your connection
+    ///
+    /// With the `strict` cargo feature, invalid parameters are logged and an error
+    /// is returned.
     pub fn buffer(
         &mut self,
         width: i32,
@@ -403,16 +476,17 @@ impl AutoMemPool {
         stride: i32,
         format: wl_shm::Format,
     ) -> io::Result<(&mut [u8], wl_buffer::WlBuffer)> {
+        crate::strict::check_buffer(0, width, height, stride, format, None)?;
         let len = (height as usize) * (stride as usize);
         let alloc_len = (len + self.align - 1) & !(self.align - 1);
-        let offset = self.alloc(alloc_len)?;
+        let offset = alloc(&mut self.inner, &self.free_list, alloc_len)?;
         let offset_i = offset as i32;
         let buffer = self.inner.pool.create_buffer(offset_i, width, height, stride, format);
         let free_list = self.free_list.clone();
         buffer.quick_assign(move |buffer, event, _| match event {
             wl_buffer::Event::Release => {
                 buffer.destroy();
-                Self::free(&free_list, offset, alloc_len);
+                free(&free_list, offset, alloc_len);
             }
             _ => unreachable!(),
         });
@@ -436,12 +510,13 @@ impl AutoMemPool {
         F: FnOnce(&mut [u8]) -> Result<(), E>,
         E: From<io::Error>,
     {
+        crate::strict::check_buffer(0, width, height, stride, format, None)?;
         let len = (height as usize) * (stride as usize);
         let alloc_len = (len + self.align - 1) & !(self.align - 1);
-        let offset = self.alloc(alloc_len)?;
+        let offset = alloc(&mut self.inner, &self.free_list, alloc_len)?;
         let offset_i = offset as i32;
         if let Err(e) = draw(&mut self.inner.mmap[offset..][..len]) {
-            Self::free(&self.free_list, offset, alloc_len);
+            free(&self.free_list, offset, alloc_len);
             return Err(e);
         }
         let buffer = self.inner.pool.create_buffer(offset_i, width, height, stride, format);
@@ -449,7 +524,7 @@ impl AutoMemPool {
         buffer.quick_assign(move |buffer, event, _| match event {
             wl_buffer::Event::Release => {
                 buffer.destroy();
-                Self::free(&free_list, offset, alloc_len);
+                free(&free_list, offset, alloc_len);
             }
             _ => unreachable!(),
         });
@@ -457,6 +532,72 @@ impl AutoMemPool {
     }
 }
 
+// Allocate memory from the free list of a pool, growing it if needed
+//
+// The smallest free segment that fits is used, to keep the larger ones available.
+pub(super) fn alloc(
+    inner: &mut Inner,
+    free_list: &RefCell<Vec<(usize, usize)>>,
+    size: usize,
+) -> io::Result<usize> {
+    let mut free = free_list.borrow_mut();
+    if let Some((offset, len)) =
+        free.iter_mut().filter(|(_, len)| *len >= size).min_by_key(|(_, len)| *len)
+    {
+        let rv = *offset;
+        *len -= size;
+        *offset += size;
+        return Ok(rv);
+    }
+    let mut rv = inner.len;
+    let mut pop_tail = false;
+    if let Some((start, len)) = free.last() {
+        if start + len == inner.len {
+            rv -= len;
+            pop_tail = true;
+        }
+    }
+    // resize like Vec::reserve, always at least doubling
+    let target = std::cmp::max(rv + size, inner.len * 2);
+    inner.resize(target)?;
+    // adjust the end of the freelist here
+    if pop_tail {
+        free.pop();
+    }
+    if target > rv + size {
+        free.push((rv + size, target - rv - size));
+    }
+    Ok(rv)
+}
+
+// Return memory to the free list of a pool
+pub(super) fn free(free_list: &RefCell<Vec<(usize, usize)>>, mut offset: usize, mut len: usize) {
+    let mut free = free_list.borrow_mut();
+    let mut nf = Vec::with_capacity(free.len() + 1);
+    for &(ioff, ilen) in free.iter() {
+        if ioff + ilen == offset {
+            offset = ioff;
+            len += ilen;
+            continue;
+        }
+        if ioff == offset + len {
+            len += ilen;
+            continue;
+        }
+        if ioff > offset + len && len != 0 {
+            nf.push((offset, len));
+            len = 0;
+        }
+        if ilen != 0 {
+            nf.push((ioff, ilen));
+        }
+    }
+    if len != 0 {
+        nf.push((offset, len));
+    }
+    *free = nf;
+}
+
 fn create_shm_fd() -> io::Result<RawFd> {
     // Only try memfd on linux
     #[cfg(target_os = "linux")]
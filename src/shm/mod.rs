@@ -7,9 +7,18 @@ use wayland_client::{
     Attached, DispatchData,
 };
 
+mod capture;
+mod format;
 mod mempool;
+pub mod multi;
+mod ninepatch;
+pub mod slot;
+pub mod staging;
 
-pub use self::mempool::{AutoMemPool, DoubleMemPool, MemPool};
+pub use self::capture::to_rgba;
+pub use self::format::{bytes_per_pixel, check_stride, min_stride};
+pub use self::mempool::{AutoMemPool, DefaultBackend, DoubleMemPool, MemPool, PoolBackend};
+pub use self::ninepatch::NinePatch;
 pub use wl_shm::Format;
 
 /// A handler for the `wl_shm` global
@@ -77,4 +86,27 @@ where
     pub fn shm_formats(&self) -> Vec<wl_shm::Format> {
         self.with_inner(|inner| inner.shm_formats())
     }
+
+    /// Pick the first format of a preference list supported by the compositor
+    ///
+    /// See [`choose_format`](../shm/fn.choose_format.html).
+    pub fn choose_shm_format(&self, preferred: &[wl_shm::Format]) -> Option<wl_shm::Format> {
+        choose_format(preferred, &self.shm_formats())
+    }
+}
+
+/// Pick the first format of a preference list that is supported
+///
+/// `preferred` is ordered from the most to the least preferred format, like
+/// `[Format::Xrgb2101010, Format::Xrgb8888]`, and `supported` is the list of formats advertised
+/// by the compositor. `Argb8888` and `Xrgb8888` are always considered supported, as all
+/// compositors are required to support them.
+pub fn choose_format(
+    preferred: &[wl_shm::Format],
+    supported: &[wl_shm::Format],
+) -> Option<wl_shm::Format> {
+    preferred.iter().copied().find(|format| {
+        matches!(format, wl_shm::Format::Argb8888 | wl_shm::Format::Xrgb8888)
+            || supported.contains(format)
+    })
 }
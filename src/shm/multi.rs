@@ -0,0 +1,369 @@
+//! A memory pool holding several buffers identified by keys
+//!
+//! The [`MultiPool`](struct.MultiPool.html) is meant for double or triple buffering: each
+//! buffer you draw to is identified by a key of your choosing, like an index or the
+//! surface it is drawn for, and the pool takes care of placing the buffers in the shared
+//! memory and of tracking which ones are still in use by the compositor.
+//!
+//! The pool also tracks the age of the buffers, the number of frames of their surface since
+//! their content was presented, so that renderers can only redraw the parts that changed
+//! since then, like with the `EGL_EXT_buffer_age` extension.
+
+use std::{cell::Cell, collections::VecDeque, io, rc::Rc};
+
+use wayland_client::{
+    protocol::{wl_buffer, wl_shm, wl_shm_pool, wl_surface},
+    Attached, Main,
+};
+
+use memmap2::MmapMut;
+
+use super::mempool::Inner;
+use super::{DefaultBackend, PoolBackend};
+use crate::DamageRect;
+
+// the number of frames whose damage is kept for the buffer ages
+const DAMAGE_HISTORY: usize = 8;
+
+#[derive(Debug)]
+struct Slot<K> {
+    // `None` once the slot was removed while its buffer was in use
+    key: Option<K>,
+    offset: usize,
+    len: usize,
+    // (width, height, stride, format)
+    params: (i32, i32, i32, wl_shm::Format),
+    buffer: wl_buffer::WlBuffer,
+    free: Rc<Cell<bool>>,
+    // the surface and frame in which the content of the buffer was last presented
+    presented: Option<(wl_surface::WlSurface, u64)>,
+}
+
+// The frames presented on a surface, each surface having its own swapchain
+#[derive(Debug)]
+struct Chain {
+    surface: wl_surface::WlSurface,
+    // the number of frames presented so far
+    frame: u64,
+    // the damage of the last frames, the most recent first
+    history: VecDeque<Vec<DamageRect>>,
+}
+
+/// A memory pool holding several buffers identified by keys
+///
+/// The buffers live in a single SHM pool, which grows as needed. The `wl_buffer` of a key is
+/// kept and reused as long as it is requested with the same parameters, and its memory is
+/// only reused once the compositor released it.
+///
+/// A buffer is considered in use as soon as it is returned by the pool, until the compositor
+/// releases it. As such, a buffer that is never committed to a surface stays unavailable
+/// until it is [`remove`](#method.remove)d.
+///
+/// Call [`presented`](#method.presented) whenever you commit one of the buffers, so that
+/// [`buffer_age`](#method.buffer_age) and [`damage_since`](#method.damage_since) can tell
+/// which parts of a buffer are out of date when it is reused. The frames are counted for
+/// each surface, so a pool can hold the buffers of several surfaces, using keys like
+/// `(surface, index)`.
+///
+/// MultiPool handles the destruction of the buffers, the `destroy()` method should not be
+/// used on the buffers it returns.
+#[derive(Debug)]
+pub struct MultiPool<K> {
+    inner: Inner,
+    align: usize,
+    // sorted by offset
+    slots: Vec<Slot<K>>,
+    chains: Vec<Chain>,
+}
+
+impl<K: PartialEq> MultiPool<K> {
+    /// Create a new multi-buffer pool associated with the given shm
+    pub fn new(shm: Attached<wl_shm::WlShm>) -> io::Result<MultiPool<K>> {
+        Self::with_backend(shm, DefaultBackend)
+    }
+
+    /// Create a new multi-buffer pool associated with the given shm, using a custom backend
+    ///
+    /// See [`PoolBackend`](../trait.PoolBackend.html) for details.
+    pub fn with_backend<B>(shm: Attached<wl_shm::WlShm>, backend: B) -> io::Result<MultiPool<K>>
+    where
+        B: PoolBackend + 'static,
+    {
+        Ok(MultiPool {
+            inner: Inner::new(shm, Box::new(backend))?,
+            align: 64,
+            slots: Vec::new(),
+            chains: Vec::new(),
+        })
+    }
+
+    /// Resize the memory pool
+    ///
+    /// This is normally done automatically, but can be used to avoid multiple resizes. The
+    /// pool can only grow, this does nothing if the requested size is smaller than the
+    /// current one.
+    pub fn resize(&mut self, new_size: usize) -> io::Result<()> {
+        self.inner.resize(new_size)
+    }
+
+    /// Get the buffer of a key, creating it if needed
+    ///
+    /// The parameters are the same as for [`MemPool::buffer`](../struct.MemPool.html#method.buffer),
+    /// the offset being chosen by the pool. If the key already has a buffer with the same
+    /// parameters, it is returned again with its previous content, otherwise a new buffer is
+    /// created, moving it elsewhere in the pool if it does not fit in its previous place.
+    ///
+    /// Returns the offset of the buffer in the pool, the buffer and its content, or `None` if
+    /// the buffer of this key is still in use by the compositor.
+    ///
+    /// With the `strict` cargo feature, invalid parameters are logged and an error
+    /// is returned.
+    pub fn create_buffer(
+        &mut self,
+        key: K,
+        width: i32,
+        height: i32,
+        stride: i32,
+        format: wl_shm::Format,
+    ) -> io::Result<Option<(usize, wl_buffer::WlBuffer, &mut [u8])>> {
+        crate::strict::check_buffer(0, width, height, stride, format, None)?;
+        self.purge();
+        let params = (width, height, stride, format);
+        let len = (height as usize) * (stride as usize);
+        let alloc_len = (len + self.align - 1) & !(self.align - 1);
+
+        let idx = match self.slots.iter().position(|slot| slot.key.as_ref() == Some(&key)) {
+            Some(idx) if !self.slots[idx].free.get() => return Ok(None),
+            Some(idx) if self.slots[idx].params == params => idx,
+            Some(idx) => {
+                let end = self.slots.get(idx + 1).map(|s| s.offset).unwrap_or(self.inner.len);
+                let slot = &mut self.slots[idx];
+                slot.buffer.destroy();
+                if slot.offset + alloc_len <= end {
+                    // the new buffer fits in place
+                    slot.len = alloc_len;
+                    slot.params = params;
+                    slot.presented = None;
+                    slot.buffer =
+                        create_wl_buffer(&self.inner.pool, slot.offset, params, &slot.free);
+                    idx
+                } else {
+                    self.slots.remove(idx);
+                    self.insert(key, alloc_len, params)?
+                }
+            }
+            None => self.insert(key, alloc_len, params)?,
+        };
+
+        let slot = &self.slots[idx];
+        slot.free.set(false);
+        Ok(Some((slot.offset, slot.buffer.clone(), &mut self.inner.mmap[slot.offset..][..len])))
+    }
+
+    /// Get the existing buffer of a key
+    ///
+    /// Returns the offset of the buffer in the pool, the buffer and its content, or `None` if
+    /// there is no buffer for this key or if it is still in use by the compositor.
+    pub fn get(&mut self, key: &K) -> Option<(usize, wl_buffer::WlBuffer, &mut [u8])> {
+        let slot = self.slots.iter().find(|slot| slot.key.as_ref() == Some(key))?;
+        if !slot.free.get() {
+            return None;
+        }
+        slot.free.set(false);
+        let len = (slot.params.1 as usize) * (slot.params.2 as usize);
+        Some((slot.offset, slot.buffer.clone(), &mut self.inner.mmap[slot.offset..][..len]))
+    }
+
+    /// Returns true if the buffer of this key is currently in use by the compositor
+    pub fn is_used(&self, key: &K) -> bool {
+        self.slots.iter().any(|slot| slot.key.as_ref() == Some(key) && !slot.free.get())
+    }
+
+    /// The age of the buffer of a key
+    ///
+    /// This is the number of frames of the surface it was last presented on since its content
+    /// was presented, following the semantics of `EGL_EXT_buffer_age`: 1 if it was presented
+    /// in the last frame, 2 if it was presented in the frame before, and so on. It is 0 if the
+    /// content of the buffer is undefined, because it was never presented or was (re)created
+    /// by [`create_buffer`](#method.create_buffer), in which case it must be fully redrawn.
+    ///
+    /// The age only makes sense when the buffer is presented again on the same surface.
+    pub fn buffer_age(&self, key: &K) -> u32 {
+        self.slots
+            .iter()
+            .find(|slot| slot.key.as_ref() == Some(key))
+            .and_then(|slot| slot.presented.as_ref())
+            .and_then(|&(ref surface, presented)| {
+                let chain = self.chains.iter().find(|chain| chain.surface == *surface)?;
+                Some((chain.frame - presented + 1).min(u64::from(std::u32::MAX)) as u32)
+            })
+            .unwrap_or(0)
+    }
+
+    /// Record that the buffer of a key was presented, along with the parts of it that changed
+    ///
+    /// Call this when committing the buffer to `surface`, `damage` being the rectangles that
+    /// were redrawn for this frame, in buffer pixels. This starts a new frame of the surface,
+    /// aging the other buffers presented on it.
+    pub fn presented(&mut self, key: &K, surface: &wl_surface::WlSurface, damage: &[DamageRect]) {
+        // forget the surfaces that were destroyed
+        self.chains.retain(|chain| chain.surface.as_ref().is_alive());
+        let idx = match self.chains.iter().position(|chain| chain.surface == *surface) {
+            Some(idx) => idx,
+            None => {
+                self.chains.push(Chain {
+                    surface: surface.clone(),
+                    frame: 0,
+                    history: VecDeque::new(),
+                });
+                self.chains.len() - 1
+            }
+        };
+        let chain = &mut self.chains[idx];
+        chain.frame += 1;
+        chain.history.push_front(damage.to_vec());
+        chain.history.truncate(DAMAGE_HISTORY);
+        let frame = chain.frame;
+        if let Some(slot) = self.slots.iter_mut().find(|slot| slot.key.as_ref() == Some(key)) {
+            slot.presented = Some((surface.clone(), frame));
+        }
+    }
+
+    /// The damage accumulated by the frames of a surface a buffer of given age missed
+    ///
+    /// This is the union of the damage given to [`presented`](#method.presented) for
+    /// `surface` in its last `age - 1` frames: a buffer of this age is up to date once these
+    /// rectangles are redrawn, in addition to the damage of the new frame. Returns `None` if
+    /// the buffer must be fully redrawn, because its age is 0 or more frames than the pool
+    /// remembers.
+    pub fn damage_since(
+        &self,
+        surface: &wl_surface::WlSurface,
+        age: u32,
+    ) -> Option<Vec<DamageRect>> {
+        let chain = self.chains.iter().find(|chain| chain.surface == *surface)?;
+        if age == 0 || age as usize > chain.history.len() + 1 {
+            return None;
+        }
+        Some(chain.history.iter().take(age as usize - 1).flatten().copied().collect())
+    }
+
+    /// Remove the buffer of a key
+    ///
+    /// If the buffer is in use by the compositor, its memory is only reused once it is
+    /// released. Returns false if there was no buffer for this key.
+    pub fn remove(&mut self, key: &K) -> bool {
+        match self.slots.iter_mut().find(|slot| slot.key.as_ref() == Some(key)) {
+            Some(slot) => slot.key = None,
+            None => return false,
+        }
+        self.purge();
+        true
+    }
+
+    /// Uses the memmap2 crate to map the underlying shared memory file
+    pub fn mmap(&mut self) -> &mut MmapMut {
+        &mut self.inner.mmap
+    }
+
+    // destroy the removed buffers that were released since
+    fn purge(&mut self) {
+        self.slots.retain(|slot| {
+            if slot.key.is_none() && slot.free.get() {
+                slot.buffer.destroy();
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    // find room for a new slot, growing the pool if needed, and return its index
+    fn insert(
+        &mut self,
+        key: K,
+        len: usize,
+        params: (i32, i32, i32, wl_shm::Format),
+    ) -> io::Result<usize> {
+        let mut offset = 0;
+        let mut idx = self.slots.len();
+        for (i, slot) in self.slots.iter().enumerate() {
+            if slot.offset >= offset + len {
+                idx = i;
+                break;
+            }
+            offset = slot.offset + slot.len;
+        }
+        if offset + len > self.inner.len {
+            // resize like Vec::reserve, always at least doubling
+            self.inner.resize(std::cmp::max(offset + len, self.inner.len * 2))?;
+        }
+        let free = Rc::new(Cell::new(true));
+        let buffer = create_wl_buffer(&self.inner.pool, offset, params, &free);
+        self.slots.insert(
+            idx,
+            Slot { key: Some(key), offset, len, params, buffer, free, presented: None },
+        );
+        Ok(idx)
+    }
+}
+
+impl<K> Drop for MultiPool<K> {
+    fn drop(&mut self) {
+        for slot in &self.slots {
+            slot.buffer.destroy();
+        }
+    }
+}
+
+fn create_wl_buffer(
+    pool: &Main<wl_shm_pool::WlShmPool>,
+    offset: usize,
+    params: (i32, i32, i32, wl_shm::Format),
+    free: &Rc<Cell<bool>>,
+) -> wl_buffer::WlBuffer {
+    let (width, height, stride, format) = params;
+    let buffer = pool.create_buffer(offset as i32, width, height, stride, format);
+    let free = free.clone();
+    buffer.quick_assign(move |_, event, _| match event {
+        wl_buffer::Event::Release => free.set(true),
+        _ => unreachable!(),
+    });
+    buffer.detach()
+}
+
+impl<E> crate::environment::Environment<E>
+where
+    E: crate::environment::GlobalHandler<wl_shm::WlShm>,
+{
+    /// Create a multi-buffer memory pool
+    ///
+    /// This pool holds several buffers identified by keys, reusing each of them once the
+    /// compositor has released it. See [`MultiPool`](../shm/multi/struct.MultiPool.html).
+    pub fn create_multi_pool<K: PartialEq>(&self) -> io::Result<MultiPool<K>> {
+        MultiPool::new(self.require_global::<wl_shm::WlShm>())
+    }
+}
+
+impl<E> crate::environment::Environment<E>
+where
+    E: crate::environment::GlobalHandler<wl_shm::WlShm> + super::ShmHandling,
+{
+    /// Create a multi-buffer memory pool, along with the format its buffers should use
+    ///
+    /// The format is the first of `preferred` supported by the compositor, as given by
+    /// [`choose_shm_format`](#method.choose_shm_format), so that renderers can configure
+    /// their pixel pipelines accordingly. Returns an error if none of them is supported.
+    pub fn create_multi_pool_with_formats<K: PartialEq>(
+        &self,
+        preferred: &[wl_shm::Format],
+    ) -> io::Result<(MultiPool<K>, wl_shm::Format)> {
+        let format = self.choose_shm_format(preferred).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "none of the preferred formats is supported by the compositor",
+            )
+        })?;
+        Ok((self.create_multi_pool()?, format))
+    }
+}
@@ -0,0 +1,117 @@
+use std::io;
+
+/// An image that can be drawn at any size by stretching its center
+///
+/// A nine-patch image is split in nine parts by its borders: the four corners are drawn
+/// unchanged, the edges are stretched along their length and the center is stretched in
+/// both directions. This makes it possible to draw themed frames, buttons or shadows of any
+/// size from a small image, without hand-written pixel loops.
+///
+/// The pixels are 4 bytes each, and are copied as-is into the canvas, so they must be in the
+/// format of the buffer being drawn, like `Argb8888`. They replace the pixels of the canvas,
+/// no blending is done.
+#[derive(Debug, Clone)]
+pub struct NinePatch {
+    pixels: Vec<u8>,
+    width: u32,
+    height: u32,
+    borders: (u32, u32, u32, u32),
+}
+
+impl NinePatch {
+    /// Create a nine-patch from an image
+    ///
+    /// `pixels` holds the `width * height` pixels of the image, without padding between
+    /// rows. `borders` gives the `(left, top, right, bottom)` sizes of the borders, which
+    /// must leave a center of at least one pixel.
+    ///
+    /// Returns an error if `pixels` has the wrong size or the borders are too large.
+    pub fn new(
+        pixels: Vec<u8>,
+        width: u32,
+        height: u32,
+        borders: (u32, u32, u32, u32),
+    ) -> io::Result<NinePatch> {
+        let (left, top, right, bottom) = borders;
+        if pixels.len() != width as usize * height as usize * 4 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "pixel data does not match the image size",
+            ));
+        }
+        if left + right >= width || top + bottom >= height {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "nine-patch borders leave no center",
+            ));
+        }
+        Ok(NinePatch { pixels, width, height, borders })
+    }
+
+    /// Draw the nine-patch in a canvas
+    ///
+    /// `canvas` is the content of a buffer with given stride (in bytes), and `dest` the
+    /// `(x, y, width, height)` rectangle to fill, in buffer pixels. The corners and the
+    /// thickness of the edges are enlarged `scale` times, to draw on HiDPI surfaces the way
+    /// the image would look on a surface of scale 1. They are shrunk if the rectangle is too
+    /// small to fit them.
+    ///
+    /// Returns an error if the rectangle does not fit in the canvas.
+    pub fn draw(
+        &self,
+        canvas: &mut [u8],
+        stride: u32,
+        dest: (u32, u32, u32, u32),
+        scale: u32,
+    ) -> io::Result<()> {
+        let (x, y, width, height) =
+            (dest.0 as usize, dest.1 as usize, dest.2 as usize, dest.3 as usize);
+        let stride = stride as usize;
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+        if (x + width) * 4 > stride || (y + height - 1) * stride + (x + width) * 4 > canvas.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "nine-patch rectangle does not fit in the canvas",
+            ));
+        }
+
+        let (left, top, right, bottom) = self.borders;
+        let scale = scale.max(1);
+        let columns = map_axis(width, self.width, left, right, scale);
+        let rows = map_axis(height, self.height, top, bottom, scale);
+        for (dy, &sy) in rows.iter().enumerate() {
+            let line = &mut canvas[(y + dy) * stride + x * 4..][..width * 4];
+            let source = &self.pixels[sy * self.width as usize * 4..];
+            for (pixel, &sx) in line.chunks_exact_mut(4).zip(&columns) {
+                pixel.copy_from_slice(&source[sx * 4..sx * 4 + 4]);
+            }
+        }
+        Ok(())
+    }
+}
+
+// For each pixel along an axis of the destination, the matching pixel of the image
+fn map_axis(len: usize, image_len: u32, start: u32, end: u32, scale: u32) -> Vec<usize> {
+    let (image_len, start, end) = (image_len as usize, start as usize, end as usize);
+    let (mut dest_start, mut dest_end) = (start * scale as usize, end * scale as usize);
+    if dest_start + dest_end > len {
+        // the borders don't fit, shrink them proportionally
+        dest_start = len * start / (start + end);
+        dest_end = len - dest_start;
+    }
+    let center = image_len - start - end;
+    let dest_center = len - dest_start - dest_end;
+    (0..len)
+        .map(|d| {
+            if d < dest_start {
+                d * start / dest_start
+            } else if d >= len - dest_end {
+                image_len - end + (d - (len - dest_end)) * end / dest_end
+            } else {
+                start + (d - dest_start) * center / dest_center
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,241 @@
+//! A memory pool handing out reusable buffers
+//!
+//! The [`SlotPool`](struct.SlotPool.html) allocates each [`Buffer`](struct.Buffer.html) in
+//! its own slot of a single SHM pool. A buffer can be drawn to again once the compositor
+//! released it, and its slot is returned to the pool when it is dropped.
+
+use std::{
+    cell::{Cell, RefCell},
+    fmt, io,
+    rc::Rc,
+};
+
+use wayland_client::{
+    protocol::{wl_buffer, wl_shm, wl_surface},
+    Attached,
+};
+
+use super::mempool::{alloc, free, Inner};
+use super::{DefaultBackend, PoolBackend};
+
+type FreeList = Rc<RefCell<Vec<(usize, usize)>>>;
+
+/// A memory pool handing out reusable buffers
+///
+/// Each buffer is allocated in its own slot of the pool, which grows when it is full. The
+/// slot of a buffer is reused for new buffers once the buffer is dropped and the compositor
+/// released it.
+#[derive(Debug)]
+pub struct SlotPool {
+    inner: Inner,
+    free_list: FreeList,
+}
+
+impl SlotPool {
+    /// Create a new slot pool associated with the given shm
+    pub fn new(shm: Attached<wl_shm::WlShm>) -> io::Result<SlotPool> {
+        Self::with_backend(shm, DefaultBackend)
+    }
+
+    /// Create a new slot pool associated with the given shm, using a custom backend
+    ///
+    /// See [`PoolBackend`](../trait.PoolBackend.html) for details.
+    pub fn with_backend<B>(shm: Attached<wl_shm::WlShm>, backend: B) -> io::Result<SlotPool>
+    where
+        B: PoolBackend + 'static,
+    {
+        let inner = Inner::new(shm, Box::new(backend))?;
+        let free_list = Rc::new(RefCell::new(vec![(0, inner.len)]));
+        Ok(SlotPool { inner, free_list })
+    }
+
+    /// Resize the memory pool
+    ///
+    /// This is normally done automatically, but can be used to avoid multiple resizes.
+    pub fn resize(&mut self, new_size: usize) -> io::Result<()> {
+        let old_size = self.inner.len;
+        if old_size >= new_size {
+            return Ok(());
+        }
+        self.inner.resize(new_size)?;
+        free(&self.free_list, old_size, new_size - old_size);
+        Ok(())
+    }
+
+    /// Create a new buffer in its own slot of the pool
+    ///
+    /// The parameters are the same as for
+    /// [`AutoMemPool::buffer`](../struct.AutoMemPool.html#method.buffer). The content of the
+    /// buffer is accessed with [`Buffer::canvas`](struct.Buffer.html#method.canvas).
+    ///
+    /// With the `strict` cargo feature, invalid parameters are logged and an error
+    /// is returned.
+    pub fn create_buffer(
+        &mut self,
+        width: i32,
+        height: i32,
+        stride: i32,
+        format: wl_shm::Format,
+    ) -> io::Result<Buffer> {
+        crate::strict::check_buffer(0, width, height, stride, format, None)?;
+        let len = (height as usize) * (stride as usize);
+        // the slots are aligned on 64 bytes
+        let alloc_len = (len + 63) & !63;
+        let offset = alloc(&mut self.inner, &self.free_list, alloc_len)?;
+        let buffer = self.inner.pool.create_buffer(offset as i32, width, height, stride, format);
+        let state = Rc::new(BufferState {
+            in_flight: Cell::new(false),
+            dropped: Cell::new(false),
+            offset,
+            alloc_len,
+            free_list: self.free_list.clone(),
+        });
+        let my_state = state.clone();
+        buffer.quick_assign(move |buffer, event, _| match event {
+            wl_buffer::Event::Release => {
+                my_state.in_flight.set(false);
+                if my_state.dropped.get() {
+                    buffer.destroy();
+                    my_state.release_slot();
+                }
+            }
+            _ => unreachable!(),
+        });
+        Ok(Buffer { buffer: buffer.detach(), size: (width, height), len, state })
+    }
+}
+
+impl<E> crate::environment::Environment<E>
+where
+    E: crate::environment::GlobalHandler<wl_shm::WlShm>,
+{
+    /// Create a slot pool
+    ///
+    /// This pool hands out buffers that can be drawn to again once the compositor released
+    /// them. See [`SlotPool`](../shm/slot/struct.SlotPool.html).
+    pub fn create_slot_pool(&self) -> io::Result<SlotPool> {
+        SlotPool::new(self.require_global::<wl_shm::WlShm>())
+    }
+}
+
+impl<E> crate::environment::Environment<E>
+where
+    E: crate::environment::GlobalHandler<wl_shm::WlShm> + super::ShmHandling,
+{
+    /// Create a slot pool, along with the format its buffers should use
+    ///
+    /// The format is the first of `preferred` supported by the compositor, as given by
+    /// [`choose_shm_format`](#method.choose_shm_format). Returns an error if none of them
+    /// is supported.
+    pub fn create_slot_pool_with_formats(
+        &self,
+        preferred: &[wl_shm::Format],
+    ) -> io::Result<(SlotPool, wl_shm::Format)> {
+        let format = self.choose_shm_format(preferred).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "none of the preferred formats is supported by the compositor",
+            )
+        })?;
+        Ok((self.create_slot_pool()?, format))
+    }
+}
+
+struct BufferState {
+    in_flight: Cell<bool>,
+    dropped: Cell<bool>,
+    offset: usize,
+    alloc_len: usize,
+    free_list: FreeList,
+}
+
+impl BufferState {
+    fn release_slot(&self) {
+        free(&self.free_list, self.offset, self.alloc_len);
+    }
+}
+
+/// A buffer allocated in a slot of a [`SlotPool`](struct.SlotPool.html)
+///
+/// The buffer is in flight from the moment it is attached to a surface with
+/// [`attach_to`](#method.attach_to) until the compositor releases it, and its content can
+/// only be accessed while it is not.
+///
+/// The `wl_buffer` it wraps is not exposed, so that the buffer can not be attached without
+/// being marked in flight.
+///
+/// Dropping the buffer destroys it and returns its slot to the pool, once the compositor
+/// released it if it is in flight.
+pub struct Buffer {
+    buffer: wl_buffer::WlBuffer,
+    size: (i32, i32),
+    len: usize,
+    state: Rc<BufferState>,
+}
+
+impl Buffer {
+    /// Whether the buffer is in use by the compositor
+    pub fn is_in_flight(&self) -> bool {
+        self.state.in_flight.get()
+    }
+
+    /// Access the content of the buffer
+    ///
+    /// `pool` must be the pool the buffer was created from. Returns `None` if the buffer is
+    /// in flight, or if it belongs to another pool.
+    pub fn canvas<'a>(&self, pool: &'a mut SlotPool) -> Option<&'a mut [u8]> {
+        if self.is_in_flight() || !Rc::ptr_eq(&self.state.free_list, &pool.free_list) {
+            return None;
+        }
+        Some(&mut pool.inner.mmap[self.state.offset..][..self.len])
+    }
+
+    /// Attach the buffer to a surface
+    ///
+    /// The buffer is in flight until the compositor releases it, which happens after the
+    /// surface is committed and the compositor is done reading it.
+    pub fn attach_to(&self, surface: &wl_surface::WlSurface) {
+        self.state.in_flight.set(true);
+        surface.attach(Some(&self.buffer), 0, 0);
+    }
+
+    /// Attach the buffer to a surface and damage the changed parts of it
+    ///
+    /// This is [`attach_with_damage`](../../fn.attach_with_damage.html) for this buffer, which
+    /// is in flight until the compositor releases it, like with
+    /// [`attach_to`](#method.attach_to). Returns the number of damage requests sent.
+    pub fn attach_with_damage_to(
+        &self,
+        surface: &wl_surface::WlSurface,
+        scale: i32,
+        damage: &[crate::DamageRect],
+        max_rects: usize,
+    ) -> usize {
+        self.state.in_flight.set(true);
+        crate::attach_with_damage(surface, &self.buffer, self.size, scale, damage, max_rects)
+    }
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        if self.state.in_flight.get() {
+            // the slot is released along with the buffer
+            self.state.dropped.set(true);
+        } else {
+            self.buffer.destroy();
+            self.state.release_slot();
+        }
+    }
+}
+
+impl fmt::Debug for Buffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Buffer")
+            .field("buffer", &self.buffer)
+            .field("size", &self.size)
+            .field("offset", &self.state.offset)
+            .field("len", &self.len)
+            .field("in_flight", &self.state.in_flight.get())
+            .finish()
+    }
+}
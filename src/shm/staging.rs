@@ -0,0 +1,284 @@
+//! A memory pool copying a staging image into its buffers
+//!
+//! With the [`StagingPool`](struct.StagingPool.html), you draw into a CPU-side staging image
+//! that persists from one frame to the next, and declare the regions you changed. The pool
+//! keeps the buffers released by the compositor around, and only copies into them the rows
+//! that changed since they were last filled.
+
+use std::{
+    cell::{Cell, RefCell},
+    io,
+    rc::Rc,
+};
+
+use wayland_client::{
+    protocol::{wl_buffer, wl_shm},
+    Attached,
+};
+
+use super::mempool::{alloc, free, Inner};
+use super::{DefaultBackend, PoolBackend};
+use crate::DamageRect;
+
+// (width, height, stride, format)
+type Params = (i32, i32, i32, wl_shm::Format);
+
+#[derive(Debug)]
+struct Frame {
+    offset: usize,
+    alloc_len: usize,
+    buffer: wl_buffer::WlBuffer,
+    released: Rc<Cell<bool>>,
+    // rows of the staging image changed since the buffer was filled, as (start, end) ranges
+    stale_rows: Vec<(usize, usize)>,
+}
+
+/// A memory pool copying a staging image into its buffers
+///
+/// Draw into the staging image returned by [`canvas`](#method.canvas), declare the changed
+/// regions with [`damage`](#method.damage), then get a buffer holding the image with
+/// [`buffer`](#method.buffer). Up to three buffers are kept and reused once released, so
+/// that only the rows damaged since a buffer was last filled are copied into it.
+///
+/// The memory of the pool is allocated in the smallest free segment fitting each buffer, and
+/// the memory of the buffers that are not reused anymore, for example after a resize, is
+/// merged back with its free neighbours once the compositor released them.
+///
+/// StagingPool handles the destruction of the buffers, the `destroy()` method should not be
+/// used on the buffers it returns.
+#[derive(Debug)]
+pub struct StagingPool {
+    inner: Inner,
+    free_list: Rc<RefCell<Vec<(usize, usize)>>>,
+    staging: Vec<u8>,
+    staging_stride: i32,
+    params: Option<Params>,
+    frames: Vec<Frame>,
+    // frames which are not reused anymore, and are freed once released
+    retired: Vec<Frame>,
+}
+
+// the number of buffers kept for reuse
+const MAX_FRAMES: usize = 3;
+
+impl StagingPool {
+    /// Create a new staging pool associated with the given shm
+    pub fn new(shm: Attached<wl_shm::WlShm>) -> io::Result<StagingPool> {
+        Self::with_backend(shm, DefaultBackend)
+    }
+
+    /// Create a new staging pool associated with the given shm, using a custom backend
+    ///
+    /// See [`PoolBackend`](../trait.PoolBackend.html) for details.
+    pub fn with_backend<B>(shm: Attached<wl_shm::WlShm>, backend: B) -> io::Result<StagingPool>
+    where
+        B: PoolBackend + 'static,
+    {
+        let inner = Inner::new(shm, Box::new(backend))?;
+        let free_list = Rc::new(RefCell::new(vec![(0, inner.len)]));
+        Ok(StagingPool {
+            inner,
+            free_list,
+            staging: Vec::new(),
+            staging_stride: 0,
+            params: None,
+            frames: Vec::new(),
+            retired: Vec::new(),
+        })
+    }
+
+    /// Resize the memory pool
+    ///
+    /// This is normally done automatically, but can be used to avoid multiple resizes.
+    pub fn resize(&mut self, new_size: usize) -> io::Result<()> {
+        let old_size = self.inner.len;
+        if old_size >= new_size {
+            return Ok(());
+        }
+        self.inner.resize(new_size)?;
+        free(&self.free_list, old_size, new_size - old_size);
+        Ok(())
+    }
+
+    /// Access the staging image
+    ///
+    /// The image holds `height` rows of `stride` bytes, and keeps its content from one frame
+    /// to the next. If its size changes, it is cleared and entirely damaged.
+    pub fn canvas(&mut self, height: i32, stride: i32) -> &mut [u8] {
+        let len = (height.max(0) as usize) * (stride.max(0) as usize);
+        if self.staging.len() != len || self.staging_stride != stride {
+            self.staging = vec![0; len];
+            self.staging_stride = stride;
+            self.retire_all();
+        }
+        &mut self.staging
+    }
+
+    /// Declare a region of the staging image as changed
+    ///
+    /// The rectangle is in buffer pixels, the rows it covers will be copied again into the
+    /// buffers filled before this call.
+    pub fn damage(&mut self, rect: DamageRect) {
+        let (_, y, _, h) = rect;
+        if h <= 0 {
+            return;
+        }
+        let (start, end) = (y.max(0) as usize, y.saturating_add(h).max(0) as usize);
+        for frame in &mut self.frames {
+            add_rows(&mut frame.stale_rows, start, end);
+        }
+    }
+
+    /// Get a buffer holding the staging image
+    ///
+    /// The parameters are the same as for
+    /// [`AutoMemPool::buffer`](../struct.AutoMemPool.html#method.buffer), and describe the
+    /// staging image. If a buffer with the same parameters was released by the compositor,
+    /// it is reused and only the rows damaged since it was last filled are copied into it.
+    ///
+    /// Returns an error if the staging image is too small for these parameters.
+    pub fn buffer(
+        &mut self,
+        width: i32,
+        height: i32,
+        stride: i32,
+        format: wl_shm::Format,
+    ) -> io::Result<wl_buffer::WlBuffer> {
+        crate::strict::check_buffer(0, width, height, stride, format, None)?;
+        let len = (height as usize) * (stride as usize);
+        if self.staging.len() < len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the staging image is smaller than the buffer",
+            ));
+        }
+        let params = (width, height, stride, format);
+        if self.params != Some(params) {
+            self.retire_all();
+            self.params = Some(params);
+        }
+        self.purge();
+
+        let stride = stride as usize;
+        let idx = match self.frames.iter().position(|frame| frame.released.get()) {
+            Some(idx) => idx,
+            None => {
+                if self.frames.len() >= MAX_FRAMES {
+                    // all kept buffers are in use, stop reusing the oldest one
+                    let frame = self.frames.remove(0);
+                    self.retired.push(frame);
+                }
+                let alloc_len = (len + 63) & !63;
+                let offset = alloc(&mut self.inner, &self.free_list, alloc_len)?;
+                let released = Rc::new(Cell::new(true));
+                let buffer = self.inner.pool.create_buffer(
+                    offset as i32,
+                    width,
+                    height,
+                    stride as i32,
+                    format,
+                );
+                let my_released = released.clone();
+                buffer.quick_assign(move |_, event, _| match event {
+                    wl_buffer::Event::Release => my_released.set(true),
+                    _ => unreachable!(),
+                });
+                self.frames.push(Frame {
+                    offset,
+                    alloc_len,
+                    buffer: buffer.detach(),
+                    released,
+                    stale_rows: vec![(0, height as usize)],
+                });
+                self.frames.len() - 1
+            }
+        };
+
+        let frame = &mut self.frames[idx];
+        for (start, end) in frame.stale_rows.drain(..) {
+            let end = end.min(height as usize);
+            if start < end {
+                let range = start * stride..end * stride;
+                self.inner.mmap[frame.offset..][range.clone()]
+                    .copy_from_slice(&self.staging[range]);
+            }
+        }
+        frame.released.set(false);
+        Ok(frame.buffer.clone())
+    }
+
+    fn retire_all(&mut self) {
+        self.retired.append(&mut self.frames);
+    }
+
+    // free the retired frames released by the compositor
+    fn purge(&mut self) {
+        let free_list = &self.free_list;
+        self.retired.retain(|frame| {
+            if frame.released.get() {
+                frame.buffer.destroy();
+                free(free_list, frame.offset, frame.alloc_len);
+                false
+            } else {
+                true
+            }
+        });
+    }
+}
+
+impl Drop for StagingPool {
+    fn drop(&mut self) {
+        for frame in self.frames.iter().chain(&self.retired) {
+            frame.buffer.destroy();
+        }
+    }
+}
+
+// Add a range of rows to a sorted list of disjoint ranges, merging them as needed
+fn add_rows(rows: &mut Vec<(usize, usize)>, mut start: usize, mut end: usize) {
+    rows.retain(|&(s, e)| {
+        if s <= end && e >= start {
+            start = start.min(s);
+            end = end.max(e);
+            false
+        } else {
+            true
+        }
+    });
+    let idx = rows.iter().position(|&(s, _)| s > start).unwrap_or(rows.len());
+    rows.insert(idx, (start, end));
+}
+
+impl<E> crate::environment::Environment<E>
+where
+    E: crate::environment::GlobalHandler<wl_shm::WlShm>,
+{
+    /// Create a staging memory pool
+    ///
+    /// This pool copies a staging image into its buffers, only copying the damaged rows into
+    /// the buffers it reuses. See [`StagingPool`](../shm/staging/struct.StagingPool.html).
+    pub fn create_staging_pool(&self) -> io::Result<StagingPool> {
+        StagingPool::new(self.require_global::<wl_shm::WlShm>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_rows_merges_ranges() {
+        let mut rows = Vec::new();
+        add_rows(&mut rows, 5, 10);
+        add_rows(&mut rows, 0, 2);
+        assert_eq!(rows, vec![(0, 2), (5, 10)]);
+        // touching ranges are merged
+        add_rows(&mut rows, 10, 12);
+        assert_eq!(rows, vec![(0, 2), (5, 12)]);
+        add_rows(&mut rows, 20, 30);
+        add_rows(&mut rows, 14, 16);
+        assert_eq!(rows, vec![(0, 2), (5, 12), (14, 16), (20, 30)]);
+        add_rows(&mut rows, 2, 14);
+        assert_eq!(rows, vec![(0, 16), (20, 30)]);
+    }
+}
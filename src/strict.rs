@@ -0,0 +1,64 @@
+//! Client-side validation of request preconditions
+//!
+//! With the `strict` cargo feature, SCTK checks some preconditions before sending requests
+//! to the server, and logs a descriptive error when they are not met, rather than letting
+//! the compositor kill the connection with a terse protocol error. When possible, the
+//! request is then not sent, and an error is returned.
+//!
+//! Without the feature, no check is done.
+
+use std::io;
+
+use wayland_client::protocol::wl_shm;
+
+/// Check a precondition of a request
+///
+/// Returns `false` and logs an error if the check failed. Always returns `true` when
+/// the `strict` feature is disabled.
+pub(crate) fn check<F: FnOnce() -> String>(request: &str, ok: bool, reason: F) -> bool {
+    if cfg!(feature = "strict") && !ok {
+        log::error!("[SCTK] Invalid {} request: {}", request, reason());
+        return false;
+    }
+    true
+}
+
+/// Check the parameters of a `wl_shm_pool.create_buffer` request
+///
+/// The bounds of the buffer are only checked if the size of the pool is provided.
+pub(crate) fn check_buffer(
+    offset: i32,
+    width: i32,
+    height: i32,
+    stride: i32,
+    format: wl_shm::Format,
+    pool_size: Option<usize>,
+) -> io::Result<()> {
+    if !cfg!(feature = "strict") {
+        return Ok(());
+    }
+    let request = "wl_shm_pool.create_buffer";
+    let mut valid =
+        check(request, width > 0 && height > 0, || {
+            format!("buffer dimensions must be positive, got {}x{}", width, height)
+        }) && check(request, crate::shm::check_stride(format, width, stride).is_ok(), || {
+            format!("stride {} is too small for {} pixels of format {:?}", stride, width, format)
+        }) && check(request, offset >= 0, || format!("negative offset {}", offset));
+    if let (true, Some(pool_size)) = (valid, pool_size) {
+        // an overflow can not fit in the pool either
+        let end = (stride as usize)
+            .checked_mul(height as usize)
+            .and_then(|len| len.checked_add(offset as usize));
+        valid = check(request, end.map_or(false, |end| end <= pool_size), || {
+            format!(
+                "buffer of {} rows of {} bytes at offset {} does not fit in the pool of {} bytes",
+                height, stride, offset, pool_size
+            )
+        });
+    }
+    if valid {
+        Ok(())
+    } else {
+        Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid buffer parameters"))
+    }
+}
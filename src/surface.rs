@@ -1,7 +1,7 @@
-use std::{cell::RefCell, rc::Rc, sync::Mutex};
+use std::{cell::RefCell, fmt, rc::Rc, sync::Mutex};
 
 use wayland_client::{
-    protocol::{wl_compositor, wl_output, wl_surface},
+    protocol::{wl_buffer, wl_compositor, wl_display, wl_output, wl_subsurface, wl_surface},
     Attached, DispatchData, Main,
 };
 
@@ -109,6 +109,8 @@ where
             wl_surface::Event::Leave { output } => {
                 user_data.leave(&output);
             }
+            // `preferred_buffer_scale` and `preferred_buffer_transform` only exist since
+            // wl_surface version 6, which cannot be bound with wayland-client 0.29
             _ => unreachable!(),
         };
         let old_scale_factor = user_data.scale_factor;
@@ -133,9 +135,14 @@ impl<E: crate::environment::GlobalHandler<wl_compositor::WlCompositor>>
     /// optimal scale factor for these. You can access them using
     /// [`get_surface_scale_factor`](../fn.get_surface_scale_factor.html) and
     /// [`get_surface_outputs`](../fn.get_surface_outputs.html).
+    ///
+    /// The surface is tracked by the environment until destroyed, see
+    /// [`surfaces`](#method.surfaces).
     pub fn create_surface(&self) -> Attached<wl_surface::WlSurface> {
         let compositor = self.require_global::<wl_compositor::WlCompositor>();
-        setup_surface(compositor.create_surface(), None::<fn(_, _, DispatchData)>)
+        let surface = setup_surface(compositor.create_surface(), None::<fn(_, _, DispatchData)>);
+        self.track_surface(&surface);
+        surface
     }
 
     /// Create a DPI-aware surface with callbacks
@@ -150,12 +157,56 @@ impl<E: crate::environment::GlobalHandler<wl_compositor::WlCompositor>>
         f: F,
     ) -> Attached<wl_surface::WlSurface> {
         let compositor = self.require_global::<wl_compositor::WlCompositor>();
-        setup_surface(compositor.create_surface(), Some(f))
+        let surface = setup_surface(compositor.create_surface(), Some(f));
+        self.track_surface(&surface);
+        surface
+    }
+}
+
+impl<E> crate::environment::Environment<E> {
+    /// The live surfaces created through this environment
+    ///
+    /// These are the surfaces created by [`create_surface`](#method.create_surface) and
+    /// [`create_surface_with_scale_callback`](#method.create_surface_with_scale_callback), in
+    /// creation order. Destroyed surfaces are forgotten.
+    ///
+    /// This allows bulk operations on all the surfaces of the application, like setting a new
+    /// buffer scale after a change of the scale preference, or destroying the remaining
+    /// surfaces on shutdown. Surfaces with a role, like the ones of windows, must only be
+    /// destroyed after their role object.
+    pub fn surfaces(&self) -> Vec<wl_surface::WlSurface> {
+        let mut surfaces = self.surfaces.borrow_mut();
+        surfaces.retain(|s| s.as_ref().is_alive());
+        surfaces.clone()
+    }
+
+    /// Log the live surfaces created through this environment
+    ///
+    /// This is meant as a leak diagnostic, for example right before exiting after all the
+    /// surfaces should have been destroyed. Each of them is logged as a warning, returns how
+    /// many there were.
+    pub fn log_live_surfaces(&self) -> usize {
+        let surfaces = self.surfaces();
+        for surface in &surfaces {
+            log::warn!("[SCTK] Surface {} was never destroyed", surface.as_ref().id());
+        }
+        surfaces.len()
+    }
+
+    fn track_surface(&self, surface: &wl_surface::WlSurface) {
+        let mut surfaces = self.surfaces.borrow_mut();
+        surfaces.retain(|s| s.as_ref().is_alive());
+        surfaces.push(surface.clone());
     }
 }
 
 /// Returns the current suggested scale factor of a surface.
 ///
+/// This is the highest scale factor of the outputs the surface is displayed on. The
+/// `preferred_buffer_scale` and `preferred_buffer_transform` hints sent directly by the
+/// compositor require `wl_compositor` version 6, which is not part of the protocol version
+/// supported by `wayland-client` 0.29, so they are not taken into account.
+///
 /// Panics if the surface was not created using `Environment::create_surface` or
 /// `Environment::create_surface_with_dpi_callback`.
 pub fn get_surface_scale_factor(surface: &wl_surface::WlSurface) -> i32 {
@@ -169,6 +220,16 @@ pub fn get_surface_scale_factor(surface: &wl_surface::WlSurface) -> i32 {
         .scale_factor
 }
 
+// Like `get_surface_scale_factor`, but returns `None` instead of panicking if the
+// surface was not created by SCTK
+pub(crate) fn try_surface_scale_factor(surface: &wl_surface::WlSurface) -> Option<i32> {
+    surface
+        .as_ref()
+        .user_data()
+        .get::<Mutex<SurfaceUserData>>()
+        .map(|data| data.lock().unwrap().scale_factor)
+}
+
 /// Returns a list of outputs the surface is displayed on.
 ///
 /// Panics if the surface was not created using `Environment::create_surface` or
@@ -186,3 +247,86 @@ pub fn get_surface_outputs(surface: &wl_surface::WlSurface) -> Vec<wl_output::Wl
         .map(|(ref output, _, _)| output.clone())
         .collect()
 }
+
+/// A protocol object that can be destroyed by a `DeferredDestroy` queue
+pub trait Destroyable: fmt::Debug {
+    /// Destroy the object
+    fn destroy(&self);
+}
+
+impl Destroyable for wl_buffer::WlBuffer {
+    fn destroy(&self) {
+        wl_buffer::WlBuffer::destroy(self);
+    }
+}
+
+impl Destroyable for wl_subsurface::WlSubsurface {
+    fn destroy(&self) {
+        wl_subsurface::WlSubsurface::destroy(self);
+    }
+}
+
+impl Destroyable for wl_surface::WlSurface {
+    fn destroy(&self) {
+        wl_surface::WlSurface::destroy(self);
+    }
+}
+
+/// A queue of protocol objects to be destroyed later
+///
+/// Destroying a buffer or a subsurface while the compositor may still be presenting
+/// it can cause visual glitches. Objects pushed into this queue are instead destroyed
+/// once the compositor is done with them: either after the next frame callback of their
+/// surface, or after the server processed all previously sent requests.
+#[derive(Debug, Default)]
+pub struct DeferredDestroy {
+    objects: Vec<Box<dyn Destroyable>>,
+}
+
+impl DeferredDestroy {
+    /// Create a new empty queue
+    pub fn new() -> DeferredDestroy {
+        DeferredDestroy { objects: Vec::new() }
+    }
+
+    /// Add an object to the queue
+    pub fn push<D: Destroyable + 'static>(&mut self, object: D) {
+        self.objects.push(Box::new(object));
+    }
+
+    /// Destroy the queued objects after the next frame callback of given surface
+    ///
+    /// This requests a frame callback, which will only be sent by the compositor after
+    /// the next commit of the surface, so you need to commit it for the objects to be
+    /// destroyed. If the surface is destroyed before, the objects are never destroyed.
+    pub fn destroy_after_frame(self, surface: &wl_surface::WlSurface) {
+        let mut objects = Some(self.objects);
+        surface.frame().quick_assign(move |_, _, _| {
+            for object in objects.take().into_iter().flatten() {
+                object.destroy();
+            }
+        });
+    }
+
+    /// Destroy the queued objects once the server processed all previously sent requests
+    ///
+    /// See [`environment::barrier`](environment/fn.barrier.html).
+    pub fn destroy_after_sync(self, display: &Attached<wl_display::WlDisplay>) {
+        let objects = self.objects;
+        crate::environment::barrier(display, move |_| {
+            for object in objects {
+                object.destroy();
+            }
+        });
+    }
+}
+
+/// Destroy a buffer after the next frame callback of the surface it was attached to
+///
+/// This is a shorthand for a [`DeferredDestroy`](struct.DeferredDestroy.html) queue
+/// containing only this buffer.
+pub fn defer_destroy(buffer: wl_buffer::WlBuffer, surface: &wl_surface::WlSurface) {
+    let mut queue = DeferredDestroy::new();
+    queue.push(buffer);
+    queue.destroy_after_frame(surface);
+}
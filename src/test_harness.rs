@@ -0,0 +1,247 @@
+//! Helpers to test clients against a real compositor
+//!
+//! This module is only available with the `test-harness` cargo feature. It spawns a headless
+//! Weston or Sway, if one of them is installed, on its own socket so that tests can connect
+//! any number of clients to it and drive them end to end:
+//!
+//! ```no_run
+//! use smithay_client_toolkit::test_harness::{dispatch_until, HeadlessCompositor};
+//! # use std::time::Duration;
+//!
+//! let compositor = match HeadlessCompositor::spawn().unwrap() {
+//!     Some(compositor) => compositor,
+//!     // no compositor is installed, skip the test
+//!     None => return,
+//! };
+//! let display = compositor.connect().unwrap();
+//! let mut queue = display.create_event_queue();
+//! // ... create surfaces and windows
+//! let configured = std::cell::Cell::new(false);
+//! assert!(dispatch_until(&mut queue, Duration::from_secs(5), || configured.get()).unwrap());
+//! ```
+//!
+//! The compositors have no input devices, so the features needing the serial of an input
+//! event, like setting the selection, cannot be tested this way.
+
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+use nix::{
+    errno::Errno,
+    poll::{poll, PollFd, PollFlags},
+};
+
+use wayland_client::{ConnectError, Display, EventQueue};
+
+/// The compositors a [`HeadlessCompositor`](struct.HeadlessCompositor.html) can run
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CompositorKind {
+    /// Weston, with its headless backend
+    Weston,
+    /// Sway, with the headless backend of wlroots
+    Sway,
+}
+
+/// A headless compositor running in a child process
+///
+/// The process is killed when this value is dropped.
+#[derive(Debug)]
+pub struct HeadlessCompositor {
+    child: Child,
+    kind: CompositorKind,
+    socket: String,
+}
+
+// the time given to the compositor to create its socket
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+lazy_static::lazy_static! {
+    // Sway is found by the socket it adds to the runtime directory, so two of them must not
+    // start at the same time
+    static ref SOCKET_DISCOVERY: Mutex<()> = Mutex::new(());
+}
+
+impl HeadlessCompositor {
+    /// Spawn the first of Weston or Sway that is installed
+    ///
+    /// Returns `None` if neither of them is installed or if `XDG_RUNTIME_DIR` is not set, so
+    /// that tests can be skipped on systems without a compositor.
+    pub fn spawn() -> io::Result<Option<HeadlessCompositor>> {
+        for &kind in &[CompositorKind::Weston, CompositorKind::Sway] {
+            if let Some(compositor) = Self::spawn_kind(kind)? {
+                return Ok(Some(compositor));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Spawn a given compositor
+    ///
+    /// Returns `None` if it is not installed or if `XDG_RUNTIME_DIR` is not set, and an error
+    /// if it failed to start.
+    ///
+    /// Sway can not be given the name of its socket, which is instead found by looking for
+    /// the new socket of the runtime directory. The Sway instances spawned by a process start
+    /// one after the other for this, but other processes starting compositors in the same
+    /// runtime directory at the same time may still confuse it.
+    pub fn spawn_kind(kind: CompositorKind) -> io::Result<Option<HeadlessCompositor>> {
+        // clients could not connect to the compositor without a runtime directory
+        let runtime_dir = match env::var_os("XDG_RUNTIME_DIR") {
+            Some(dir) => PathBuf::from(dir),
+            None => return Ok(None),
+        };
+
+        let mut command = match kind {
+            CompositorKind::Weston => Command::new("weston"),
+            CompositorKind::Sway => Command::new("sway"),
+        };
+        let socket = match kind {
+            CompositorKind::Weston => {
+                let socket = format!("sctk-test-{}-{}", std::process::id(), unique_suffix());
+                command.arg("--backend=headless-backend.so").arg(format!("--socket={}", socket));
+                Some(socket)
+            }
+            CompositorKind::Sway => {
+                // sway picks the name of its socket itself
+                command
+                    .arg("--config")
+                    .arg("/dev/null")
+                    .env("WLR_BACKENDS", "headless")
+                    .env("WLR_LIBINPUT_NO_DEVICES", "1");
+                None
+            }
+        };
+        command.env_remove("WAYLAND_DISPLAY").env_remove("DISPLAY");
+        command.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null());
+
+        // a poisoned lock only means another spawn panicked
+        let _discovery = match socket {
+            Some(_) => None,
+            None => Some(SOCKET_DISCOVERY.lock().unwrap_or_else(|e| e.into_inner())),
+        };
+        let existing = list_sockets(&runtime_dir);
+        let mut child = match command.spawn() {
+            Ok(child) => child,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        let start = Instant::now();
+        loop {
+            let found = match socket {
+                Some(ref socket) if runtime_dir.join(socket).exists() => Some(socket.clone()),
+                Some(_) => None,
+                None => list_sockets(&runtime_dir).into_iter().find(|s| !existing.contains(s)),
+            };
+            if let Some(socket) = found {
+                return Ok(Some(HeadlessCompositor { child, kind, socket }));
+            }
+            if let Some(status) = child.try_wait()? {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotConnected,
+                    format!("the compositor exited during startup: {}", status),
+                ));
+            }
+            if start.elapsed() > STARTUP_TIMEOUT {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "the compositor did not create its socket",
+                ));
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// The compositor that is running
+    pub fn kind(&self) -> CompositorKind {
+        self.kind
+    }
+
+    /// The name of the socket of the compositor, to use as `WAYLAND_DISPLAY`
+    pub fn socket_name(&self) -> &str {
+        &self.socket
+    }
+
+    /// Connect a new client to the compositor
+    ///
+    /// This can be called several times, to test the interactions between clients.
+    pub fn connect(&self) -> Result<Display, ConnectError> {
+        Display::connect_to_name(&self.socket)
+    }
+}
+
+impl Drop for HeadlessCompositor {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Dispatch an event queue until a condition holds
+///
+/// The queue is dispatched, waiting for events from the compositor, until `condition`
+/// returns `true` or `timeout` elapses. Returns whether the condition was met.
+pub fn dispatch_until<F>(
+    queue: &mut EventQueue,
+    timeout: Duration,
+    mut condition: F,
+) -> io::Result<bool>
+where
+    F: FnMut() -> bool,
+{
+    let start = Instant::now();
+    while !condition() {
+        if start.elapsed() > timeout {
+            return Ok(false);
+        }
+        if let Err(e) = queue.display().flush() {
+            if e.kind() != io::ErrorKind::WouldBlock {
+                return Err(e);
+            }
+        }
+        let fd = queue.display().get_connection_fd();
+        if let Some(guard) = queue.prepare_read() {
+            // wait with a timeout, so that a compositor that stopped answering is noticed
+            let mut fds = [PollFd::new(fd, PollFlags::POLLIN)];
+            let remaining = timeout.checked_sub(start.elapsed()).unwrap_or_default();
+            match poll(&mut fds, remaining.as_millis() as i32) {
+                Ok(0) => continue,
+                Ok(_) => {
+                    if let Err(e) = guard.read_events() {
+                        if e.kind() != io::ErrorKind::WouldBlock {
+                            return Err(e);
+                        }
+                    }
+                }
+                Err(Errno::EINTR) => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        queue.dispatch_pending(&mut (), |_, _, _| {})?;
+    }
+    Ok(true)
+}
+
+fn list_sockets(dir: &Path) -> Vec<String> {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+                .filter(|name| name.starts_with("wayland-") && !name.ends_with(".lock"))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn unique_suffix() -> u32 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.subsec_nanos()).unwrap_or(0)
+}
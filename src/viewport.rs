@@ -0,0 +1,172 @@
+//! Cropping and scaling of surfaces with `wp_viewporter`
+//!
+//! A viewport lets the compositor crop and scale the contents of a surface: only a source
+//! rectangle of the buffer is shown, stretched to a destination size. This allows zooming
+//! and panning over a large buffer, like a picture in an image viewer, without re-rendering it.
+//!
+//! The `wp_viewporter` global is not part of the
+//! [`default_environment!`](../macro.default_environment.html), you can add it to your
+//! environment as a [`SimpleGlobal`](../environment/struct.SimpleGlobal.html).
+
+use std::fmt;
+
+use wayland_client::{
+    protocol::{wl_output::Transform, wl_surface},
+    Attached,
+};
+
+use wayland_protocols::viewporter::client::wp_viewport::WpViewport;
+pub use wayland_protocols::viewporter::client::wp_viewporter::WpViewporter;
+
+use crate::environment::{Environment, GlobalHandler};
+use crate::MissingGlobal;
+
+/// An invalid source rectangle was given to a viewport
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct InvalidSourceRect {
+    /// The rejected rectangle, as `(x, y, width, height)`
+    pub rect: (f64, f64, f64, f64),
+}
+
+impl std::error::Error for InvalidSourceRect {}
+
+impl fmt::Display for InvalidSourceRect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (x, y, w, h) = self.rect;
+        write!(f, "invalid viewport source rectangle {}x{} at ({}, {})", w, h, x, y)
+    }
+}
+
+/// A viewport cropping and scaling a surface
+///
+/// All the changes are applied on the next commit of the surface. The viewport is
+/// destroyed when dropped, which unsets the source rectangle and destination size.
+#[derive(Debug)]
+pub struct Viewport {
+    viewport: WpViewport,
+    buffer_size: Option<(f64, f64)>,
+    has_destination: bool,
+}
+
+impl Viewport {
+    /// Create the viewport of a surface
+    ///
+    /// A surface can only have one viewport at a time.
+    pub fn new(viewporter: &Attached<WpViewporter>, surface: &wl_surface::WlSurface) -> Viewport {
+        Viewport {
+            viewport: viewporter.get_viewport(surface).detach(),
+            buffer_size: None,
+            has_destination: false,
+        }
+    }
+
+    /// Set the size of the buffers attached to the surface
+    ///
+    /// This is the size of the buffers in pixels, along with the buffer scale and buffer
+    /// transform of the surface. Once it is known, source rectangles are checked to fit in the
+    /// buffers, as a source rectangle extending outside of the buffer is a protocol error.
+    pub fn set_buffer_size(&mut self, width: i32, height: i32, scale: i32, transform: Transform) {
+        let scale = f64::from(scale.max(1));
+        let (width, height) = (f64::from(width) / scale, f64::from(height) / scale);
+        // the source rectangle is in surface coordinates, after the buffer transform
+        self.buffer_size = Some(match transform {
+            Transform::_90 | Transform::_270 | Transform::Flipped90 | Transform::Flipped270 => {
+                (height, width)
+            }
+            _ => (width, height),
+        });
+    }
+
+    /// Show buffers rendered at a given scale with a given logical size
+    ///
+    /// This is meant for scale factors that are not integers: the buffers are attached with a
+    /// buffer scale of 1, and stretched back to the logical size. This unsets the source
+    /// rectangle, sets the destination size, and returns the size in pixels the buffers must
+    /// have.
+    ///
+    /// `scale` is the scale factor of the surface, the zoom of a window is not applied to the
+    /// buffers: see [`Window::set_zoom`](../window/struct.Window.html#method.set_zoom).
+    pub fn set_scaled_buffer(&mut self, logical_size: (u32, u32), scale: f64) -> (i32, i32) {
+        let (width, height) = logical_size;
+        let scale = if scale.is_finite() && scale > 0.0 { scale } else { 1.0 };
+        let pixels = |len: u32| ((f64::from(len) * scale).ceil() as i32).max(1);
+        let buffer_size = (pixels(width), pixels(height));
+        self.set_buffer_size(buffer_size.0, buffer_size.1, 1, Transform::Normal);
+        self.unset_source_rect();
+        self.set_destination(width, height);
+        buffer_size
+    }
+
+    /// Set the rectangle of the buffer to display
+    ///
+    /// The rectangle is expressed in surface coordinates, that is in buffer pixels divided
+    /// by the buffer scale. Its size must be positive, and if the size of the buffers was set
+    /// with [`set_buffer_size`](#method.set_buffer_size) it must fit in them.
+    ///
+    /// If no destination size is set, the surface takes the size of the rectangle, which must
+    /// then be a whole number of pixels: set the destination first to use a fractional size.
+    /// Invalid rectangles are not sent to the server and an error is returned.
+    pub fn set_source_rect(
+        &self,
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+    ) -> Result<(), InvalidSourceRect> {
+        let fits = match self.buffer_size {
+            Some((buf_w, buf_h)) => x + width <= buf_w && y + height <= buf_h,
+            None => true,
+        };
+        let whole = width.fract() == 0.0 && height.fract() == 0.0;
+        if !(x >= 0.0 && y >= 0.0 && width > 0.0 && height > 0.0 && fits)
+            || !(whole || self.has_destination)
+        {
+            return Err(InvalidSourceRect { rect: (x, y, width, height) });
+        }
+        self.viewport.set_source(x, y, width, height);
+        Ok(())
+    }
+
+    /// Show the whole buffer again
+    pub fn unset_source_rect(&self) {
+        self.viewport.set_source(-1.0, -1.0, -1.0, -1.0);
+    }
+
+    /// Set the size the surface is stretched to, in logical pixels
+    ///
+    /// Sizes of `0` are ignored.
+    pub fn set_destination(&mut self, width: u32, height: u32) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        self.viewport.set_destination(width as i32, height as i32);
+        self.has_destination = true;
+    }
+
+    /// Stop stretching the surface
+    ///
+    /// The source rectangle, if any, must then have a whole number of pixels.
+    pub fn unset_destination(&mut self) {
+        self.viewport.set_destination(-1, -1);
+        self.has_destination = false;
+    }
+}
+
+impl Drop for Viewport {
+    fn drop(&mut self) {
+        self.viewport.destroy();
+    }
+}
+
+impl<E: GlobalHandler<WpViewporter>> Environment<E> {
+    /// Create the viewport of a surface
+    ///
+    /// Returns an error if the compositor does not support `wp_viewporter`.
+    pub fn create_viewport(
+        &self,
+        surface: &wl_surface::WlSurface,
+    ) -> Result<Viewport, MissingGlobal> {
+        let viewporter = self.get_global::<WpViewporter>().ok_or(MissingGlobal)?;
+        Ok(Viewport::new(&viewporter, surface))
+    }
+}
@@ -0,0 +1,122 @@
+use std::{
+    cell::RefCell,
+    fmt,
+    rc::{Rc, Weak},
+    time::{Duration, Instant},
+};
+
+use wayland_client::{protocol::wl_keyboard::KeyState, DispatchData};
+
+use super::{Event, WindowInner};
+use crate::seat::keyboard::{self, ModifiersState};
+
+/// A debugging escape hatch closing a window when a chord is held
+///
+/// Applications combining fullscreen, pointer locks and shortcut inhibitors can leave their
+/// developer with no way to quit them when they misbehave. Feed this helper the events of
+/// the keyboards, and once the key of the chord was held with its modifiers for the hold
+/// duration, the window receives an [`Event::Close`](enum.Event.html), as if the user closed
+/// it.
+///
+/// The duration is checked on every event, including the key repetitions, and by
+/// [`poll`](#method.poll), which should be called regularly if key repetition is disabled.
+/// Both take the `DispatchData` given to the callback of the window along with the
+/// `Event::Close`, which should be the one your window callback expects.
+/// It is created by [`Window::escape_chord`](struct.Window.html#method.escape_chord), and
+/// only available with the `debug-escape` cargo feature.
+pub struct EscapeChord<F> {
+    inner: Weak<RefCell<Option<WindowInner<F>>>>,
+    keysym: u32,
+    modifiers: ModifiersState,
+    hold: Duration,
+    current: ModifiersState,
+    // when the chord was pressed, reset once it fired
+    pressed: Option<Instant>,
+}
+
+impl<F> EscapeChord<F> {
+    pub(super) fn new(
+        inner: &Rc<RefCell<Option<WindowInner<F>>>>,
+        keysym: u32,
+        modifiers: ModifiersState,
+        hold: Duration,
+    ) -> EscapeChord<F> {
+        EscapeChord {
+            inner: Rc::downgrade(inner),
+            keysym,
+            modifiers,
+            hold,
+            current: ModifiersState::default(),
+            pressed: None,
+        }
+    }
+
+    /// Process a keyboard event
+    ///
+    /// `ddata` is usually the dispatch data given to the keyboard callback. Returns whether the
+    /// window was closed.
+    pub fn handle_event(&mut self, event: &keyboard::Event, ddata: DispatchData) -> bool {
+        match *event {
+            keyboard::Event::Modifiers { modifiers } => {
+                self.current = modifiers;
+                if !self.modifiers_match() {
+                    self.pressed = None;
+                }
+            }
+            keyboard::Event::Leave { .. } => {
+                self.current = ModifiersState::default();
+                self.pressed = None;
+            }
+            keyboard::Event::Key { keysym, state, .. } if keysym == self.keysym => {
+                self.pressed = match state {
+                    KeyState::Pressed if self.modifiers_match() => Some(Instant::now()),
+                    _ => None,
+                };
+            }
+            _ => {}
+        }
+        self.check(ddata)
+    }
+
+    /// Check whether the chord was held long enough
+    ///
+    /// Returns the time left before the window is closed, if the chord is being held.
+    pub fn poll(&mut self, ddata: DispatchData) -> Option<Duration> {
+        self.check(ddata);
+        self.pressed.map(|pressed| self.hold.checked_sub(pressed.elapsed()).unwrap_or_default())
+    }
+
+    fn check(&mut self, ddata: DispatchData) -> bool {
+        match self.pressed {
+            Some(pressed) if pressed.elapsed() >= self.hold => {
+                self.pressed = None;
+                let inner = match self.inner.upgrade() {
+                    Some(inner) => inner,
+                    None => return false,
+                };
+                log::warn!("[SCTK] Escape chord held, closing the window");
+                if let Some(ref mut inner) = *inner.borrow_mut() {
+                    (inner.user_impl)(Event::Close, ddata);
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn modifiers_match(&self) -> bool {
+        let (a, b) = (&self.modifiers, &self.current);
+        a.ctrl == b.ctrl && a.alt == b.alt && a.shift == b.shift && a.logo == b.logo
+    }
+}
+
+impl<F> fmt::Debug for EscapeChord<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EscapeChord")
+            .field("keysym", &self.keysym)
+            .field("modifiers", &self.modifiers)
+            .field("hold", &self.hold)
+            .field("pressed", &self.pressed)
+            .finish()
+    }
+}
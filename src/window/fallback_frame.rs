@@ -9,7 +9,11 @@ use wayland_client::{Attached, DispatchData};
 
 use log::error;
 
-use super::{ButtonState, Frame, FrameRequest, State, WindowState};
+use wayland_protocols::xdg_shell::client::xdg_toplevel::ResizeEdge;
+
+use super::{
+    resize_cursor, ButtonState, Frame, FrameRequest, ResizeEdgeCalculator, State, WindowState,
+};
 use crate::seat::pointer::{ThemeManager, ThemeSpec, ThemedPointer};
 use crate::shm::AutoMemPool;
 
@@ -48,6 +52,8 @@ enum Location {
     BottomLeft,
     Left,
     TopLeft,
+    // A part of the borders from which the window cannot be resized
+    Border,
     Button(UIButton),
 }
 
@@ -106,6 +112,7 @@ impl Drop for Part {
 }
 
 struct PointerUserData {
+    part: Location,
     location: Location,
     position: (f64, f64),
     seat: wl_seat::WlSeat,
@@ -119,6 +126,7 @@ struct Inner {
     parts: Vec<Part>,
     size: (u32, u32),
     resizable: bool,
+    resize_edges: ResizeEdgeCalculator,
     theme_over_surface: bool,
     implem: Box<dyn FnMut(FrameRequest, u32, DispatchData)>,
     maximized: bool,
@@ -153,6 +161,7 @@ impl fmt::Debug for Inner {
             .field("parts", &self.parts)
             .field("size", &self.size)
             .field("resizable", &self.resizable)
+            .field("resize_edges", &self.resize_edges)
             .field("theme_over_surface", &self.theme_over_surface)
             .field("implem", &"FnMut(FrameRequest, u32, DispatchData) -> { ... }")
             .field("maximized", &self.maximized)
@@ -161,31 +170,28 @@ impl fmt::Debug for Inner {
     }
 }
 
-fn precise_location(old: Location, width: u32, x: f64, y: f64) -> Location {
-    match old {
-        Location::Head | Location::Button(_) => find_button(x, y, width),
-
-        Location::Top | Location::TopLeft | Location::TopRight => {
-            if x <= f64::from(BORDER_SIZE) {
-                Location::TopLeft
-            } else if x >= f64::from(width + BORDER_SIZE) {
-                Location::TopRight
-            } else {
-                Location::Top
-            }
-        }
-
-        Location::Bottom | Location::BottomLeft | Location::BottomRight => {
-            if x <= f64::from(BORDER_SIZE) {
-                Location::BottomLeft
-            } else if x >= f64::from(width + BORDER_SIZE) {
-                Location::BottomRight
-            } else {
-                Location::Bottom
-            }
-        }
+fn precise_location(part: Location, inner: &Inner, x: f64, y: f64) -> Location {
+    let (width, height) = inner.size;
+    // Position of the part in the whole window area
+    let (part_x, part_y) = match part {
+        Location::Head => return find_button(x, y, width),
+        Location::Top => (0, 0),
+        Location::Left => (0, BORDER_SIZE),
+        Location::Right => (width + BORDER_SIZE, BORDER_SIZE),
+        Location::Bottom => (0, BORDER_SIZE + HEADER_SIZE + height),
+        other => return other,
+    };
 
-        other => other,
+    match inner.resize_edges.edge(x + f64::from(part_x), y + f64::from(part_y)) {
+        ResizeEdge::Top => Location::Top,
+        ResizeEdge::TopRight => Location::TopRight,
+        ResizeEdge::Right => Location::Right,
+        ResizeEdge::BottomRight => Location::BottomRight,
+        ResizeEdge::Bottom => Location::Bottom,
+        ResizeEdge::BottomLeft => Location::BottomLeft,
+        ResizeEdge::Left => Location::Left,
+        ResizeEdge::TopLeft => Location::TopLeft,
+        _ => Location::Border,
     }
 }
 
@@ -240,6 +246,31 @@ pub struct FallbackFrame {
     pointers: Vec<ThemedPointer>,
     themer: ThemeManager,
     surface_version: u32,
+    drawn: Option<DrawnState>,
+}
+
+/// The state the decorations were last drawn with
+///
+/// It is compared to the current state on redraw, so that only the parts
+/// that changed are redrawn and damaged.
+#[derive(Debug, PartialEq, Eq)]
+struct DrawnState {
+    size: (u32, u32),
+    scales: Vec<u32>,
+    active: WindowState,
+    resizable: bool,
+    // Hover state of the buttons, from right to left
+    hovered: [bool; 3],
+}
+
+impl DrawnState {
+    // Whether only the hover state of the buttons differs
+    fn same_but_hover(&self, other: &DrawnState) -> bool {
+        self.size == other.size
+            && self.scales == other.scales
+            && self.active == other.active
+            && self.resizable == other.resizable
+    }
 }
 
 impl Frame for FallbackFrame {
@@ -263,6 +294,10 @@ impl Frame for FallbackFrame {
             parts: vec![],
             size: (1, 1),
             resizable: true,
+            resize_edges: ResizeEdgeCalculator::new(
+                (1 + 2 * BORDER_SIZE, 1 + HEADER_SIZE + 2 * BORDER_SIZE),
+                BORDER_SIZE,
+            ),
             implem: implementation,
             theme_over_surface,
             maximized: false,
@@ -282,12 +317,14 @@ impl Frame for FallbackFrame {
             pointers: Vec::new(),
             themer,
             surface_version: compositor.as_ref().version(),
+            drawn: None,
         })
     }
 
     fn new_seat(&mut self, seat: &Attached<wl_seat::WlSeat>) {
         use self::wl_pointer::Event;
         let inner = self.inner.clone();
+        let themer = self.themer.clone();
         let pointer = self.themer.theme_pointer_with_impl(
             seat,
             move |event, pointer: ThemedPointer, ddata: DispatchData| {
@@ -296,24 +333,20 @@ impl Frame for FallbackFrame {
                 let mut inner = inner.borrow_mut();
                 match event {
                     Event::Enter { serial, surface, surface_x, surface_y } => {
-                        data.location = precise_location(
-                            inner.find_surface(&surface),
-                            inner.size.0,
-                            surface_x,
-                            surface_y,
-                        );
+                        data.part = inner.find_surface(&surface);
+                        data.location = precise_location(data.part, &inner, surface_x, surface_y);
                         data.position = (surface_x, surface_y);
-                        change_pointer(&pointer, &inner, data.location, Some(serial))
+                        change_pointer(&pointer, &themer, &inner, data.location, Some(serial))
                     }
                     Event::Leave { serial, .. } => {
+                        data.part = Location::None;
                         data.location = Location::None;
-                        change_pointer(&pointer, &inner, data.location, Some(serial));
+                        change_pointer(&pointer, &themer, &inner, data.location, Some(serial));
                         (&mut inner.implem)(FrameRequest::Refresh, 0, ddata);
                     }
                     Event::Motion { surface_x, surface_y, .. } => {
                         data.position = (surface_x, surface_y);
-                        let newpos =
-                            precise_location(data.location, inner.size.0, surface_x, surface_y);
+                        let newpos = precise_location(data.part, &inner, surface_x, surface_y);
                         if newpos != data.location {
                             match (newpos, data.location) {
                                 (Location::Button(_), _) | (_, Location::Button(_)) => {
@@ -325,7 +358,7 @@ impl Frame for FallbackFrame {
                             // we changed of part of the decoration, pointer image
                             // may need to be changed
                             data.location = newpos;
-                            change_pointer(&pointer, &inner, data.location, None)
+                            change_pointer(&pointer, &themer, &inner, data.location, None)
                         }
                     }
                     Event::Button { serial, button, state, .. } => {
@@ -353,6 +386,7 @@ impl Frame for FallbackFrame {
         );
         pointer.as_ref().user_data().set(|| {
             RefCell::new(PointerUserData {
+                part: Location::None,
                 location: Location::None,
                 position: (0.0, 0.0),
                 seat: seat.detach(),
@@ -402,6 +436,7 @@ impl Frame for FallbackFrame {
 
     fn set_hidden(&mut self, hidden: bool) {
         self.hidden = hidden;
+        self.drawn = None;
         let mut inner = self.inner.borrow_mut();
         if !self.hidden {
             if inner.parts.is_empty() {
@@ -424,11 +459,20 @@ impl Frame for FallbackFrame {
     }
 
     fn set_resizable(&mut self, resizable: bool) {
-        self.inner.borrow_mut().resizable = resizable;
+        let mut inner = self.inner.borrow_mut();
+        inner.resizable = resizable;
+        inner.resize_edges.set_resizable(resizable);
+    }
+
+    fn set_size_limits(&mut self, min: Option<(u32, u32)>, max: Option<(u32, u32)>) {
+        self.inner.borrow_mut().resize_edges.set_size_limits(min, max);
     }
 
     fn resize(&mut self, newsize: (u32, u32)) {
-        self.inner.borrow_mut().size = newsize;
+        let mut inner = self.inner.borrow_mut();
+        inner.size = newsize;
+        let (w, h) = newsize;
+        inner.resize_edges.set_size((w + 2 * BORDER_SIZE, h + HEADER_SIZE + 2 * BORDER_SIZE));
     }
 
     fn redraw(&mut self) {
@@ -441,6 +485,7 @@ impl Frame for FallbackFrame {
                 p.surface.attach(None, 0, 0);
                 p.surface.commit();
             }
+            self.drawn = None;
             return;
         }
 
@@ -455,6 +500,45 @@ impl Frame for FallbackFrame {
 
         let (width, height) = inner.size;
 
+        let mouses = self
+            .pointers
+            .iter()
+            .flat_map(|p| {
+                if p.as_ref().is_alive() {
+                    let data: &RefCell<PointerUserData> = p.as_ref().user_data().get().unwrap();
+                    Some(data.borrow().location)
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<Location>>();
+
+        let state = DrawnState {
+            size: inner.size,
+            scales: scales.clone(),
+            active: self.active,
+            resizable: inner.resizable,
+            hovered: [
+                mouses.contains(&Location::Button(UIButton::Close)),
+                mouses.contains(&Location::Button(UIButton::Maximize)),
+                mouses.contains(&Location::Button(UIButton::Minimize)),
+            ],
+        };
+
+        // Only redraw what changed since the last redraw: nothing at all if the state is
+        // the same, and only the header with the damage of the buttons whose hover state
+        // changed if nothing else did.
+        let header_damage = match self.drawn {
+            Some(ref drawn) if *drawn == state => return,
+            Some(ref drawn) if drawn.same_but_hover(&state) => Some(
+                (0..3)
+                    .filter(|&i| drawn.hovered[i] != state.hovered[i])
+                    .map(|i| (width as i32 - (i as i32 + 1) * HEADER_SIZE as i32, 0))
+                    .collect::<Vec<_>>(),
+            ),
+            _ => None,
+        };
+
         // Use header scale for all the thing.
         let header_scale = scales[HEAD];
 
@@ -483,44 +567,39 @@ impl Frame for FallbackFrame {
                     pixel[3] = color[3];
                 }
 
-                draw_buttons(
-                    canvas,
-                    width,
-                    header_scale,
-                    inner.resizable,
-                    self.active,
-                    &self
-                        .pointers
-                        .iter()
-                        .flat_map(|p| {
-                            if p.as_ref().is_alive() {
-                                let data: &RefCell<PointerUserData> =
-                                    p.as_ref().user_data().get().unwrap();
-                                Some(data.borrow().location)
-                            } else {
-                                None
-                            }
-                        })
-                        .collect::<Vec<Location>>(),
-                );
+                draw_buttons(canvas, width, header_scale, inner.resizable, self.active, &mouses);
 
                 parts[HEAD].subsurface.set_position(0, -(HEADER_SIZE as i32));
                 parts[HEAD].surface.attach(Some(&buffer), 0, 0);
-                if self.surface_version >= 4 {
-                    parts[HEAD].surface.damage_buffer(
-                        0,
-                        0,
-                        scaled_header_width as i32,
-                        scaled_header_height as i32,
-                    );
-                } else {
-                    // surface is old and does not support damage_buffer, so we damage
-                    // in surface coordinates and hope it is not rescaled
-                    parts[HEAD].surface.damage(0, 0, width as i32, HEADER_SIZE as i32);
+                match header_damage {
+                    Some(ref buttons) => {
+                        for &(x, y) in buttons {
+                            damage_part(
+                                &parts[HEAD],
+                                self.surface_version,
+                                header_scale,
+                                (x, y),
+                                (HEADER_SIZE, HEADER_SIZE),
+                            );
+                        }
+                    }
+                    None => damage_part(
+                        &parts[HEAD],
+                        self.surface_version,
+                        header_scale,
+                        (0, 0),
+                        (width, HEADER_SIZE),
+                    ),
                 }
                 parts[HEAD].surface.commit();
             }
 
+            if header_damage.is_some() {
+                // The borders did not change
+                self.drawn = Some(state);
+                return;
+            }
+
             // -> top-subsurface
             if let Ok((canvas, buffer)) = self.pool.buffer(
                 ((width + 2 * BORDER_SIZE) * scales[TOP]) as i32,
@@ -539,23 +618,13 @@ impl Frame for FallbackFrame {
                     -(HEADER_SIZE as i32 + BORDER_SIZE as i32),
                 );
                 parts[TOP].surface.attach(Some(&buffer), 0, 0);
-                if self.surface_version >= 4 {
-                    parts[TOP].surface.damage_buffer(
-                        0,
-                        0,
-                        ((width + 2 * BORDER_SIZE) * scales[TOP]) as i32,
-                        (BORDER_SIZE * scales[TOP]) as i32,
-                    );
-                } else {
-                    // surface is old and does not support damage_buffer, so we damage
-                    // in surface coordinates and hope it is not rescaled
-                    parts[TOP].surface.damage(
-                        0,
-                        0,
-                        (width + 2 * BORDER_SIZE) as i32,
-                        BORDER_SIZE as i32,
-                    );
-                }
+                damage_part(
+                    &parts[TOP],
+                    self.surface_version,
+                    scales[TOP],
+                    (0, 0),
+                    (width + 2 * BORDER_SIZE, BORDER_SIZE),
+                );
                 parts[TOP].surface.commit();
             }
 
@@ -574,23 +643,13 @@ impl Frame for FallbackFrame {
                 }
                 parts[BOTTOM].subsurface.set_position(-(BORDER_SIZE as i32), height as i32);
                 parts[BOTTOM].surface.attach(Some(&buffer), 0, 0);
-                if self.surface_version >= 4 {
-                    parts[BOTTOM].surface.damage_buffer(
-                        0,
-                        0,
-                        ((width + 2 * BORDER_SIZE) * scales[BOTTOM]) as i32,
-                        (BORDER_SIZE * scales[BOTTOM]) as i32,
-                    );
-                } else {
-                    // surface is old and does not support damage_buffer, so we damage
-                    // in surface coordinates and hope it is not rescaled
-                    parts[BOTTOM].surface.damage(
-                        0,
-                        0,
-                        (width + 2 * BORDER_SIZE) as i32,
-                        BORDER_SIZE as i32,
-                    );
-                }
+                damage_part(
+                    &parts[BOTTOM],
+                    self.surface_version,
+                    scales[BOTTOM],
+                    (0, 0),
+                    (width + 2 * BORDER_SIZE, BORDER_SIZE),
+                );
                 parts[BOTTOM].surface.commit();
             }
 
@@ -609,23 +668,13 @@ impl Frame for FallbackFrame {
                 }
                 parts[LEFT].subsurface.set_position(-(BORDER_SIZE as i32), -(HEADER_SIZE as i32));
                 parts[LEFT].surface.attach(Some(&buffer), 0, 0);
-                if self.surface_version >= 4 {
-                    parts[LEFT].surface.damage_buffer(
-                        0,
-                        0,
-                        (BORDER_SIZE * scales[LEFT]) as i32,
-                        ((height + HEADER_SIZE) * scales[LEFT]) as i32,
-                    );
-                } else {
-                    // surface is old and does not support damage_buffer, so we damage
-                    // in surface coordinates and hope it is not rescaled
-                    parts[LEFT].surface.damage(
-                        0,
-                        0,
-                        BORDER_SIZE as i32,
-                        (height + HEADER_SIZE) as i32,
-                    );
-                }
+                damage_part(
+                    &parts[LEFT],
+                    self.surface_version,
+                    scales[LEFT],
+                    (0, 0),
+                    (BORDER_SIZE, height + HEADER_SIZE),
+                );
                 parts[LEFT].surface.commit();
             }
 
@@ -644,26 +693,18 @@ impl Frame for FallbackFrame {
                 }
                 parts[RIGHT].subsurface.set_position(width as i32, -(HEADER_SIZE as i32));
                 parts[RIGHT].surface.attach(Some(&buffer), 0, 0);
-                if self.surface_version >= 4 {
-                    parts[RIGHT].surface.damage_buffer(
-                        0,
-                        0,
-                        (BORDER_SIZE * scales[RIGHT]) as i32,
-                        ((height + HEADER_SIZE) * scales[RIGHT]) as i32,
-                    );
-                } else {
-                    // surface is old and does not support damage_buffer, so we damage
-                    // in surface coordinates and hope it is not rescaled
-                    parts[RIGHT].surface.damage(
-                        0,
-                        0,
-                        BORDER_SIZE as i32,
-                        (height + HEADER_SIZE) as i32,
-                    );
-                }
+                damage_part(
+                    &parts[RIGHT],
+                    self.surface_version,
+                    scales[RIGHT],
+                    (0, 0),
+                    (BORDER_SIZE, height + HEADER_SIZE),
+                );
                 parts[RIGHT].surface.commit();
             }
         }
+
+        self.drawn = Some(state);
     }
 
     fn subtract_borders(&self, width: i32, height: i32) -> (i32, i32) {
@@ -705,27 +746,57 @@ impl Drop for FallbackFrame {
     }
 }
 
-fn change_pointer(pointer: &ThemedPointer, inner: &Inner, location: Location, serial: Option<u32>) {
+// Damage a rectangle of a part, given in surface coordinates
+fn damage_part(
+    part: &Part,
+    surface_version: u32,
+    scale: u32,
+    (x, y): (i32, i32),
+    (w, h): (u32, u32),
+) {
+    if surface_version >= 4 {
+        let scale = scale as i32;
+        part.surface.damage_buffer(x * scale, y * scale, w as i32 * scale, h as i32 * scale);
+    } else {
+        // surface is old and does not support damage_buffer, so we damage
+        // in surface coordinates and hope it is not rescaled
+        part.surface.damage(x, y, w as i32, h as i32);
+    }
+}
+
+fn change_pointer(
+    pointer: &ThemedPointer,
+    themer: &ThemeManager,
+    inner: &Inner,
+    location: Location,
+    serial: Option<u32>,
+) {
     // Prevent theming of the surface if it was requested.
     if !inner.theme_over_surface && location == Location::None {
         return;
     }
 
-    let name = match location {
+    let edge = match location {
         // If we can't resize a frame we shouldn't show resize cursors.
-        _ if !inner.resizable => "left_ptr",
-        Location::Top => "top_side",
-        Location::TopRight => "top_right_corner",
-        Location::Right => "right_side",
-        Location::BottomRight => "bottom_right_corner",
-        Location::Bottom => "bottom_side",
-        Location::BottomLeft => "bottom_left_corner",
-        Location::Left => "left_side",
-        Location::TopLeft => "top_left_corner",
-        _ => "left_ptr",
+        _ if !inner.resizable => ResizeEdge::None,
+        Location::Top => ResizeEdge::Top,
+        Location::TopRight => ResizeEdge::TopRight,
+        Location::Right => ResizeEdge::Right,
+        Location::BottomRight => ResizeEdge::BottomRight,
+        Location::Bottom => ResizeEdge::Bottom,
+        Location::BottomLeft => ResizeEdge::BottomLeft,
+        Location::Left => ResizeEdge::Left,
+        Location::TopLeft => ResizeEdge::TopLeft,
+        _ => ResizeEdge::None,
+    };
+    let name = match (resize_cursor(edge), location) {
+        (Some(name), _) => name.to_owned(),
+        // Restore the cursor of the content when the pointer goes back to it
+        (None, Location::None) => themer.content_cursor(),
+        (None, _) => "left_ptr".to_owned(),
     };
 
-    if pointer.set_cursor(name, serial).is_err() {
+    if pointer.set_cursor(&name, serial).is_err() {
         error!("Failed to set cursor");
     }
 }
@@ -0,0 +1,67 @@
+use wayland_client::protocol::wl_keyboard::KeyState;
+
+use crate::seat::keyboard::{keysyms, Event, ModifiersState};
+
+/// A keyboard shortcut opening the window menu
+///
+/// Client-side decorations open the window menu on a right click on the title bar, but
+/// keyboard users need a shortcut for it too. Feed this helper the events of a keyboard,
+/// and when the shortcut is pressed it returns the serial to give to
+/// [`Window::show_window_menu_from_keyboard`](struct.Window.html#method.show_window_menu_from_keyboard).
+///
+/// The default shortcut is Alt+Space.
+#[derive(Debug, Copy, Clone)]
+pub struct WindowMenuShortcut {
+    keysym: u32,
+    modifiers: ModifiersState,
+    current: ModifiersState,
+}
+
+impl WindowMenuShortcut {
+    /// Create the default Alt+Space shortcut
+    pub fn new() -> WindowMenuShortcut {
+        let modifiers = ModifiersState { alt: true, ..Default::default() };
+        WindowMenuShortcut::with_keys(keysyms::XKB_KEY_space, modifiers)
+    }
+
+    /// Create a shortcut with the given key and modifiers
+    ///
+    /// Only the ctrl, alt, shift and logo modifiers are compared, the state of the
+    /// lock keys is ignored.
+    pub fn with_keys(keysym: u32, modifiers: ModifiersState) -> WindowMenuShortcut {
+        WindowMenuShortcut { keysym, modifiers, current: ModifiersState::default() }
+    }
+
+    /// Process a keyboard event
+    ///
+    /// Returns the serial of the key press if it triggered the shortcut.
+    pub fn handle_event(&mut self, event: &Event) -> Option<u32> {
+        match *event {
+            Event::Modifiers { modifiers } => {
+                self.current = modifiers;
+                None
+            }
+            Event::Leave { .. } => {
+                self.current = ModifiersState::default();
+                None
+            }
+            Event::Key { serial, keysym, state: KeyState::Pressed, .. }
+                if keysym == self.keysym && self.modifiers_match() =>
+            {
+                Some(serial)
+            }
+            _ => None,
+        }
+    }
+
+    fn modifiers_match(&self) -> bool {
+        let (a, b) = (&self.modifiers, &self.current);
+        a.ctrl == b.ctrl && a.alt == b.alt && a.shift == b.shift && a.logo == b.logo
+    }
+}
+
+impl Default for WindowMenuShortcut {
+    fn default() -> WindowMenuShortcut {
+        WindowMenuShortcut::new()
+    }
+}
@@ -23,9 +23,27 @@ use crate::{
     shell,
 };
 
+#[cfg(feature = "debug-escape")]
+mod escape;
+#[cfg(feature = "debug-escape")]
+pub use self::escape::EscapeChord;
+
 mod fallback_frame;
 pub use self::fallback_frame::FallbackFrame;
 
+mod menu;
+pub use self::menu::WindowMenuShortcut;
+
+mod resize_edge;
+pub use self::resize_edge::{resize_cursor, ResizeEdgeCalculator};
+
+mod snapshot;
+pub use self::snapshot::{RenderSnapshot, RenderSnapshotHandle};
+
+mod state_change;
+use self::state_change::PendingRequests;
+pub use self::state_change::StateChange;
+
 // Defines the minimum window size. Minimum width is set to 2 pixels to circumvent
 // a bug in mutter - https://gitlab.gnome.org/GNOME/mutter/issues/259
 const MIN_WINDOW_SIZE: (u32, u32) = (2, 1);
@@ -103,6 +121,28 @@ pub enum Event {
     Refresh,
 }
 
+/// The last configuration of a window received from the server
+///
+/// It holds the values of the last [`Event::Configure`](enum.Event.html) given to your
+/// implementation, see [`Window::last_configure`](struct.Window.html#method.last_configure).
+#[derive(Clone, Debug, PartialEq)]
+pub struct WindowConfigure {
+    /// Size of the *inner* surface, if the server suggested one
+    ///
+    /// This is the size after clamping to the size limits of the window, and restoring
+    /// the size the window had before being maximized, fullscreened or tiled.
+    pub new_size: Option<(u32, u32)>,
+    /// Combination of states of the window
+    pub states: Vec<State>,
+}
+
+impl WindowConfigure {
+    /// The size suggested by the server, see the `new_size` field
+    pub fn size(&self) -> Option<crate::geometry::Size<u32>> {
+        self.new_size.map(Into::into)
+    }
+}
+
 /// Possible decoration modes for a Window
 ///
 /// This represents what your application requests from the server.
@@ -132,7 +172,65 @@ struct WindowInner<F> {
     max_size: Option<(u32, u32)>,
     current_size: (u32, u32),
     old_size: Option<(u32, u32)>,
+    states: Vec<State>,
     decorated: bool,
+    resizable: bool,
+    staged: Option<StagedProperties>,
+    last_configure: Option<WindowConfigure>,
+    pending_requests: PendingRequests,
+    state_change_cb: Option<Box<StateChangeCallback>>,
+    zoom: f64,
+}
+
+type StateChangeCallback = dyn FnMut(StateChange, DispatchData);
+
+// Min and max sizes of a window, decorations included
+type SizeLimits = (Option<(i32, i32)>, Option<(i32, i32)>);
+
+impl<F: Frame> WindowInner<F> {
+    // Compute the min/max sizes to send to the server, accounting for the
+    // decorations and for the resizability of the window
+    fn size_limits(&self, frame: &F) -> SizeLimits {
+        if self.resizable {
+            let (minw, minh) = self.min_size;
+            (
+                Some(frame.add_borders(minw as i32, minh as i32)),
+                self.max_size.map(|(w, h)| frame.add_borders(w as i32, h as i32)),
+            )
+        } else {
+            let (w, h) = self.current_size;
+            let size = frame.add_borders(w as i32, h as i32);
+            (Some(size), Some(size))
+        }
+    }
+
+    // Send the properties that were staged before the window was mapped
+    fn flush_staged(&self, staged: StagedProperties, frame: &F) {
+        if let Some(app_id) = staged.app_id {
+            self.shell_surface.set_app_id(app_id);
+        }
+        if let Some(title) = staged.title {
+            self.shell_surface.set_title(title);
+        }
+        if staged.size_limits {
+            let (min_size, max_size) = self.size_limits(frame);
+            self.shell_surface.set_min_size(min_size);
+            self.shell_surface.set_max_size(max_size);
+        }
+    }
+}
+
+/// Window properties set before the window received its first configure
+///
+/// The initial commit of the shell surface is done as soon as it is created,
+/// so these are queued and sent in a fixed order (app id, title, size limits)
+/// right after the first configure is acked, so that they are part of the state
+/// applied by the commit mapping the window.
+#[derive(Debug, Default)]
+struct StagedProperties {
+    title: Option<String>,
+    app_id: Option<String>,
+    size_limits: bool,
 }
 
 impl<F> fmt::Debug for WindowInner<F>
@@ -148,7 +246,14 @@ where
             .field("max_size", &self.max_size)
             .field("current_size", &self.current_size)
             .field("old_size", &self.old_size)
+            .field("states", &self.states)
             .field("decorated", &self.decorated)
+            .field("resizable", &self.resizable)
+            .field("staged", &self.staged)
+            .field("last_configure", &self.last_configure)
+            .field("pending_requests", &self.pending_requests)
+            .field("zoom", &self.zoom)
+            .field("state_change_cb", &self.state_change_cb.as_ref().map(|_| "Fn() -> { ... }"))
             .finish()
     }
 }
@@ -165,6 +270,10 @@ where
 /// their attached buffer, you need to keep the decorations in sync with
 /// your contents via the `resize(..)` method.
 ///
+/// The title, app id and min/max sizes set before the window receives
+/// its first configure event are queued, and sent to the server in a
+/// consistent order just before the window is mapped.
+///
 /// Different kind of decorations can be used by customizing the type
 /// parameter. A few are provided in this crate if the `frames` cargo feature
 /// is enabled, but any type implementing the `Frame` trait can do.
@@ -174,6 +283,7 @@ pub struct Window<F: Frame> {
     decoration: Option<ZxdgToplevelDecorationV1>,
     shell_surface: Arc<Box<dyn shell::ShellSurface>>,
     inner: Rc<RefCell<Option<WindowInner<F>>>>,
+    snapshot: RenderSnapshotHandle,
     _seat_listener: crate::seat::SeatListener,
 }
 
@@ -262,6 +372,12 @@ impl<F: Frame + 'static> Window<F> {
                     shell::Event::Configure { states, mut new_size } => {
                         let mut frame = inner.frame.borrow_mut();
 
+                        // If this is the first configure, send the properties that were set
+                        // before the window was mapped.
+                        if let Some(staged) = inner.staged.take() {
+                            inner.flush_staged(staged, &frame);
+                        }
+
                         // Populate frame changes. We should do it before performing new_size
                         // recalculation, since we should account for a fullscreen state.
                         let need_refresh = frame.set_states(&states);
@@ -314,9 +430,20 @@ impl<F: Frame + 'static> Window<F> {
                             inner.old_size = None;
                         }
 
+                        let state_change =
+                            inner.pending_requests.state_change(&inner.states, &states);
+                        inner.states = states.clone();
+                        inner.last_configure =
+                            Some(WindowConfigure { new_size, states: states.clone() });
+
                         if need_refresh {
                             (inner.user_impl)(Event::Refresh, ddata.reborrow());
                         }
+                        if let (Some(change), Some(cb)) =
+                            (state_change, inner.state_change_cb.as_mut())
+                        {
+                            cb(change, ddata.reborrow());
+                        }
                         (inner.user_impl)(Event::Configure { states, new_size }, ddata);
                     }
                     shell::Event::Close => {
@@ -369,7 +496,14 @@ impl<F: Frame + 'static> Window<F> {
             max_size: None,
             current_size: initial_dims,
             old_size: None,
+            states: Vec::new(),
             decorated: true,
+            resizable: true,
+            staged: Some(StagedProperties::default()),
+            last_configure: None,
+            pending_requests: PendingRequests::default(),
+            state_change_cb: None,
+            zoom: 1.0,
         });
 
         // Setup window decorations if applicable.
@@ -386,6 +520,7 @@ impl<F: Frame + 'static> Window<F> {
             decoration,
             surface,
             inner,
+            snapshot: RenderSnapshotHandle::new(RenderSnapshot::new(initial_dims, 1, &[])),
             _seat_listener: seat_listener,
         };
 
@@ -436,6 +571,55 @@ impl<F: Frame + 'static> Window<F> {
         &self.surface
     }
 
+    /// The raw `wl_surface` pointer of this window, for C and C++ libraries
+    ///
+    /// This is the `struct wl_proxy *` of the surface in `libwayland-client.so`, to give to
+    /// engines doing their own rendering with EGL or Vulkan, along with the pointer of the
+    /// display given by
+    /// [`Display::get_display_ptr`](../reexports/client/struct.Display.html#method.get_display_ptr).
+    /// SCTK keeps managing the window and its decorations.
+    ///
+    /// The pointer is valid as long as the window is alive: the surface is destroyed when the
+    /// window is dropped, so the rendering resources using it (like the `VkSurfaceKHR` or the
+    /// `wl_egl_window`) must be destroyed before that. The surface must not be destroyed or
+    /// committed with a new role from the C side, and its events are still handled by SCTK.
+    ///
+    /// This is only available with the `dlopen` cargo feature, which makes `wayland-client`
+    /// use the system library.
+    #[cfg(feature = "dlopen")]
+    pub fn wl_surface_ptr(&self) -> *mut std::os::raw::c_void {
+        self.surface.as_ref().c_ptr() as *mut std::os::raw::c_void
+    }
+
+    /// The last configuration of the window received from the server
+    ///
+    /// This is `None` until the window received its first configure event.
+    pub fn last_configure(&self) -> Option<WindowConfigure> {
+        self.inner.borrow().as_ref().and_then(|inner| inner.last_configure.clone())
+    }
+
+    /// Set a callback notified of the changes of the maximized and fullscreen states
+    ///
+    /// The callback is invoked with a [`StateChange`](struct.StateChange.html) before the
+    /// `Configure` event applying it, and tells whether the change was requested by the
+    /// application with [`set_maximized`](#method.set_maximized) and friends or initiated
+    /// by the compositor.
+    pub fn set_state_change_callback<C>(&self, callback: C)
+    where
+        C: FnMut(StateChange, DispatchData) + 'static,
+    {
+        if let Some(inner) = self.inner.borrow_mut().as_mut() {
+            inner.state_change_cb = Some(Box::new(callback));
+        }
+    }
+
+    // Remember a state requested by the application
+    fn request_state<G: FnOnce(&mut PendingRequests)>(&self, f: G) {
+        if let Some(inner) = self.inner.borrow_mut().as_mut() {
+            f(&mut inner.pending_requests);
+        }
+    }
+
     /// Refreshes the frame
     ///
     /// Redraws the frame to match its requested state (dimensions, presence/
@@ -469,6 +653,9 @@ impl<F: Frame + 'static> Window<F> {
             title.truncate(new_len);
         }
         self.frame.borrow_mut().set_title(title.clone());
+        if self.stage(|staged| staged.title = Some(title.clone())) {
+            return;
+        }
         self.shell_surface.set_title(title);
     }
 
@@ -480,9 +667,26 @@ impl<F: Frame + 'static> Window<F> {
     /// Several wayland compositors will try to find a `.desktop` file matching this name
     /// to find metadata about your apps.
     pub fn set_app_id(&self, app_id: String) {
+        if self.stage(|staged| staged.app_id = Some(app_id.clone())) {
+            return;
+        }
         self.shell_surface.set_app_id(app_id);
     }
 
+    // Update the properties staged until the window is mapped
+    //
+    // Returns `false` if the window is already mapped, in which case the
+    // property should be sent right away.
+    fn stage<G: FnOnce(&mut StagedProperties)>(&self, f: G) -> bool {
+        match *self.inner.borrow_mut() {
+            Some(WindowInner { staged: Some(ref mut staged), .. }) => {
+                f(staged);
+                true
+            }
+            _ => false,
+        }
+    }
+
     /// Set whether the window should be decorated or not.
     ///
     /// If `zxdg_toplevel_decoration_v1` object is presented and alive, requesting `None`
@@ -571,20 +775,16 @@ impl<F: Frame + 'static> Window<F> {
         frame.set_resizable(resizable);
         let mut inner = self.inner.borrow_mut();
         if let Some(ref mut inner) = *inner {
-            if resizable {
-                // restore the min/max sizes
-                self.shell_surface.set_min_size(
-                    Some(inner.min_size).map(|(w, h)| frame.add_borders(w as i32, h as i32)),
-                );
-                self.shell_surface.set_max_size(
-                    inner.max_size.map(|(w, h)| frame.add_borders(w as i32, h as i32)),
-                );
-            } else {
-                // Lock the min/max sizes to current size.
-                let (w, h) = inner.current_size;
-                self.shell_surface.set_min_size(Some(frame.add_borders(w as i32, h as i32)));
-                self.shell_surface.set_max_size(Some(frame.add_borders(w as i32, h as i32)));
+            // When not resizable, the min/max sizes are locked to the current size,
+            // otherwise the previous min/max sizes are restored.
+            inner.resizable = resizable;
+            if let Some(ref mut staged) = inner.staged {
+                staged.size_limits = true;
+                return;
             }
+            let (min_size, max_size) = inner.size_limits(&frame);
+            self.shell_surface.set_min_size(min_size);
+            self.shell_surface.set_max_size(max_size);
         }
     }
 
@@ -614,11 +814,13 @@ impl<F: Frame + 'static> Window<F> {
 
     /// Request the window to be maximized
     pub fn set_maximized(&self) {
+        self.request_state(|requests| requests.request_maximized(true));
         self.shell_surface.set_maximized();
     }
 
     /// Request the window to be un-maximized
     pub fn unset_maximized(&self) {
+        self.request_state(|requests| requests.request_maximized(false));
         self.shell_surface.unset_maximized();
     }
 
@@ -632,11 +834,13 @@ impl<F: Frame + 'static> Window<F> {
     /// Note: The decorations hiding behavior is `Frame` dependant.
     /// To check whether you need to hide them consult your frame documentation.
     pub fn set_fullscreen(&self, output: Option<&wl_output::WlOutput>) {
+        self.request_state(|requests| requests.request_fullscreen(true));
         self.shell_surface.set_fullscreen(output);
     }
 
     /// Request the window to quit fullscreen mode
     pub fn unset_fullscreen(&self) {
+        self.request_state(|requests| requests.request_fullscreen(false));
         self.shell_surface.unset_fullscreen();
     }
 
@@ -652,13 +856,23 @@ impl<F: Frame + 'static> Window<F> {
     ///
     /// This size is expressed in logical pixels, like the one received
     /// in [`Event::Configure`](enum.Event.html).
+    ///
+    /// With the `strict` cargo feature, a minimum size larger than the maximum size
+    /// is logged and ignored.
     pub fn set_min_size(&mut self, size: Option<(u32, u32)>) {
+        if !self.check_size_limits(size, self.max_size()) {
+            return;
+        }
+        if let Some(ref mut inner) = *self.inner.borrow_mut() {
+            inner.min_size = size.unwrap_or(MIN_WINDOW_SIZE);
+            self.frame.borrow_mut().set_size_limits(Some(inner.min_size), inner.max_size);
+        }
+        if self.stage(|staged| staged.size_limits = true) {
+            return;
+        }
         let (w, h) = size.unwrap_or(MIN_WINDOW_SIZE);
         let (w, h) = self.frame.borrow_mut().add_borders(w as i32, h as i32);
         self.shell_surface.set_min_size(Some((w, h)));
-        if let Some(ref mut inner) = *self.inner.borrow_mut() {
-            inner.min_size = size.unwrap_or(MIN_WINDOW_SIZE)
-        }
     }
 
     /// Sets the maximum possible size for this window
@@ -673,12 +887,45 @@ impl<F: Frame + 'static> Window<F> {
     ///
     /// This size is expressed in logical pixels, like the one received
     /// in [`Event::Configure`](enum.Event.html).
+    ///
+    /// With the `strict` cargo feature, a maximum size smaller than the minimum size
+    /// is logged and ignored.
     pub fn set_max_size(&mut self, size: Option<(u32, u32)>) {
-        let max_size = size.map(|(w, h)| self.frame.borrow_mut().add_borders(w as i32, h as i32));
-        self.shell_surface.set_max_size(max_size);
+        if !self.check_size_limits(self.min_size(), size) {
+            return;
+        }
         if let Some(ref mut inner) = *self.inner.borrow_mut() {
-            inner.max_size = size.map(|(w, h)| (w as u32, h as u32));
+            inner.max_size = size;
+            self.frame.borrow_mut().set_size_limits(Some(inner.min_size), inner.max_size);
         }
+        if self.stage(|staged| staged.size_limits = true) {
+            return;
+        }
+        let max_size = size.map(|(w, h)| self.frame.borrow_mut().add_borders(w as i32, h as i32));
+        self.shell_surface.set_max_size(max_size);
+    }
+
+    fn min_size(&self) -> Option<(u32, u32)> {
+        self.inner.borrow().as_ref().map(|inner| inner.min_size)
+    }
+
+    fn max_size(&self) -> Option<(u32, u32)> {
+        self.inner.borrow().as_ref().and_then(|inner| inner.max_size)
+    }
+
+    // With the `strict` feature, check that the min size is not larger than the max size
+    // on a limited axis, which is a protocol error
+    fn check_size_limits(&self, min: Option<(u32, u32)>, max: Option<(u32, u32)>) -> bool {
+        let (minw, minh) = min.unwrap_or(MIN_WINDOW_SIZE);
+        let (maxw, maxh) = match max {
+            Some(max) => max,
+            None => return true,
+        };
+        crate::strict::check(
+            "xdg_toplevel.set_min_size/set_max_size",
+            (maxw == 0 || minw <= maxw) && (maxh == 0 || minh <= maxh),
+            || format!("min size {}x{} is larger than max size {}x{}", minw, minh, maxw, maxh),
+        )
     }
 
     /// Sets the frame configuration for the window
@@ -703,6 +950,135 @@ impl<F: Frame + 'static> Window<F> {
     pub fn start_interactive_move(&self, seat: &wl_seat::WlSeat, serial: u32) {
         self.shell_surface.move_(seat, serial);
     }
+
+    /// Show the window menu
+    ///
+    /// The window menu is provided by the compositor, and contains actions like
+    /// maximizing or moving the window. It is shown at the given position, relative to the
+    /// top-left corner of the content of the window.
+    ///
+    /// This request must be used in response to a user action, whose serial is passed.
+    pub fn show_window_menu(&self, seat: &wl_seat::WlSeat, serial: u32, x: i32, y: i32) {
+        self.shell_surface.show_window_menu(seat, serial, x, y);
+    }
+
+    /// Show the window menu in response to a keyboard shortcut
+    ///
+    /// The menu is shown at the top-left corner of the content of the window. See
+    /// [`WindowMenuShortcut`](struct.WindowMenuShortcut.html) to detect the shortcut.
+    pub fn show_window_menu_from_keyboard(&self, seat: &wl_seat::WlSeat, serial: u32) {
+        self.show_window_menu(seat, serial, 0, 0);
+    }
+
+    /// Create a debugging escape hatch closing this window
+    ///
+    /// Holding Ctrl+Alt+Shift+Escape for 3 seconds sends an `Event::Close` to this window.
+    /// See [`EscapeChord`](struct.EscapeChord.html). This is only available with the
+    /// `debug-escape` cargo feature.
+    #[cfg(feature = "debug-escape")]
+    pub fn escape_chord(&self) -> EscapeChord<F> {
+        let modifiers = crate::seat::keyboard::ModifiersState {
+            ctrl: true,
+            alt: true,
+            shift: true,
+            ..Default::default()
+        };
+        self.escape_chord_with_keys(
+            crate::seat::keyboard::keysyms::XKB_KEY_Escape,
+            modifiers,
+            std::time::Duration::from_secs(3),
+        )
+    }
+
+    /// Create a debugging escape hatch closing this window, with a custom chord
+    ///
+    /// Holding the key with the given modifiers for `hold` sends an `Event::Close` to this
+    /// window. Only the ctrl, alt, shift and logo modifiers are compared. This is only
+    /// available with the `debug-escape` cargo feature.
+    #[cfg(feature = "debug-escape")]
+    pub fn escape_chord_with_keys(
+        &self,
+        keysym: u32,
+        modifiers: crate::seat::keyboard::ModifiersState,
+        hold: std::time::Duration,
+    ) -> EscapeChord<F> {
+        EscapeChord::new(&self.inner, keysym, modifiers, hold)
+    }
+
+    /// Set the zoom of the content of this window
+    ///
+    /// This is a multiplier applied on top of the scale factor of the surface, for example to
+    /// implement an accessibility zoom. The buffers keep the size given by the scale factor,
+    /// the content is drawn larger instead: lay it out in the
+    /// [`layout_size`](#method.layout_size) of the window, and draw it with
+    /// [`effective_scale`](#method.effective_scale) buffer pixels per layout unit.
+    ///
+    /// The default zoom is `1.0`, invalid values (not finite or not positive) are ignored.
+    pub fn set_zoom(&self, zoom: f64) {
+        if !(zoom.is_finite() && zoom > 0.0) {
+            log::warn!("[SCTK] Ignoring invalid window zoom {}.", zoom);
+            return;
+        }
+        if let Some(inner) = self.inner.borrow_mut().as_mut() {
+            inner.zoom = zoom;
+        }
+    }
+
+    /// The zoom of the content of this window
+    pub fn zoom(&self) -> f64 {
+        self.inner.borrow().as_ref().map(|inner| inner.zoom).unwrap_or(1.0)
+    }
+
+    /// The scale the content of this window should be rendered at
+    ///
+    /// This is the scale factor of the surface, given by the outputs it is displayed on,
+    /// multiplied by the [`zoom`](#method.zoom) of the window: the number of buffer pixels
+    /// per unit of the [`layout_size`](#method.layout_size).
+    ///
+    /// The size of the buffers only depends on the scale factor, which is also the buffer
+    /// scale to set on the surface.
+    pub fn effective_scale(&self) -> f64 {
+        let scale_factor = crate::surface::try_surface_scale_factor(&self.surface).unwrap_or(1);
+        f64::from(scale_factor) * self.zoom()
+    }
+
+    /// The size available to the layout of the content of this window
+    ///
+    /// This is the size of the window divided by its [`zoom`](#method.zoom): a zoom of `2.0`
+    /// fits half as much content in the window, each unit of layout being drawn with
+    /// [`effective_scale`](#method.effective_scale) buffer pixels.
+    pub fn layout_size(&self) -> (f64, f64) {
+        match self.inner.borrow().as_ref() {
+            Some(inner) => {
+                let (w, h) = inner.current_size;
+                (f64::from(w) / inner.zoom, f64::from(h) / inner.zoom)
+            }
+            None => (0.0, 0.0),
+        }
+    }
+
+    /// Get a handle to the render snapshots of this window
+    ///
+    /// The handle can be sent to a render thread, which reads the last snapshot published
+    /// with [`publish_render_snapshot`](#method.publish_render_snapshot) from it.
+    pub fn render_snapshot_handle(&self) -> RenderSnapshotHandle {
+        self.snapshot.clone()
+    }
+
+    /// Publish a new render snapshot of this window
+    ///
+    /// This copies the current size, scale factor and states of the window into its
+    /// [`RenderSnapshotHandle`](struct.RenderSnapshotHandle.html). You should call it once
+    /// after each dispatch of the event queue, once you have handled the configure events
+    /// and resized the window.
+    pub fn publish_render_snapshot(&self) {
+        let scale_factor = crate::surface::try_surface_scale_factor(&self.surface).unwrap_or(1);
+        let snapshot = match *self.inner.borrow() {
+            Some(ref inner) => RenderSnapshot::new(inner.current_size, scale_factor, &inner.states),
+            None => return,
+        };
+        self.snapshot.publish(snapshot);
+    }
 }
 
 impl<F: Frame> Drop for Window<F> {
@@ -722,6 +1098,7 @@ where
             .field("decoration", &self.decoration)
             .field("shell_surface", &self.shell_surface)
             .field("inner", &self.inner)
+            .field("snapshot", &self.snapshot)
             .field("_seat_listener", &self._seat_listener)
             .finish()
     }
@@ -789,6 +1166,13 @@ pub trait Frame: Sized {
     /// Set whether interactive resize hints should be displayed
     /// and reacted to
     fn set_resizable(&mut self, resizable: bool);
+    /// Notify the frame of the min and max sizes of the window
+    ///
+    /// These are interior sizes, not counting decorations. A frame may use
+    /// them to stop suggesting resizing along an axis on which both are equal.
+    ///
+    /// Calling this should *not* trigger a redraw
+    fn set_size_limits(&mut self, _min: Option<(u32, u32)>, _max: Option<(u32, u32)>) {}
     /// Notify that a new wl_seat should be handled
     ///
     /// This seat is guaranteed to have pointer capability
@@ -0,0 +1,149 @@
+use wayland_protocols::xdg_shell::client::xdg_toplevel::ResizeEdge;
+
+/// Helper computing the resize edge matching a position in a window
+///
+/// This is what a `Frame` uses to decide which edge an interactive resize
+/// should be started from when the user grabs its borders, you can also use it
+/// if you draw your own decorations or resize handles.
+///
+/// Positions are expressed in logical pixels, relative to the top-left corner of
+/// the whole window area (decorations included), which is `width` by `height`.
+///
+/// The calculator accounts for:
+///
+/// - the thickness of the borders, positions further inside the window are not
+///   on any edge
+/// - the size of the corners, positions on a border close enough to a corner
+///   resize along both axes
+/// - right-to-left layouts, where the horizontal coordinate is measured from the
+///   right edge of the window
+/// - disabled axes, if the minimum and maximum sizes are equal on an axis the
+///   window cannot be resized along it, and if the window is not resizable at all
+///   no edge is ever returned
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ResizeEdgeCalculator {
+    size: (u32, u32),
+    border: u32,
+    corner: u32,
+    rtl: bool,
+    resizable: bool,
+    horizontal: bool,
+    vertical: bool,
+}
+
+impl ResizeEdgeCalculator {
+    /// Create a calculator for a window area of given size and border thickness
+    ///
+    /// Corners are initially as large as the borders are thick.
+    pub fn new(size: (u32, u32), border: u32) -> ResizeEdgeCalculator {
+        ResizeEdgeCalculator {
+            size,
+            border,
+            corner: border,
+            rtl: false,
+            resizable: true,
+            horizontal: true,
+            vertical: true,
+        }
+    }
+
+    /// Set the size of the window area
+    pub fn set_size(&mut self, size: (u32, u32)) {
+        self.size = size;
+    }
+
+    /// Set the distance from a corner under which a position on a border is considered
+    /// to be on this corner
+    pub fn set_corner_size(&mut self, corner: u32) {
+        self.corner = corner;
+    }
+
+    /// Set whether horizontal positions are measured from the right edge of the window
+    pub fn set_right_to_left(&mut self, rtl: bool) {
+        self.rtl = rtl;
+    }
+
+    /// Set whether the window can be resized at all
+    pub fn set_resizable(&mut self, resizable: bool) {
+        self.resizable = resizable;
+    }
+
+    /// Set the minimum and maximum sizes of the window
+    ///
+    /// An axis on which both sizes are set and equal is disabled. Like for the
+    /// `xdg_toplevel` requests, a size of `0` on an axis means it is not limited.
+    pub fn set_size_limits(&mut self, min: Option<(u32, u32)>, max: Option<(u32, u32)>) {
+        let (minw, minh) = min.unwrap_or((0, 0));
+        let (maxw, maxh) = max.unwrap_or((0, 0));
+        self.horizontal = maxw == 0 || minw != maxw;
+        self.vertical = maxh == 0 || minh != maxh;
+    }
+
+    /// Compute the edge matching given position
+    ///
+    /// Returns `ResizeEdge::None` if the position is not on a border, or if the
+    /// window cannot be resized from there.
+    pub fn edge(&self, x: f64, y: f64) -> ResizeEdge {
+        if !self.resizable {
+            return ResizeEdge::None;
+        }
+        let (w, h) = (f64::from(self.size.0), f64::from(self.size.1));
+        if x < 0.0 || y < 0.0 || x >= w || y >= h {
+            return ResizeEdge::None;
+        }
+        let x = if self.rtl { w - x } else { x };
+        let (border, corner) = (f64::from(self.border), f64::from(self.corner));
+
+        let on_left = x < border;
+        let on_right = x >= w - border;
+        let on_top = y < border;
+        let on_bottom = y >= h - border;
+        if !(on_left || on_right || on_top || on_bottom) {
+            return ResizeEdge::None;
+        }
+
+        // Positions on a border close enough to a corner are on this corner
+        let left = self.horizontal && (on_left || ((on_top || on_bottom) && x < corner));
+        let right = self.horizontal && (on_right || ((on_top || on_bottom) && x >= w - corner));
+        let top = self.vertical && (on_top || ((on_left || on_right) && y < corner));
+        let bottom = self.vertical && (on_bottom || ((on_left || on_right) && y >= h - corner));
+
+        match (top, bottom, left, right) {
+            (true, _, true, _) => ResizeEdge::TopLeft,
+            (true, _, _, true) => ResizeEdge::TopRight,
+            (_, true, true, _) => ResizeEdge::BottomLeft,
+            (_, true, _, true) => ResizeEdge::BottomRight,
+            (true, _, _, _) => ResizeEdge::Top,
+            (_, true, _, _) => ResizeEdge::Bottom,
+            (_, _, true, _) => ResizeEdge::Left,
+            (_, _, _, true) => ResizeEdge::Right,
+            _ => ResizeEdge::None,
+        }
+    }
+
+    /// Compute the name of the cursor to show at given position
+    ///
+    /// Returns `None` if the position is not on a border, in which case the cursor
+    /// of whatever is under the pointer should be shown. See [`resize_cursor`](fn.resize_cursor.html).
+    pub fn cursor(&self, x: f64, y: f64) -> Option<&'static str> {
+        resize_cursor(self.edge(x, y))
+    }
+}
+
+/// Get the name of the cursor matching a resize edge
+///
+/// These are the names of the resize cursors of the usual cursor themes, returns `None`
+/// for `ResizeEdge::None`.
+pub fn resize_cursor(edge: ResizeEdge) -> Option<&'static str> {
+    match edge {
+        ResizeEdge::Top => Some("top_side"),
+        ResizeEdge::TopRight => Some("top_right_corner"),
+        ResizeEdge::Right => Some("right_side"),
+        ResizeEdge::BottomRight => Some("bottom_right_corner"),
+        ResizeEdge::Bottom => Some("bottom_side"),
+        ResizeEdge::BottomLeft => Some("bottom_left_corner"),
+        ResizeEdge::Left => Some("left_side"),
+        ResizeEdge::TopLeft => Some("top_left_corner"),
+        _ => None,
+    }
+}
@@ -0,0 +1,132 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex,
+};
+
+use super::State;
+
+/// A copy of the state of a window a render thread needs
+///
+/// It is plain data, so that a render thread can use it without ever touching the
+/// SCTK state, which is only accessed while dispatching events. See
+/// [`Window::publish_render_snapshot`](struct.Window.html#method.publish_render_snapshot).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RenderSnapshot {
+    /// Inner size of the window, in logical pixels
+    pub size: (u32, u32),
+    /// Scale factor of the window surface
+    ///
+    /// Always 1 if the surface was not created through SCTK.
+    pub scale_factor: i32,
+    /// Whether the window is maximized
+    pub maximized: bool,
+    /// Whether the window is fullscreen
+    pub fullscreen: bool,
+    /// Whether the window is being interactively resized
+    pub resizing: bool,
+    /// Whether the window is active
+    pub activated: bool,
+    /// Whether the window is tiled on any side
+    pub tiled: bool,
+    /// Whether the whole window needs to be redrawn
+    ///
+    /// This is set when the size or scale factor changed since the last snapshot read
+    /// through the same handle, even if the snapshots in between were never read, in which
+    /// case the contents of previous buffers cannot be reused. It is also set for the first
+    /// snapshot read through a handle.
+    pub full_damage: bool,
+    /// Counter incremented whenever a published snapshot differs from the previous one
+    ///
+    /// A render thread can compare it with the one of the last snapshot it rendered to
+    /// skip redundant redraws.
+    pub generation: u64,
+}
+
+impl RenderSnapshot {
+    pub(crate) fn new(size: (u32, u32), scale_factor: i32, states: &[State]) -> RenderSnapshot {
+        let has = |state: State| states.contains(&state);
+        RenderSnapshot {
+            size,
+            scale_factor,
+            maximized: has(State::Maximized),
+            fullscreen: has(State::Fullscreen),
+            resizing: has(State::Resizing),
+            activated: has(State::Activated),
+            tiled: has(State::TiledLeft)
+                || has(State::TiledRight)
+                || has(State::TiledTop)
+                || has(State::TiledBottom),
+            full_damage: true,
+            generation: 0,
+        }
+    }
+
+    // Compare ignoring the bookkeeping fields
+    fn same_state(&self, other: &RenderSnapshot) -> bool {
+        RenderSnapshot { full_damage: false, generation: 0, ..*self }
+            == RenderSnapshot { full_damage: false, generation: 0, ..*other }
+    }
+}
+
+#[derive(Debug)]
+struct Published {
+    snapshot: RenderSnapshot,
+    // the generation of the last snapshot whose size or scale factor changed
+    resized: u64,
+}
+
+/// A shared handle to the last published `RenderSnapshot` of a window
+///
+/// It can be cloned and sent to other threads. Reading it only locks the snapshot
+/// for the duration of a copy.
+///
+/// Each handle tracks the last snapshot read through it, to compute its
+/// [`full_damage`](struct.RenderSnapshot.html#structfield.full_damage). A clone starts
+/// without any snapshot read, so each render thread should use its own clone.
+#[derive(Debug)]
+pub struct RenderSnapshotHandle {
+    inner: Arc<Mutex<Published>>,
+    // the generation of the last snapshot read through this handle plus one, 0 if none was
+    last_read: AtomicU64,
+}
+
+impl RenderSnapshotHandle {
+    pub(crate) fn new(initial: RenderSnapshot) -> RenderSnapshotHandle {
+        RenderSnapshotHandle {
+            inner: Arc::new(Mutex::new(Published { snapshot: initial, resized: 0 })),
+            last_read: AtomicU64::new(0),
+        }
+    }
+
+    /// Get a copy of the last published snapshot
+    pub fn get(&self) -> RenderSnapshot {
+        let published = self.inner.lock().unwrap();
+        let mut snapshot = published.snapshot;
+        let last_read = self.last_read.swap(snapshot.generation + 1, Ordering::AcqRel);
+        snapshot.full_damage = last_read == 0 || published.resized >= last_read;
+        snapshot
+    }
+
+    // Replace the published snapshot, updating the bookkeeping fields
+    pub(crate) fn publish(&self, mut snapshot: RenderSnapshot) {
+        let mut published = self.inner.lock().unwrap();
+        if published.snapshot.same_state(&snapshot) {
+            return;
+        }
+        let current = &published.snapshot;
+        let resized =
+            current.size != snapshot.size || current.scale_factor != snapshot.scale_factor;
+        snapshot.generation = current.generation + 1;
+        snapshot.full_damage = resized;
+        if resized {
+            published.resized = snapshot.generation;
+        }
+        published.snapshot = snapshot;
+    }
+}
+
+impl Clone for RenderSnapshotHandle {
+    fn clone(&self) -> RenderSnapshotHandle {
+        RenderSnapshotHandle { inner: self.inner.clone(), last_read: AtomicU64::new(0) }
+    }
+}
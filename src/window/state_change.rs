@@ -0,0 +1,103 @@
+use super::State;
+
+/// A change of the maximized or fullscreen state of a window
+///
+/// This is given to the callback set with
+/// [`Window::set_state_change_callback`](struct.Window.html#method.set_state_change_callback)
+/// when a configure event maximizes, un-maximizes, fullscreens or un-fullscreens the window,
+/// before the matching [`Event::Configure`](enum.Event.html).
+///
+/// The compositor can change these states on its own, for example with a keyboard shortcut
+/// or when the title bar of server-side decorations is double-clicked. Such changes are
+/// reported with `requested` set to `false`, so that applications can save and restore their
+/// own geometry and keep their UI toggles in sync with the real state of the window.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StateChange {
+    /// The states of the window before the change
+    pub previous: Vec<State>,
+    /// The states of the window after the change
+    pub states: Vec<State>,
+    /// The new maximized state, if it changed
+    pub maximized: Option<bool>,
+    /// The new fullscreen state, if it changed
+    pub fullscreen: Option<bool>,
+    /// Whether all the changes were requested by the application
+    ///
+    /// This is `false` if any of them was initiated by the compositor, or by the user through
+    /// the buttons of client-side decorations.
+    pub requested: bool,
+}
+
+// The maximized and fullscreen states requested by the application and not yet applied by
+// the compositor
+#[derive(Debug, Default)]
+pub(super) struct PendingRequests {
+    maximized: Option<bool>,
+    fullscreen: Option<bool>,
+}
+
+impl PendingRequests {
+    pub(super) fn request_maximized(&mut self, maximized: bool) {
+        self.maximized = Some(maximized);
+    }
+
+    pub(super) fn request_fullscreen(&mut self, fullscreen: bool) {
+        self.fullscreen = Some(fullscreen);
+    }
+
+    // Compute the change between two sets of states, consuming the requests it fulfills
+    pub(super) fn state_change(
+        &mut self,
+        previous: &[State],
+        states: &[State],
+    ) -> Option<StateChange> {
+        let maximized = transition(previous, states, State::Maximized);
+        let fullscreen = transition(previous, states, State::Fullscreen);
+        let maximized_requested = consume(&mut self.maximized, maximized);
+        let fullscreen_requested = consume(&mut self.fullscreen, fullscreen);
+
+        // A request is dropped once the window is in the requested state, even if it was
+        // already in it or the compositor changed it on its own.
+        let is_maximized = states.contains(&State::Maximized);
+        let is_fullscreen = states.contains(&State::Fullscreen);
+        if self.maximized == Some(is_maximized) {
+            self.maximized = None;
+        }
+        if self.fullscreen == Some(is_fullscreen) {
+            self.fullscreen = None;
+        }
+
+        if maximized.is_none() && fullscreen.is_none() {
+            return None;
+        }
+        Some(StateChange {
+            previous: previous.to_vec(),
+            states: states.to_vec(),
+            maximized,
+            fullscreen,
+            requested: maximized_requested && fullscreen_requested,
+        })
+    }
+}
+
+// The new value of a state, if it changed
+fn transition(previous: &[State], states: &[State], state: State) -> Option<bool> {
+    let (was, is) = (previous.contains(&state), states.contains(&state));
+    if was != is {
+        Some(is)
+    } else {
+        None
+    }
+}
+
+// Whether a transition was requested, consuming the request
+fn consume(request: &mut Option<bool>, transition: Option<bool>) -> bool {
+    match transition {
+        Some(value) if *request == Some(value) => {
+            *request = None;
+            true
+        }
+        Some(_) => false,
+        None => true,
+    }
+}
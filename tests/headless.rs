@@ -0,0 +1,79 @@
+//! End to end tests against a headless compositor
+//!
+//! These tests need the `test-harness` cargo feature, and are skipped if neither Weston nor
+//! Sway is installed.
+
+extern crate smithay_client_toolkit as sctk;
+
+use std::{cell::Cell, rc::Rc, time::Duration};
+
+use sctk::{
+    shm::Format,
+    test_harness::{dispatch_until, HeadlessCompositor},
+    window::{Event as WEvent, FallbackFrame},
+};
+
+sctk::default_environment!(TestEnv, desktop);
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+fn spawn() -> Option<HeadlessCompositor> {
+    let compositor = HeadlessCompositor::spawn().expect("Failed to start the compositor");
+    if compositor.is_none() {
+        eprintln!("No headless compositor is installed, skipping the test");
+    }
+    compositor
+}
+
+#[test]
+fn binds_the_core_globals() {
+    let compositor = match spawn() {
+        Some(compositor) => compositor,
+        None => return,
+    };
+    let display = compositor.connect().unwrap();
+    let mut queue = display.create_event_queue();
+    let env = sctk::new_default_environment!(TestEnv, desktop, with = (display, queue)).unwrap();
+
+    assert!(env
+        .get_global::<sctk::reexports::client::protocol::wl_compositor::WlCompositor>()
+        .is_some());
+    assert!(env.get_shell().is_some());
+    assert!(env.shm_formats().contains(&Format::Argb8888));
+}
+
+#[test]
+fn maps_a_window() {
+    let compositor = match spawn() {
+        Some(compositor) => compositor,
+        None => return,
+    };
+    let display = compositor.connect().unwrap();
+    let mut queue = display.create_event_queue();
+    let env =
+        sctk::new_default_environment!(TestEnv, desktop, with = (display.clone(), queue)).unwrap();
+
+    let configured = Rc::new(Cell::new(false));
+    let my_configured = configured.clone();
+    let surface = env.create_surface().detach();
+    let window = env
+        .create_window::<FallbackFrame, _>(surface, None, (64, 64), move |event, _| {
+            if let WEvent::Configure { .. } = event {
+                my_configured.set(true);
+            }
+        })
+        .unwrap();
+    window.surface().commit();
+
+    assert!(dispatch_until(&mut queue, TIMEOUT, || configured.get()).unwrap());
+
+    let mut pool = env.create_auto_pool().unwrap();
+    let (canvas, buffer) = pool.buffer(64, 64, 256, Format::Argb8888).unwrap();
+    for pixel in canvas.chunks_exact_mut(4) {
+        pixel.copy_from_slice(&[0x80, 0x80, 0x80, 0xFF]);
+    }
+    window.surface().attach(Some(&buffer), 0, 0);
+    window.surface().damage_buffer(0, 0, 64, 64);
+    window.surface().commit();
+    queue.sync_roundtrip(&mut (), |_, _, _| unreachable!()).unwrap();
+}